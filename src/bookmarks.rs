@@ -0,0 +1,191 @@
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{self, Path, PathBuf},
+};
+
+use crate::{
+    popup::Popup,
+    terminal::{Position, Size, TResult},
+    theme::Theme,
+};
+use vyse_core::{
+    paths::{self, PathDisplay},
+    persistence,
+};
+
+/// The first line of every bookmarks file, to detect files written by an incompatible version.
+const BOOKMARKS_FILE_HEADER: &str = "vyse-bookmarks 1";
+
+/// A line marked to come back to, optionally with a note describing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    /// The absolute path of the file.
+    pub path: PathBuf,
+    pub row: usize,
+    pub note: String,
+}
+
+/// The bookmarks of a project, stored in the cache directory.
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    pub entries: Vec<Bookmark>,
+    /// The directory the bookmarks belong to.
+    pub project_root: PathBuf,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks of the project containing the given directory.
+    pub fn load(working_dir: &Path) -> Self {
+        let project_root =
+            paths::find_project_root(working_dir).unwrap_or_else(|| working_dir.to_path_buf());
+        let entries = get_bookmarks_path(&project_root)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .filter(|content| content.lines().next() == Some(BOOKMARKS_FILE_HEADER))
+            .map(|content| content.lines().skip(1).filter_map(parse_bookmark).collect())
+            .unwrap_or_default();
+
+        Self {
+            entries,
+            project_root,
+        }
+    }
+
+    /// Writes the bookmarks to the cache directory.
+    pub fn save(&self) -> io::Result<()> {
+        let bookmarks_path =
+            get_bookmarks_path(&self.project_root).ok_or(io::ErrorKind::NotFound)?;
+        if let Some(parent) = bookmarks_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut writer = BufWriter::new(fs::File::create(bookmarks_path)?);
+        writeln!(writer, "{BOOKMARKS_FILE_HEADER}")?;
+
+        for bookmark in &self.entries {
+            writeln!(
+                writer,
+                "{} {}\t{}",
+                bookmark.row,
+                bookmark.path.display(),
+                bookmark.note
+            )?;
+        }
+
+        writer.flush()
+    }
+
+    /// Returns the bookmark on a line of a file.
+    pub fn get(&self, path: &Path, row: usize) -> Option<&Bookmark> {
+        let path = path::absolute(path).ok()?;
+        self.entries
+            .iter()
+            .find(|bookmark| bookmark.path == path && bookmark.row == row)
+    }
+
+    /// Marks a line of a file, replacing the note of an existing bookmark on it.
+    pub fn set(&mut self, path: &Path, row: usize, note: String) {
+        let Ok(path) = path::absolute(path) else {
+            return;
+        };
+
+        match self
+            .entries
+            .iter_mut()
+            .find(|bookmark| bookmark.path == path && bookmark.row == row)
+        {
+            Some(bookmark) => bookmark.note = note,
+            None => self.entries.push(Bookmark { path, row, note }),
+        }
+    }
+
+    /// Removes the bookmark on a line of a file, returns whether there was one.
+    pub fn remove(&mut self, path: &Path, row: usize) -> bool {
+        let Ok(path) = path::absolute(path) else {
+            return false;
+        };
+
+        let count = self.entries.len();
+        self.entries
+            .retain(|bookmark| bookmark.path != path || bookmark.row != row);
+
+        self.entries.len() != count
+    }
+}
+
+/// Returns the file the bookmarks of a project are stored in.
+fn get_bookmarks_path(project_root: &Path) -> Option<PathBuf> {
+    Some(
+        persistence::get_cache_dir()?
+            .join("bookmarks")
+            .join(persistence::get_path_key(project_root)),
+    )
+}
+
+/// Parses a line of a bookmarks file, `<row> <path>\t<note>`.
+fn parse_bookmark(line: &str) -> Option<Bookmark> {
+    let (row, rest) = line.split_once(' ')?;
+    let (path, note) = rest.split_once('\t')?;
+
+    Some(Bookmark {
+        path: PathBuf::from(path),
+        row: row.parse().ok()?,
+        note: note.to_string(),
+    })
+}
+
+/// A list of the bookmarks of the project to jump to, shown at the top of the screen.
+#[derive(Debug, Default)]
+pub struct BookmarkPicker {
+    pub selected: usize,
+    pub scroll: usize,
+}
+
+impl BookmarkPicker {
+    /// Moves the selection by the given number of bookmarks, stopping at either end.
+    pub fn move_selection(&mut self, delta: isize, bookmarks: &Bookmarks) {
+        let last = bookmarks.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// Renders the bookmarks as `path:line  note`.
+    pub fn render(&mut self, bookmarks: &Bookmarks, screen: Size, theme: &Theme) -> TResult<()> {
+        let lines: Vec<String> = if bookmarks.entries.is_empty() {
+            vec![" no bookmarks, add one with `mark [note]`".to_string()]
+        } else {
+            bookmarks
+                .entries
+                .iter()
+                .map(|bookmark| {
+                    format!(
+                        " {}:{}  {}",
+                        PathDisplay::Relative.format(&bookmark.path, &bookmarks.project_root),
+                        bookmark.row + 1,
+                        bookmark.note
+                    )
+                })
+                .collect()
+        };
+
+        let height = lines.len().min(screen.height.saturating_sub(1) as usize);
+
+        // Keep the selected bookmark visible.
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+
+        let popup = Popup {
+            position: Position { x: 0, y: 0 },
+            size: Size {
+                width: screen.width,
+                #[allow(clippy::cast_possible_truncation)]
+                height: height as u16,
+            },
+        };
+
+        let selected = (!bookmarks.entries.is_empty()).then(|| self.selected - self.scroll);
+        popup.render(&lines[self.scroll..], selected, theme)
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    collections::VecDeque,
+    env,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    popup::Popup,
+    terminal::{Position, Size, TResult},
+    theme::Theme,
+};
+
+/// The number of copied and deleted texts that are kept.
+const MAX_HISTORY: usize = 32;
+/// The width of the list of the clipboard picker, the preview takes the rest of the screen.
+const PICKER_LIST_WIDTH: u16 = 40;
+
+/// The system clipboard, falling back to a clipboard inside the editor when the system one cannot
+/// be reached, e.g. without a display server.
+#[derive(Debug, Default)]
+pub struct Clipboard {
+    /// The texts copied, cut or deleted, newest first, like a kill ring. The newest one is
+    /// pasted when the system clipboard cannot be read.
+    pub history: VecDeque<String>,
+    /// The text last copied as a whole line without a selection, which is pasted above the
+    /// current line rather than at the cursor.
+    pub copied_line: Option<String>,
+}
+
+impl Clipboard {
+    /// Copies text to the system clipboard, returns whether it was reached.
+    pub fn copy(&mut self, text: &str) -> bool {
+        self.remember(text);
+        self.copied_line = None;
+
+        get_copy_commands()
+            .iter()
+            .any(|command| run_with_input(command, text))
+    }
+
+    /// Adds text to the history without putting it on the system clipboard, e.g. deleted text.
+    pub fn remember(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        self.history.retain(|entry| entry != text);
+        self.history.push_front(text.to_string());
+        self.history.truncate(MAX_HISTORY);
+    }
+
+    /// Returns the text on the system clipboard, or the text last copied in the editor if the
+    /// system clipboard cannot be read.
+    pub fn paste(&self) -> Option<String> {
+        get_paste_commands()
+            .iter()
+            .find_map(|command| run(command))
+            .or_else(|| self.history.front().cloned())
+    }
+}
+
+/// A list of the texts in the clipboard history to paste one of them, shown at the top of the
+/// screen next to a preview of the selected text.
+#[derive(Debug, Default)]
+pub struct ClipboardPicker {
+    pub selected: usize,
+    pub scroll: usize,
+}
+
+impl ClipboardPicker {
+    /// Moves the selection by the given number of entries, stopping at either end.
+    pub fn move_selection(&mut self, delta: isize, clipboard: &Clipboard) {
+        let last = clipboard.history.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// Renders the entries as their first line, followed by the selected entry in full.
+    pub fn render(&mut self, clipboard: &Clipboard, screen: Size, theme: &Theme) -> TResult<()> {
+        let lines: Vec<String> = if clipboard.history.is_empty() {
+            vec![" nothing copied or deleted yet".to_string()]
+        } else {
+            clipboard
+                .history
+                .iter()
+                .enumerate()
+                .map(|(idx, text)| {
+                    let line_count = text.lines().count();
+                    let first_line = text.lines().next().unwrap_or_default().trim();
+                    if line_count > 1 {
+                        format!(" {idx:>2}  {first_line} (+{} lines)", line_count - 1)
+                    } else {
+                        format!(" {idx:>2}  {first_line}")
+                    }
+                })
+                .collect()
+        };
+
+        let height = lines.len().min(screen.height.saturating_sub(1) as usize);
+
+        // Keep the selected entry visible.
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+
+        let list = Popup {
+            position: Position { x: 0, y: 0 },
+            size: Size {
+                width: PICKER_LIST_WIDTH.min(screen.width),
+                #[allow(clippy::cast_possible_truncation)]
+                height: height as u16,
+            },
+        };
+        let selected = (!clipboard.history.is_empty()).then(|| self.selected - self.scroll);
+        list.render(&lines[self.scroll..], selected, theme)?;
+
+        if let (Some(text), Some(preview)) = (
+            clipboard.history.get(self.selected),
+            list.beside(screen.width, screen),
+        ) {
+            let preview_lines: Vec<String> = text
+                .replace('\t', "    ")
+                .lines()
+                .map(|line| format!(" {line}"))
+                .collect();
+            preview.render(&preview_lines, None, theme)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the primary selection, i.e. the text last selected with the mouse in any application.
+///
+/// The selection is owned by the display server, so it is read through `wl-paste` on Wayland
+/// and `xclip` or `xsel` on X11.
+pub fn read_primary_selection() -> Option<String> {
+    let commands: &[&[&str]] = if env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[&["wl-paste", "--primary", "--no-newline"]]
+    } else {
+        &[
+            &["xclip", "-out", "-selection", "primary"],
+            &["xsel", "--primary", "--output"],
+        ]
+    };
+
+    commands.iter().find_map(|command| run(command))
+}
+
+/// Returns the commands that write the system clipboard, in the order they are tried.
+fn get_copy_commands() -> &'static [&'static [&'static str]] {
+    if cfg!(target_os = "macos") {
+        &[&["pbcopy"]]
+    } else if cfg!(windows) {
+        &[&["clip.exe"]]
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[&["wl-copy"]]
+    } else if env::var_os("DISPLAY").is_some() {
+        &[
+            &["xclip", "-in", "-selection", "clipboard"],
+            &["xsel", "--clipboard", "--input"],
+        ]
+    } else {
+        &[]
+    }
+}
+
+/// Returns the commands that read the system clipboard, in the order they are tried.
+fn get_paste_commands() -> &'static [&'static [&'static str]] {
+    if cfg!(target_os = "macos") {
+        &[&["pbpaste"]]
+    } else if cfg!(windows) {
+        &[&[
+            "powershell.exe",
+            "-NoProfile",
+            "-Command",
+            "Get-Clipboard -Raw",
+        ]]
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[&["wl-paste", "--no-newline"]]
+    } else if env::var_os("DISPLAY").is_some() {
+        &[
+            &["xclip", "-out", "-selection", "clipboard"],
+            &["xsel", "--clipboard", "--output"],
+        ]
+    } else {
+        &[]
+    }
+}
+
+/// Runs a command and returns its output if it succeeds.
+fn run(command: &[&str]) -> Option<String> {
+    let (program, arguments) = command.split_first()?;
+    let output = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Runs a command with text as its input, returns whether it succeeds.
+fn run_with_input(command: &[&str], input: &str) -> bool {
+    let Some((program, arguments)) = command.split_first() else {
+        return false;
+    };
+    let Ok(mut child) = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+
+    // The input is closed before waiting, since the command reads until it ends.
+    let written = child
+        .stdin
+        .take()
+        .is_some_and(|mut stdin| stdin.write_all(input.as_bytes()).is_ok());
+
+    child.wait().is_ok_and(|status| status.success()) && written
+}
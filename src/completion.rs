@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    popup::Popup,
+    terminal::{Position, Size, TResult},
+    theme::Theme,
+};
+use vyse_core::buffer::{Buffer, Location};
+
+/// The maximum number of candidates visible at once.
+const MAX_VISIBLE_ITEMS: usize = 8;
+/// The maximum width of the candidate list.
+const MAX_LIST_WIDTH: usize = 30;
+/// The maximum width of the documentation panel.
+const MAX_DETAIL_WIDTH: u16 = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub detail: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Completion {
+    pub items: Vec<CompletionItem>,
+    pub selected: usize,
+    pub scroll: usize,
+    pub prefix: String,
+}
+
+impl Completion {
+    /// Collects completion candidates from the words in the buffer, matching the word before the cursor.
+    pub fn from_buffer(buffer: &Buffer, cursor: Location) -> Option<Self> {
+        let line = buffer.get_line(cursor.row)?;
+        let before_cursor: Vec<char> = line[..line.byte_index(cursor.col)].chars().collect();
+        let prefix_len = before_cursor
+            .iter()
+            .rev()
+            .take_while(|ch| is_word_char(**ch))
+            .count();
+        let prefix: String = before_cursor[before_cursor.len() - prefix_len..]
+            .iter()
+            .collect();
+
+        // Remember the first line and the number of occurrences for every word.
+        let mut words: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for (row, line) in buffer.lines().enumerate() {
+            for word in line.split(|ch: char| !is_word_char(ch)) {
+                if word.len() > prefix.len() && word.starts_with(&prefix) {
+                    words.entry(word.to_string()).or_insert((row, 0)).1 += 1;
+                }
+            }
+        }
+
+        let items: Vec<CompletionItem> = words
+            .into_iter()
+            .map(|(word, (row, count))| CompletionItem {
+                label: word,
+                detail: vec![
+                    format!("buffer word, {count} occurrence(s)"),
+                    String::new(),
+                    format!("line {}:", row + 1),
+                    buffer
+                        .get_line(row)
+                        .map_or_else(String::new, |line| line.trim().to_string()),
+                ],
+            })
+            .collect();
+
+        if items.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            items,
+            selected: 0,
+            scroll: 0,
+            prefix,
+        })
+    }
+
+    /// Returns the currently selected item.
+    pub fn selected_item(&self) -> Option<&CompletionItem> {
+        self.items.get(self.selected)
+    }
+
+    /// Selects the next item, wrapping around at the end.
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.items.len();
+        self.update_scroll();
+    }
+
+    /// Selects the previous item, wrapping around at the beginning.
+    pub fn select_previous(&mut self) {
+        self.selected = self
+            .selected
+            .checked_sub(1)
+            .unwrap_or(self.items.len().saturating_sub(1));
+        self.update_scroll();
+    }
+
+    /// Returns the text that has to be inserted at the cursor to complete the selected item.
+    pub fn completion_text(&self) -> Option<&str> {
+        self.selected_item()
+            .map(|item| &item.label[self.prefix.len()..])
+    }
+
+    /// Updates the scroll offset so the selected item is always visible.
+    fn update_scroll(&mut self) {
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + MAX_VISIBLE_ITEMS {
+            self.scroll = self.selected + 1 - MAX_VISIBLE_ITEMS;
+        }
+    }
+
+    /// Renders the candidate list below the anchor and the documentation of the selected item beside it.
+    pub fn render(&self, anchor: Position, screen: Size, theme: &Theme) -> TResult<()> {
+        let visible: Vec<String> = self
+            .items
+            .iter()
+            .skip(self.scroll)
+            .take(MAX_VISIBLE_ITEMS)
+            .map(|item| format!(" {} ", item.label))
+            .collect();
+
+        let width = visible
+            .iter()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(MAX_LIST_WIDTH);
+
+        #[allow(clippy::cast_possible_truncation)]
+        let list = Popup::place(
+            anchor,
+            Size {
+                width: width as u16,
+                height: visible.len() as u16,
+            },
+            screen,
+        );
+        list.render(&visible, Some(self.selected - self.scroll), theme)?;
+
+        if let (Some(item), Some(panel)) =
+            (self.selected_item(), list.beside(MAX_DETAIL_WIDTH, screen))
+        {
+            let detail: Vec<String> = item.detail.iter().map(|line| format!(" {line}")).collect();
+            panel.render(&detail, None, theme)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns whether the character can be part of a word.
+pub fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Returns the byte range of the word around a byte index of a line, which is empty if there is
+/// no word there.
+pub fn get_word_bounds(line: &str, byte_index: usize) -> (usize, usize) {
+    let start = line[..byte_index]
+        .char_indices()
+        .rev()
+        .take_while(|(_, ch)| is_word_char(*ch))
+        .last()
+        .map_or(byte_index, |(idx, _)| idx);
+    let end = line[byte_index..]
+        .find(|ch: char| !is_word_char(ch))
+        .map_or(line.len(), |idx| byte_index + idx);
+
+    (start, end)
+}
@@ -1,14 +1,73 @@
-use std::panic::{set_hook, take_hook};
+use std::{
+    collections::VecDeque,
+    env, io, mem,
+    panic::{set_hook, take_hook},
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::Instant,
+};
 
 use crate::{
-    terminal::{self, Position, TResult},
-    view::View,
+    bookmarks::Bookmarks,
+    progress::Progress,
+    recording::{RecordedEvent, Recorder},
+    terminal::{self, Position, Size, TResult},
+    todo_panel::{self, TodoItem},
+    view::{StartPosition, View},
 };
 use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use vyse_core::{
+    buffer::Buffer,
+    cancellation::CancellationToken,
+    continuation,
+    lock::{FileLock, LockError, DETECTS_STALE_LOCKS},
+};
+
+/// A message sent to the editor by the input thread or a worker thread.
+#[derive(Debug)]
+pub enum Message {
+    Event(Event),
+    /// An event of the recording being replayed.
+    ReplayedEvent(Event),
+    /// Every recorded event was replayed.
+    ReplayFinished,
+    Progress(Progress),
+    /// The start of a large file, shown while the rest is still loading.
+    BufferPartiallyLoaded(Buffer),
+    BufferLoaded(TResult<Buffer>),
+    /// The tagged comments collected below a directory.
+    TodoItemsCollected(PathBuf, Vec<TodoItem>),
+}
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct Editor {
     should_quit: bool,
+    quit_requested: bool,
     pub view: View,
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+    running_task: Option<CancellationToken>,
+    /// The running scan for tagged comments, cancelled when another one starts.
+    todo_scan: Option<CancellationToken>,
+    /// A loaded buffer waiting for the user to decide how to handle another instance editing it.
+    lock_conflict: Option<Buffer>,
+    /// Opens files read-only, set by the `-R` flag.
+    pub read_only: bool,
+    /// Where to place the cursor once the first file is shown, set by a `+` argument.
+    pub start_position: Option<StartPosition>,
+    /// A file opened while another one was loading, it is loaded once the running load finishes.
+    pending_open: Option<PathBuf>,
+    /// Files waiting to be loaded into background buffers once the running load finishes.
+    queued_paths: VecDeque<PathBuf>,
+    /// The running load is for a background buffer, so it is not shown when it finishes.
+    loading_in_background: bool,
+    /// Writes the received events to a file, set by the `--record` flag.
+    pub recorder: Option<Recorder>,
+    /// Events fed back through the event loop, set by the `--replay` flag.
+    pub replay: Vec<RecordedEvent>,
+    /// Recorded events are being replayed, input from the terminal is ignored until they are done.
+    replaying: bool,
 }
 
 impl Editor {
@@ -20,34 +79,423 @@ impl Editor {
             current_hook(panic_info);
         }));
 
-        let view = View::default();
+        let working_dir = env::current_dir().unwrap_or_default();
+        let view = View {
+            bookmarks: Bookmarks::load(&working_dir),
+            working_dir,
+            // Known before running, so a start position can be centered in a buffer read from stdin.
+            current_size: terminal::size().unwrap_or_default(),
+            middle_click_paste: true,
+            highlight_current_line: true,
+            sticky_header: true,
+            todo_tags: todo_panel::DEFAULT_TAGS.map(String::from).to_vec(),
+            continued_filetypes: continuation::FILETYPES
+                .iter()
+                .map(|filetype| filetype.name.to_string())
+                .collect(),
+            date_format: "YYYY-MM-DD".to_string(),
+            time_format: "hh:mm:ss".to_string(),
+            ..View::default()
+        };
+        let (sender, receiver) = channel();
 
         Self {
             should_quit: false,
+            quit_requested: false,
             view,
+            sender,
+            receiver,
+            running_task: None,
+            todo_scan: None,
+            lock_conflict: None,
+            read_only: false,
+            start_position: None,
+            pending_open: None,
+            queued_paths: VecDeque::new(),
+            loading_in_background: false,
+            recorder: None,
+            replay: Vec::new(),
+            replaying: false,
+        }
+    }
+
+    /// Loads a file on a worker thread, reporting the progress to the editor. Directories are
+    /// shown in the explorer instead.
+    ///
+    /// Only one file is loaded at a time, so a file opened while another one is loading waits for
+    /// it to finish.
+    pub fn open<P: Into<PathBuf>>(&mut self, path: P) {
+        let path = path.into();
+
+        if path.is_dir() {
+            self.view.open_explorer(&path);
+            return;
+        }
+
+        if self.running_task.is_some() || self.lock_conflict.is_some() {
+            self.pending_open = Some(path);
+            return;
+        }
+
+        self.load(path);
+    }
+
+    /// Starts loading a file on a worker thread.
+    fn load(&mut self, path: PathBuf) {
+        let sender = self.sender.clone();
+        let token = CancellationToken::default();
+        self.running_task = Some(token.clone());
+
+        thread::spawn(move || {
+            let label = format!("loading {}", path.display());
+            let buffer = Buffer::from_path(
+                &path,
+                &token,
+                |current, total| {
+                    let _ = sender.send(Message::Progress(Progress {
+                        label: label.clone(),
+                        current,
+                        total,
+                    }));
+                },
+                |bytes| {
+                    let buffer = Buffer::partial(path.clone(), bytes);
+                    let _ = sender.send(Message::BufferPartiallyLoaded(buffer));
+                },
+            );
+
+            let buffer = match buffer {
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Buffer::new_file(path)),
+                buffer => buffer,
+            };
+
+            let _ = sender.send(Message::BufferLoaded(buffer));
+        });
+    }
+
+    /// Collects the tagged comments the view asked for on a worker thread, cancelling the
+    /// previous scan.
+    fn start_todo_scan(&mut self) {
+        let Some(root) = self.view.todo_scan_request.take() else {
+            return;
+        };
+
+        if let Some(token) = self.todo_scan.take() {
+            token.cancel();
+        }
+
+        let sender = self.sender.clone();
+        let token = CancellationToken::default();
+        let tags = self.view.todo_tags.clone();
+        let options = self.view.walk_options.clone();
+        self.todo_scan = Some(token.clone());
+
+        thread::spawn(move || {
+            let items = todo_panel::scan(&root, &tags, &options, &token);
+            if !token.is_cancelled() {
+                let _ = sender.send(Message::TodoItemsCollected(root, items));
+            }
+        });
+    }
+
+    /// Loads files into background buffers one after another.
+    pub fn open_in_background(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        self.queued_paths.extend(paths);
+        self.open_next_queued();
+    }
+
+    /// Starts loading the file that waited to be opened, or else the next queued file, unless a
+    /// file is still loading.
+    fn open_next_queued(&mut self) {
+        if self.running_task.is_some() || self.lock_conflict.is_some() {
+            return;
+        }
+
+        if let Some(path) = self.pending_open.take() {
+            self.open(path);
+            return;
+        }
+
+        while let Some(path) = self.queued_paths.pop_front() {
+            // Directories can only be shown in place of the current buffer.
+            if path.is_dir() {
+                self.view.status_message = Some(format!("skipped directory {}", path.display()));
+                continue;
+            }
+
+            self.loading_in_background = true;
+            self.load(path);
+            return;
+        }
+    }
+
+    /// Shows a loaded buffer, or keeps it behind the current one if it was loaded in the background.
+    pub fn show_buffer(&mut self, buffer: Buffer) {
+        if self.loading_in_background {
+            self.view.add_buffer(buffer);
+        } else {
+            self.view.set_buffer(buffer);
+
+            if let Some(start_position) = self.start_position.take() {
+                self.view.move_to_start_position(&start_position);
+            }
         }
+
+        self.loading_in_background = false;
+        self.open_next_queued();
     }
 
     /// The main application loop.
     pub fn run(&mut self) -> TResult<()> {
         terminal::initialize()?;
 
+        self.view.current_size = terminal::size()?;
+        self.view.needs_redraw = true;
+
+        // A replay starts at the size the recording was made at, since it is recorded first.
+        let Size { width, height } = self.view.current_size;
+        self.record_event(&Event::Resize(width, height));
+
+        spawn_input_thread(self.sender.clone());
+        if !self.replay.is_empty() {
+            self.replaying = true;
+            spawn_replay_thread(self.sender.clone(), mem::take(&mut self.replay));
+        }
+
+        let result = self.run_loop();
+        terminal::terminate()?;
+
+        result
+    }
+
+    /// Renders and handles all pending messages until the editor quits.
+    fn run_loop(&mut self) -> TResult<()> {
         loop {
             self.render()?;
 
+            if self.should_quit {
+                return Ok(());
+            }
+
+            let Ok(message) = self.receiver.recv() else {
+                return Ok(());
+            };
+
+            // Handle everything that queued up while rendering before rendering again, so
+            // repeated keys and mouse drags do not lag behind on slow terminals.
+            let mut messages = vec![message];
+            messages.extend(self.receiver.try_iter());
+            self.handle_messages(messages);
+        }
+    }
+
+    /// Handles the messages that arrived together. Characters arriving together with nothing
+    /// else, like the text committed by an input method, are inserted as one edit.
+    fn handle_messages(&mut self, messages: Vec<Message>) {
+        let typed: Option<String> = messages
+            .iter()
+            .map(|message| match message {
+                Message::Event(event) => get_typed_char(event),
+                _ => None,
+            })
+            .collect();
+
+        // Replays handle every key on its own, so they do not depend on how fast the events
+        // arrive, and terminal input is ignored while they run. Typing is coalesced again
+        // once the replay finished.
+        let coalesce = messages.len() > 1
+            && !self.replaying
+            && self.lock_conflict.is_none()
+            && self.view.accepts_typed_text();
+
+        if let Some(text) = typed.filter(|_| coalesce) {
+            for message in &messages {
+                if let Message::Event(event) = message {
+                    self.record_event(event);
+                }
+            }
+
+            self.quit_requested = false;
+            self.view.insert_typed_text(&text);
+            return;
+        }
+
+        for message in messages {
+            self.handle_message(message);
+
             if self.should_quit {
                 break;
             }
+        }
+    }
+
+    /// Handles a message from the input thread or a worker thread.
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            // Input during a replay would make it differ from the recording.
+            Message::Event(_) if self.replaying => (),
+            Message::Event(event) | Message::ReplayedEvent(event) => {
+                self.record_event(&event);
+                self.handle_event(&event);
+            }
+            Message::ReplayFinished => {
+                self.replaying = false;
+
+                // The terminal may have been resized while its events were ignored.
+                if let Ok(size) = terminal::size() {
+                    if size != self.view.current_size {
+                        let Size { width, height } = size;
+                        self.handle_message(Message::Event(Event::Resize(width, height)));
+                    }
+                }
+            }
+            Message::Progress(progress) => {
+                self.view.progress = Some(progress);
+                self.view.needs_redraw = true;
+            }
+            Message::BufferPartiallyLoaded(buffer) => {
+                if !self.loading_in_background {
+                    self.view.set_buffer(buffer);
+                }
+            }
+            Message::TodoItemsCollected(root, items) => {
+                self.todo_scan = None;
+                self.view.show_todo_items(&root, items);
+            }
+            Message::BufferLoaded(buffer) => {
+                self.view.progress = None;
+                self.view.needs_redraw = true;
+                self.running_task = None;
 
-            let event = read()?;
-            self.handle_event(&event);
+                match buffer {
+                    Ok(mut buffer) => {
+                        buffer.read_only |= self.read_only;
+                        self.lock_buffer(buffer);
+                    }
+                    // Failing to open one file should not lose the buffers that are open.
+                    Err(err) => {
+                        if !self.loading_in_background {
+                            self.view.restore_replaced_buffer();
+                        }
+                        self.view.status_message =
+                            Some(if err.kind() == io::ErrorKind::Interrupted {
+                                err.to_string()
+                            } else {
+                                format!("could not open file: {err}")
+                            });
+                        self.view.needs_redraw = true;
+                        self.loading_in_background = false;
+                        self.open_next_queued();
+                    }
+                }
+            }
         }
+    }
+
+    /// Writes an event to the recording, stopping the recording if writing fails.
+    fn record_event(&mut self, event: &Event) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
 
-        terminal::terminate()
+        if let Err(err) = recorder.record(event) {
+            self.recorder = None;
+            self.view.status_message = Some(format!("recording stopped: {err}"));
+            self.view.needs_redraw = true;
+        }
+    }
+
+    /// Locks the file of a loaded buffer and shows it, asking the user first if another instance is editing it.
+    fn lock_buffer(&mut self, mut buffer: Buffer) {
+        let Some(path) = buffer.path.clone() else {
+            self.show_buffer(buffer);
+            return;
+        };
+
+        match FileLock::acquire(&path) {
+            Ok(lock) => {
+                buffer.lock = Some(lock);
+                self.show_buffer(buffer);
+            }
+            Err(LockError::HeldBy(pid)) => {
+                // Without a way to check the owner, the lock may be left over from a crash.
+                let stale_note = if DETECTS_STALE_LOCKS {
+                    ""
+                } else {
+                    " (or crashed)"
+                };
+                self.view.status_message = Some(format!(
+                    "open in pid {pid}{stale_note}: [o] read-only, [e] edit anyway, [a] abort"
+                ));
+                self.view.needs_redraw = true;
+                self.lock_conflict = Some(buffer);
+            }
+
+            // Failing to create the lock should not prevent editing the file.
+            Err(LockError::Io(err)) => {
+                self.show_buffer(buffer);
+                self.view.status_message = Some(format!("could not lock file: {err}"));
+            }
+        }
+    }
+
+    /// Handles the answer to a lock conflict, returns whether the event was consumed.
+    fn handle_lock_conflict_event(&mut self, event: &Event) -> bool {
+        let Event::Key(KeyEvent {
+            code: KeyCode::Char(answer),
+            kind: KeyEventKind::Press,
+            ..
+        }) = event
+        else {
+            return matches!(event, Event::Key(_));
+        };
+
+        let Some(mut buffer) = self.lock_conflict.take() else {
+            return false;
+        };
+
+        match answer {
+            'o' => {
+                buffer.read_only = true;
+                self.show_buffer(buffer);
+            }
+            'e' => {
+                buffer.lock = buffer
+                    .path
+                    .as_deref()
+                    .and_then(|path| FileLock::force(path).ok());
+                self.show_buffer(buffer);
+            }
+            // Only the conflicting file is not opened, the open buffers stay as they are.
+            'a' => {
+                if !self.loading_in_background {
+                    self.view.restore_replaced_buffer();
+                }
+                self.view.status_message = buffer
+                    .path
+                    .map(|path| format!("did not open {}", path.display()));
+                self.view.needs_redraw = true;
+                self.loading_in_background = false;
+                self.open_next_queued();
+            }
+            _ => self.lock_conflict = Some(buffer),
+        }
+
+        true
     }
 
     /// Handle an event, e.g. input or resizing.
     fn handle_event(&mut self, event: &Event) {
+        if self.lock_conflict.is_some() && self.handle_lock_conflict_event(event) {
+            return;
+        }
+
+        // Quitting with unsaved changes has to be confirmed by pressing Ctrl+Q twice in a row.
+        let quit_requested = self.quit_requested;
+        if matches!(event, Event::Key(_)) {
+            self.quit_requested = false;
+        }
+
         match event {
             // Handle quit event.
             Event::Key(KeyEvent {
@@ -56,11 +504,59 @@ impl Editor {
                 modifiers: KeyModifiers::CONTROL,
                 ..
             }) => {
-                self.should_quit = true;
+                if self.view.has_unsaved_changes() && !quit_requested {
+                    self.quit_requested = true;
+                    self.view.status_message =
+                        Some("unsaved changes, press Ctrl+Q again to quit".to_string());
+                    self.view.needs_redraw = true;
+                } else {
+                    self.should_quit = true;
+                }
+            }
+
+            // Handle cancelling the running task.
+            Event::Key(
+                KeyEvent {
+                    code: KeyCode::Esc,
+                    kind: KeyEventKind::Press,
+                    ..
+                }
+                | KeyEvent {
+                    code: KeyCode::Char('c'),
+                    kind: KeyEventKind::Press,
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                },
+            ) if self.running_task.is_some() => {
+                if let Some(token) = self.running_task.take() {
+                    token.cancel();
+                }
             }
 
-            event => self.view.handle_event(event),
+            // Handle save event.
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('s'),
+                kind: KeyEventKind::Press,
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.view.save(),
+
+            event => {
+                self.view.handle_event(event);
+
+                if let Some(path) = self.view.open_request.take() {
+                    if let Some(row) = self.view.open_request_row.take() {
+                        self.start_position = Some(StartPosition::Line(row + 1));
+                    }
+                    self.open(path);
+                }
+
+                let paths = mem::take(&mut self.view.background_open_requests);
+                self.open_in_background(paths);
+            }
         }
+
+        self.start_todo_scan();
     }
 
     /// Renders the editor to the screen.
@@ -75,7 +571,51 @@ impl Editor {
             self.view.render()?;
         }
 
-        terminal::show_cursor()?;
+        // The cursor is hidden while the view is scrolled away from it with the mouse.
+        if self.should_quit || self.view.is_cursor_visible() {
+            terminal::show_cursor()?;
+        }
         terminal::execute()
     }
 }
+
+/// Returns the character inserted by a key event, `None` for other keys and events.
+fn get_typed_char(event: &Event) -> Option<char> {
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+            kind: KeyEventKind::Press,
+            ..
+        }) => Some(*ch),
+        _ => None,
+    }
+}
+
+/// Spawns a thread forwarding terminal events to the editor.
+fn spawn_input_thread(sender: Sender<Message>) {
+    thread::spawn(move || {
+        while let Ok(event) = read() {
+            if sender.send(Message::Event(event)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Spawns a thread sending recorded events to the editor at the times they were recorded.
+fn spawn_replay_thread(sender: Sender<Message>, events: Vec<RecordedEvent>) {
+    thread::spawn(move || {
+        let start = Instant::now();
+
+        for RecordedEvent { time, event } in events {
+            thread::sleep(time.saturating_sub(start.elapsed()));
+
+            if sender.send(Message::ReplayedEvent(event)).is_err() {
+                return;
+            }
+        }
+
+        let _ = sender.send(Message::ReplayFinished);
+    });
+}
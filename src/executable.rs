@@ -0,0 +1,47 @@
+use std::{io, path::Path};
+
+#[cfg(unix)]
+use std::{fs, os::unix::fs::PermissionsExt};
+
+/// Whether new scripts are made executable when they are saved for the first time.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutablePolicy {
+    Always,
+    #[default]
+    Ask,
+    Never,
+}
+
+impl ExecutablePolicy {
+    /// Parses the value of the `executable` option.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "always" => Some(Self::Always),
+            "ask" => Some(Self::Ask),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Returns whether the first line of a file is a shebang, e.g. `#!/bin/sh`.
+pub fn is_shebang(first_line: &str) -> bool {
+    first_line.starts_with("#!")
+}
+
+/// Sets the executable bits of a file for everyone who may read it.
+#[cfg(unix)]
+pub fn make_executable(path: &Path) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    let mode = permissions.mode();
+
+    permissions.set_mode(mode | (mode & 0o444) >> 2);
+    fs::set_permissions(path, permissions)
+}
+
+/// Does nothing, files are executable by their extension rather than a permission on other
+/// platforms.
+#[cfg(not(unix))]
+pub fn make_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    terminal::{self, Position, Size, TResult},
+    theme::Theme,
+};
+use vyse_core::line::Line;
+
+/// An entry of a directory listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// A navigable listing of a directory, shown in place of the buffer.
+#[derive(Debug)]
+pub struct Explorer {
+    pub dir: PathBuf,
+    pub entries: Vec<Entry>,
+    pub selected: usize,
+    pub scroll: usize,
+}
+
+impl Explorer {
+    /// Lists a directory with its subdirectories first, both sorted by name.
+    pub fn read(dir: &Path) -> io::Result<Self> {
+        let dir = fs::canonicalize(dir)?;
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            entries.push(Entry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                is_dir: entry.path().is_dir(),
+            });
+        }
+
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+        if dir.parent().is_some() {
+            entries.insert(
+                0,
+                Entry {
+                    name: "..".to_string(),
+                    is_dir: true,
+                },
+            );
+        }
+
+        Ok(Self {
+            dir,
+            entries,
+            selected: 0,
+            scroll: 0,
+        })
+    }
+
+    /// Returns the path of the selected entry.
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        let entry = self.entries.get(self.selected)?;
+
+        Some(if entry.name == ".." {
+            self.dir.parent()?.to_path_buf()
+        } else {
+            self.dir.join(&entry.name)
+        })
+    }
+
+    /// Returns whether the selected entry is a directory.
+    pub fn is_dir_selected(&self) -> bool {
+        self.entries
+            .get(self.selected)
+            .is_some_and(|entry| entry.is_dir)
+    }
+
+    /// Moves the selection by the given number of entries, stopping at either end.
+    pub fn move_selection(&mut self, delta: isize, height: usize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+
+        // Keep the selected entry visible.
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    /// Renders the listing into all but the last row of the screen.
+    pub fn render(&self, size: Size, theme: &Theme) -> TResult<()> {
+        for pos_y in 0..size.height.saturating_sub(1) {
+            let idx = self.scroll + pos_y as usize;
+
+            terminal::move_cursor_to(Position { x: 0, y: pos_y })?;
+            terminal::clear_line()?;
+
+            let Some(entry) = self.entries.get(idx) else {
+                terminal::print("~")?;
+                continue;
+            };
+
+            let suffix = if entry.is_dir { "/" } else { "" };
+            let text = Line::new(format!("{}{suffix}", entry.name));
+
+            if idx == self.selected {
+                terminal::set_foreground_color(theme.popup_selected_text)?;
+                terminal::set_background_color(theme.popup_selected_background)?;
+            }
+
+            terminal::print(text.into_visible(0, size.width as usize))?;
+
+            terminal::set_foreground_color(theme.text)?;
+            terminal::set_background_color(theme.background)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+
+/// Files dropped onto the terminal, which most terminals paste as their quoted or escaped paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDrop {
+    /// The pasted text, inserted instead if the user does not want to open the files.
+    pub text: String,
+    pub paths: Vec<PathBuf>,
+}
+
+impl FileDrop {
+    /// Returns the dropped files if the pasted text consists only of absolute paths or `file://`
+    /// URIs of existing files, otherwise gives the text back.
+    pub fn detect(text: String) -> Result<Self, String> {
+        let paths: Option<Vec<PathBuf>> = split_words(&text).and_then(|words| {
+            words
+                .iter()
+                .map(|word| {
+                    let path = PathBuf::from(decode_file_uri(word));
+                    (path.is_absolute() && path.is_file()).then_some(path)
+                })
+                .collect()
+        });
+
+        match paths {
+            Some(paths) if !paths.is_empty() => Ok(Self { text, paths }),
+            _ => Err(text),
+        }
+    }
+}
+
+/// Splits text into words like a shell, honoring single and double quotes and backslash escapes.
+/// Returns `None` if a quote is not closed.
+fn split_words(text: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' | '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        quote if quote == ch => break,
+                        '\\' if ch == '"' => word.push(chars.next()?),
+                        other => word.push(other),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).push(chars.next()?),
+            ch if ch.is_whitespace() => words.extend(word.take()),
+            ch => word.get_or_insert_with(String::new).push(ch),
+        }
+    }
+
+    words.extend(word);
+    Some(words)
+}
+
+/// Turns a `file://` URI into a path, decoding percent-encoded bytes. Other text is returned as is.
+fn decode_file_uri(word: &str) -> String {
+    let Some(encoded) = word.strip_prefix("file://") else {
+        return word.to_string();
+    };
+
+    // The host is usually empty or `localhost`, the path starts after it.
+    let encoded = &encoded[encoded.find('/').unwrap_or(encoded.len())..];
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        if let Some(decoded) = decoded {
+            bytes.push(decoded);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[test]
+    fn splits_words_like_a_shell() {
+        let words = split_words(r#"'a b' "c\"d" e\ f  g"#).unwrap();
+        assert_eq!(words, ["a b", "c\"d", "e f", "g"]);
+        assert_eq!(split_words("''").unwrap(), [""]);
+        assert_eq!(split_words("'open"), None);
+    }
+
+    #[test]
+    fn decodes_file_uris() {
+        assert_eq!(decode_file_uri("file:///tmp/a%20b%C3%A9"), "/tmp/a bé");
+        assert_eq!(decode_file_uri("file://localhost/tmp/a"), "/tmp/a");
+        assert_eq!(decode_file_uri("file:///100%"), "/100%");
+        assert_eq!(decode_file_uri("/tmp/a%20b"), "/tmp/a%20b");
+    }
+
+    #[test]
+    fn detects_dropped_files() {
+        let path = env::temp_dir().join(format!("vyse drop {}", process::id()));
+        fs::write(&path, "").unwrap();
+
+        let quoted = format!("'{}' ", path.display());
+        let detected = FileDrop::detect(quoted.clone());
+        let missing = FileDrop::detect(format!("{quoted} /no/such/file"));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            detected,
+            Ok(FileDrop {
+                text: quoted,
+                paths: vec![path],
+            })
+        );
+        assert!(missing.is_err());
+        assert_eq!(
+            FileDrop::detect("Cargo.toml".to_string()),
+            Err("Cargo.toml".to_string())
+        );
+        assert!(FileDrop::detect(" ".to_string()).is_err());
+    }
+}
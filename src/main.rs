@@ -1,21 +1,83 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use std::env;
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+};
 
 use editor::Editor;
+use recording::Recorder;
 use terminal::TResult;
+use view::StartPosition;
+use vyse_core::buffer::Buffer;
 
-mod buffer;
+mod bookmarks;
+mod clipboard;
+mod completion;
 mod editor;
+mod executable;
+mod explorer;
+mod file_drop;
+mod open_files;
+mod popup;
+mod progress;
+mod prompt;
+mod recording;
+mod rename;
+mod scroll;
+mod snippet_session;
 mod terminal;
+mod theme;
+mod todo_panel;
+mod trash;
+mod undo_panel;
 mod view;
 
 fn main() -> TResult<()> {
     let mut editor = Editor::new();
+    let mut paths = Vec::new();
 
-    if let Some(path) = env::args().nth(1) {
-        editor.view.load(path)?;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-R" | "--readonly" => editor.read_only = true,
+            "--record" => {
+                let path = args.next().ok_or_else(|| missing_value(&arg))?;
+                editor.recorder = Some(Recorder::create(Path::new(&path))?);
+            }
+            "--replay" => {
+                let path = args.next().ok_or_else(|| missing_value(&arg))?;
+                editor.replay = recording::load(Path::new(&path))?;
+            }
+            _ if arg.starts_with('+') => {
+                editor.start_position = Some(StartPosition::parse(&arg).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, format!("invalid line: {arg}"))
+                })?);
+            }
+            _ => paths.push(arg),
+        }
     }
 
+    // Reading the whole of stdin first lets the editor end a pipeline, the terminal is opened
+    // through /dev/tty for input since stdin is not one.
+    let mut paths = paths.into_iter().map(PathBuf::from);
+    match paths.next() {
+        Some(path) if path == Path::new("-") => {
+            let mut buffer = Buffer::from_reader(io::stdin().lock())?;
+            buffer.read_only |= editor.read_only;
+            editor.show_buffer(buffer);
+        }
+        Some(path) => editor.open(path),
+        None => (),
+    }
+
+    // The remaining files are opened behind the first one.
+    editor.open_in_background(paths);
+
     editor.run()
 }
+
+/// Returns the error for a flag given without its value.
+fn missing_value(flag: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{flag} needs a file"))
+}
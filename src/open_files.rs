@@ -0,0 +1,45 @@
+use std::{fs, path::Path, process};
+
+/// Another process that has a file open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileUser {
+    pub pid: u32,
+    /// The command name of the process, e.g. `tail`.
+    pub name: String,
+}
+
+/// Returns the other processes that have the file open, like `lsof`. This reads the file
+/// descriptors in `/proc`, so it only finds anything on Linux and only for processes of the
+/// same user.
+pub fn find_users(path: &Path) -> Vec<FileUser> {
+    let Ok(path) = fs::canonicalize(path) else {
+        return Vec::new();
+    };
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    processes
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| *pid != process::id())
+        .filter(|pid| has_open(*pid, &path))
+        .map(|pid| FileUser {
+            pid,
+            name: fs::read_to_string(format!("/proc/{pid}/comm"))
+                .map(|name| name.trim_end().to_string())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Returns whether one of the file descriptors of the process points to the path.
+fn has_open(pid: u32, path: &Path) -> bool {
+    let Ok(descriptors) = fs::read_dir(format!("/proc/{pid}/fd")) else {
+        return false;
+    };
+
+    descriptors
+        .flatten()
+        .any(|descriptor| fs::read_link(descriptor.path()).is_ok_and(|target| target == path))
+}
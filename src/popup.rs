@@ -0,0 +1,93 @@
+use crate::{
+    terminal::{self, Position, Size, TResult},
+    theme::Theme,
+};
+
+/// A rectangular area that is drawn on top of the buffer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Popup {
+    pub position: Position,
+    pub size: Size,
+}
+
+impl Popup {
+    /// Places a popup below the anchor, moving it above the anchor or to the left if it does not fit on the screen.
+    pub fn place(anchor: Position, size: Size, screen: Size) -> Self {
+        let size = Size {
+            width: size.width.min(screen.width),
+            height: size.height.min(screen.height.saturating_sub(1)),
+        };
+
+        let below = anchor.y.saturating_add(1);
+        let y = if below.saturating_add(size.height) < screen.height {
+            below
+        } else {
+            anchor.y.saturating_sub(size.height)
+        };
+
+        let x = anchor.x.min(screen.width.saturating_sub(size.width));
+
+        Self {
+            position: Position { x, y },
+            size,
+        }
+    }
+
+    /// Renders the given lines into the popup, highlighting the selected line.
+    pub fn render<S: AsRef<str>>(
+        self,
+        lines: &[S],
+        selected: Option<usize>,
+        theme: &Theme,
+    ) -> TResult<()> {
+        let width = self.size.width as usize;
+
+        for row in 0..self.size.height {
+            let index = row as usize;
+
+            if selected == Some(index) {
+                terminal::set_foreground_color(theme.popup_selected_text)?;
+                terminal::set_background_color(theme.popup_selected_background)?;
+            } else {
+                terminal::set_foreground_color(theme.popup_text)?;
+                terminal::set_background_color(theme.popup_background)?;
+            }
+
+            let text = lines.get(index).map_or("", AsRef::as_ref);
+            let text: String = text.chars().take(width).collect();
+
+            terminal::move_cursor_to(Position {
+                x: self.position.x,
+                y: self.position.y + row,
+            })?;
+            terminal::print(format!("{text:<width$}"))?;
+        }
+
+        // Reset popup colors.
+        terminal::set_foreground_color(theme.text)?;
+        terminal::set_background_color(theme.background)?;
+
+        Ok(())
+    }
+
+    /// Returns the popup directly to the right of this one with the given width.
+    pub fn beside(self, width: u16, screen: Size) -> Option<Self> {
+        let x = self.position.x.saturating_add(self.size.width);
+        let width = width.min(screen.width.saturating_sub(x));
+
+        if width == 0 {
+            return None;
+        }
+
+        Some(Self {
+            position: Position {
+                x,
+                y: self.position.y,
+            },
+            size: Size {
+                width,
+                height: self.size.height,
+            },
+        })
+    }
+}
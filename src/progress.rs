@@ -0,0 +1,35 @@
+/// The number of cells used by the gauge in the status bar.
+const GAUGE_WIDTH: usize = 20;
+
+/// The progress of a long-running operation, reported by a worker thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub label: String,
+    pub current: u64,
+    pub total: u64,
+}
+
+impl Progress {
+    /// Returns how much of the operation is done in percent.
+    pub fn percentage(&self) -> u64 {
+        if self.total == 0 {
+            return 100;
+        }
+
+        self.current.min(self.total) * 100 / self.total
+    }
+
+    /// Formats the progress as a gauge for the status bar.
+    pub fn gauge(&self) -> String {
+        let percentage = self.percentage();
+        let filled = usize::try_from(percentage)
+            .map_or(GAUGE_WIDTH, |percentage| percentage * GAUGE_WIDTH / 100);
+
+        format!(
+            "{} [{}{}] {percentage}%",
+            self.label,
+            "#".repeat(filled),
+            " ".repeat(GAUGE_WIDTH - filled),
+        )
+    }
+}
@@ -0,0 +1,76 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use vyse_core::line::Line;
+
+/// The outcome of a key press inside the prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptResult {
+    Pending,
+    Submitted(String),
+    Cancelled,
+}
+
+/// A single line of user input shown in place of the status bar.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Prompt {
+    pub label: String,
+    pub input: String,
+    /// The cursor position within the input, counted in characters.
+    pub cursor: usize,
+}
+
+impl Prompt {
+    /// Creates an empty prompt with the given label.
+    pub fn new<S: Into<String>>(label: S) -> Self {
+        Self {
+            label: label.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Handles a key press, editing the input or finishing the prompt.
+    pub fn handle_key_event(&mut self, key_event: &KeyEvent) -> PromptResult {
+        match key_event.code {
+            KeyCode::Enter => return PromptResult::Submitted(self.input.clone()),
+            KeyCode::Esc => return PromptResult::Cancelled,
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.input.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.chars().count(),
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let byte_index = self.byte_index();
+                self.input.remove(byte_index);
+            }
+            KeyCode::Char(ch)
+                if key_event.modifiers == KeyModifiers::NONE
+                    || key_event.modifiers == KeyModifiers::SHIFT =>
+            {
+                let byte_index = self.byte_index();
+                self.input.insert(byte_index, ch);
+                self.cursor += 1;
+            }
+            _ => (),
+        }
+
+        PromptResult::Pending
+    }
+
+    /// Returns the text shown on screen.
+    pub fn text(&self) -> String {
+        format!("{}{}", self.label, self.input)
+    }
+
+    /// Returns the column of the cursor on screen.
+    pub fn cursor_column(&self) -> usize {
+        Line::new(self.label.as_str()).width() + Line::new(&self.input[..self.byte_index()]).width()
+    }
+
+    /// Returns the byte index of the cursor within the input.
+    fn byte_index(&self) -> usize {
+        self.input
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.input.len(), |(idx, _)| idx)
+    }
+}
@@ -0,0 +1,382 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+
+/// The first line of every recording, to detect files written by an incompatible version.
+const RECORDING_FILE_HEADER: &str = "vyse-recording 1";
+
+/// An event read from a recording together with when it happened.
+#[derive(Debug, Clone)]
+pub struct RecordedEvent {
+    /// The time since the recording started.
+    pub time: Duration,
+    pub event: Event,
+}
+
+/// Writes every event the editor receives to a file, one `<milliseconds> <event>` line each, so a
+/// session can be replayed with `--replay`.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    /// Creates the recording file, overwriting an existing one.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{RECORDING_FILE_HEADER}")?;
+
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Appends an event to the recording. Events that cannot be written, like media keys, are
+    /// skipped.
+    pub fn record(&mut self, event: &Event) -> io::Result<()> {
+        let Some(event) = format_event(event) else {
+            return Ok(());
+        };
+
+        writeln!(self.writer, "{} {event}", self.start.elapsed().as_millis())?;
+
+        // Flushed right away so the recording is complete even if the editor crashes.
+        self.writer.flush()
+    }
+}
+
+/// Reads the events of a recording.
+pub fn load(path: &Path) -> io::Result<Vec<RecordedEvent>> {
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.lines();
+
+    if lines.next() != Some(RECORDING_FILE_HEADER) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a vyse recording", path.display()),
+        ));
+    }
+
+    lines
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(idx, line)| {
+            parse_recorded_event(line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: invalid event", path.display(), idx + 2),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Parses a line of a recording, `<milliseconds> <event>`.
+fn parse_recorded_event(line: &str) -> Option<RecordedEvent> {
+    let (time, event) = line.split_once(' ')?;
+
+    Some(RecordedEvent {
+        time: Duration::from_millis(time.parse().ok()?),
+        event: parse_event(event)?,
+    })
+}
+
+/// Formats an event as a line of a recording:
+///
+/// - `key <code> <modifiers> <kind>`
+/// - `mouse <kind> <column> <row> <modifiers>`
+/// - `resize <width> <height>`
+/// - `paste <text>` with backslashes, tabs and line breaks escaped
+/// - `focus gained` or `focus lost`
+fn format_event(event: &Event) -> Option<String> {
+    Some(match event {
+        Event::Key(key_event) => format!(
+            "key {} {} {}",
+            format_key_code(key_event.code)?,
+            key_event.modifiers.bits(),
+            match key_event.kind {
+                KeyEventKind::Press => "press",
+                KeyEventKind::Repeat => "repeat",
+                KeyEventKind::Release => "release",
+            }
+        ),
+        Event::Mouse(mouse_event) => format!(
+            "mouse {} {} {} {}",
+            format_mouse_event_kind(mouse_event.kind),
+            mouse_event.column,
+            mouse_event.row,
+            mouse_event.modifiers.bits()
+        ),
+        Event::Resize(width, height) => format!("resize {width} {height}"),
+        Event::Paste(text) => format!("paste {}", escape(text)),
+        Event::FocusGained => "focus gained".to_string(),
+        Event::FocusLost => "focus lost".to_string(),
+    })
+}
+
+/// Parses an event written by `format_event`.
+fn parse_event(text: &str) -> Option<Event> {
+    let (kind, rest) = text.split_once(' ').unwrap_or((text, ""));
+
+    if kind == "paste" {
+        return Some(Event::Paste(unescape(rest)?));
+    }
+
+    let fields: Vec<&str> = rest.split(' ').collect();
+    match (kind, fields.as_slice()) {
+        ("key", [code, modifiers, kind]) => Some(Event::Key(KeyEvent::new_with_kind(
+            parse_key_code(code)?,
+            KeyModifiers::from_bits_truncate(modifiers.parse().ok()?),
+            match *kind {
+                "press" => KeyEventKind::Press,
+                "repeat" => KeyEventKind::Repeat,
+                "release" => KeyEventKind::Release,
+                _ => return None,
+            },
+        ))),
+        ("mouse", [kind, column, row, modifiers]) => Some(Event::Mouse(MouseEvent {
+            kind: parse_mouse_event_kind(kind)?,
+            column: column.parse().ok()?,
+            row: row.parse().ok()?,
+            modifiers: KeyModifiers::from_bits_truncate(modifiers.parse().ok()?),
+        })),
+        ("resize", [width, height]) => {
+            Some(Event::Resize(width.parse().ok()?, height.parse().ok()?))
+        }
+        ("focus", ["gained"]) => Some(Event::FocusGained),
+        ("focus", ["lost"]) => Some(Event::FocusLost),
+        _ => None,
+    }
+}
+
+/// Returns the name of a key, characters are written as their code point so spaces survive.
+fn format_key_code(code: KeyCode) -> Option<String> {
+    Some(match code {
+        KeyCode::Char(ch) => format!("char:{}", u32::from(ch)),
+        KeyCode::F(number) => format!("f:{number}"),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::BackTab => "backtab".to_string(),
+        KeyCode::Delete => "delete".to_string(),
+        KeyCode::Insert => "insert".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        _ => return None,
+    })
+}
+
+/// Parses a key name written by `format_key_code`.
+fn parse_key_code(name: &str) -> Option<KeyCode> {
+    if let Some(code_point) = name.strip_prefix("char:") {
+        return char::from_u32(code_point.parse().ok()?).map(KeyCode::Char);
+    }
+    if let Some(number) = name.strip_prefix("f:") {
+        return number.parse().ok().map(KeyCode::F);
+    }
+
+    Some(match name {
+        "backspace" => KeyCode::Backspace,
+        "enter" => KeyCode::Enter,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "esc" => KeyCode::Esc,
+        _ => return None,
+    })
+}
+
+/// Returns the name of a kind of mouse event, e.g. `down:left`.
+fn format_mouse_event_kind(kind: MouseEventKind) -> String {
+    let button_name = |button| match button {
+        MouseButton::Left => "left",
+        MouseButton::Right => "right",
+        MouseButton::Middle => "middle",
+    };
+
+    match kind {
+        MouseEventKind::Down(button) => format!("down:{}", button_name(button)),
+        MouseEventKind::Up(button) => format!("up:{}", button_name(button)),
+        MouseEventKind::Drag(button) => format!("drag:{}", button_name(button)),
+        MouseEventKind::Moved => "moved".to_string(),
+        MouseEventKind::ScrollDown => "scrolldown".to_string(),
+        MouseEventKind::ScrollUp => "scrollup".to_string(),
+        MouseEventKind::ScrollLeft => "scrollleft".to_string(),
+        MouseEventKind::ScrollRight => "scrollright".to_string(),
+    }
+}
+
+/// Parses a kind of mouse event written by `format_mouse_event_kind`.
+fn parse_mouse_event_kind(name: &str) -> Option<MouseEventKind> {
+    if let Some((kind, button)) = name.split_once(':') {
+        let button = match button {
+            "left" => MouseButton::Left,
+            "right" => MouseButton::Right,
+            "middle" => MouseButton::Middle,
+            _ => return None,
+        };
+
+        return match kind {
+            "down" => Some(MouseEventKind::Down(button)),
+            "up" => Some(MouseEventKind::Up(button)),
+            "drag" => Some(MouseEventKind::Drag(button)),
+            _ => None,
+        };
+    }
+
+    match name {
+        "moved" => Some(MouseEventKind::Moved),
+        "scrolldown" => Some(MouseEventKind::ScrollDown),
+        "scrollup" => Some(MouseEventKind::ScrollUp),
+        "scrollleft" => Some(MouseEventKind::ScrollLeft),
+        "scrollright" => Some(MouseEventKind::ScrollRight),
+        _ => None,
+    }
+}
+
+/// Escapes pasted text so it fits on one line.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+/// Reverts `escape`.
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+
+        unescaped.push(match chars.next()? {
+            '\\' => '\\',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            _ => return None,
+        });
+    }
+
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::Resize(80, 24),
+            Event::Key(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE)),
+            Event::Key(KeyEvent::new(KeyCode::Char('é'), KeyModifiers::SHIFT)),
+            Event::Key(KeyEvent::new_with_kind(
+                KeyCode::F(5),
+                KeyModifiers::CONTROL | KeyModifiers::ALT,
+                KeyEventKind::Release,
+            )),
+            Event::Key(KeyEvent::new(KeyCode::PageDown, KeyModifiers::NONE)),
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Drag(MouseButton::Middle),
+                column: 3,
+                row: 14,
+                modifiers: KeyModifiers::SHIFT,
+            }),
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::ScrollLeft,
+                column: 0,
+                row: 0,
+                modifiers: KeyModifiers::NONE,
+            }),
+            Event::Paste("a\\b\tc\r\nd ".to_string()),
+            Event::FocusLost,
+        ]
+    }
+
+    #[test]
+    fn events_round_trip() {
+        for event in events() {
+            let line = format_event(&event).unwrap();
+            assert!(!line.contains(['\n', '\r']), "{line:?}");
+            assert_eq!(parse_event(&line), Some(event), "{line}");
+        }
+    }
+
+    #[test]
+    fn skips_events_without_name() {
+        let event = Event::Key(KeyEvent::new(KeyCode::CapsLock, KeyModifiers::NONE));
+        assert_eq!(format_event(&event), None);
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert!(parse_recorded_event("12 key char:97 0").is_none());
+        assert!(parse_recorded_event("soon resize 80 24").is_none());
+        assert!(parse_recorded_event("0 paste \\x").is_none());
+        assert!(parse_recorded_event("0 mouse down:side 1 1 0").is_none());
+    }
+
+    #[test]
+    fn recording_round_trips() {
+        let path = env::temp_dir().join(format!("vyse-recording-{}", process::id()));
+        let mut recorder = Recorder::create(&path).unwrap();
+        for event in events() {
+            recorder.record(&event).unwrap();
+        }
+
+        let loaded = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let loaded: Vec<Event> = loaded.into_iter().map(|recorded| recorded.event).collect();
+        assert_eq!(loaded, events());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = env::temp_dir().join(format!("vyse-not-a-recording-{}", process::id()));
+        fs::write(&path, "0 resize 80 24\n").unwrap();
+
+        let err = load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
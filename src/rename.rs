@@ -0,0 +1,118 @@
+use crate::completion::{get_word_bounds, is_word_char};
+use vyse_core::{
+    buffer::{Buffer, Location},
+    line::Line,
+};
+
+/// Editing all occurrences of a word at once, as if there was a cursor in each of them.
+#[derive(Debug)]
+pub struct Rename {
+    /// The start of every occurrence, in the order they appear in the buffer.
+    pub occurrences: Vec<Location>,
+    /// The length of the occurrences in grapheme clusters.
+    pub length: usize,
+}
+
+impl Rename {
+    /// Finds all occurrences of the word under the cursor, skipping those inside longer words.
+    pub fn from_buffer(buffer: &Buffer, cursor: Location) -> Option<Self> {
+        let line = buffer.get_line(cursor.row)?;
+        let cursor_byte = line.byte_index(cursor.col);
+
+        let (start, end) = get_word_bounds(&line, cursor_byte);
+
+        if start == end {
+            return None;
+        }
+
+        let word = &line[start..end];
+        let mut occurrences = Vec::new();
+
+        for (row, line) in buffer.lines().enumerate() {
+            for (idx, _) in line.match_indices(word) {
+                let is_word_before = line[..idx].chars().next_back().is_some_and(is_word_char);
+                let is_word_after = line[idx + word.len()..]
+                    .chars()
+                    .next()
+                    .is_some_and(is_word_char);
+
+                if !is_word_before && !is_word_after {
+                    occurrences.push(Location {
+                        row,
+                        col: line.grapheme_index(idx),
+                    });
+                }
+            }
+        }
+
+        Some(Self {
+            occurrences,
+            length: Line::new(word).grapheme_count(),
+        })
+    }
+
+    /// Returns the index of the occurrence the location lies in, including its end.
+    pub fn occurrence_at(&self, location: Location) -> Option<usize> {
+        self.occurrences.iter().position(|occurrence| {
+            occurrence.row == location.row
+                && (occurrence.col..=occurrence.col + self.length).contains(&location.col)
+        })
+    }
+
+    /// Inserts text at the given column of every occurrence.
+    pub fn insert(&mut self, buffer: &mut Buffer, offset: usize, text: &str) {
+        let mut inserted = 0;
+
+        self.edit_each(|occurrence| {
+            let location = Location {
+                row: occurrence.row,
+                col: occurrence.col + offset,
+            };
+            inserted = buffer.insert_str(location, text).col - location.col;
+            inserted.cast_signed()
+        });
+
+        self.length += inserted;
+    }
+
+    /// Deletes the grapheme cluster before the given column of every occurrence.
+    pub fn delete_before(&mut self, buffer: &mut Buffer, offset: usize) {
+        if offset == 0 {
+            return;
+        }
+
+        self.edit_each(|occurrence| {
+            let end = Location {
+                row: occurrence.row,
+                col: occurrence.col + offset,
+            };
+            buffer.delete(
+                Location {
+                    col: end.col - 1,
+                    ..end
+                },
+                end,
+            );
+            -1
+        });
+
+        self.length -= 1;
+    }
+
+    /// Edits every occurrence in order. The edit returns the change in length in grapheme
+    /// clusters, by which the later occurrences on the same line are moved.
+    fn edit_each(&mut self, mut edit: impl FnMut(Location) -> isize) {
+        let mut previous_row = None;
+        let mut shift = 0;
+
+        for occurrence in &mut self.occurrences {
+            if previous_row != Some(occurrence.row) {
+                previous_row = Some(occurrence.row);
+                shift = 0;
+            }
+
+            occurrence.col = occurrence.col.saturating_add_signed(shift);
+            shift += edit(*occurrence);
+        }
+    }
+}
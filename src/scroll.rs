@@ -0,0 +1,20 @@
+/// How far the view moves for each mouse wheel or touchpad scroll event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrollSettings {
+    /// The number of lines scrolled up or down.
+    pub vertical_step: usize,
+    /// The number of columns scrolled sideways.
+    pub horizontal_step: usize,
+    /// Moves the content in the direction of the wheel or swipe instead of the view.
+    pub natural: bool,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        Self {
+            vertical_step: 3,
+            horizontal_step: 4,
+            natural: false,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use vyse_core::{
+    buffer::{Buffer, Location},
+    line::Line,
+    snippet::Snippet,
+};
+
+/// A placeholder of an inserted snippet together with the placeholders mirroring it.
+#[derive(Debug)]
+pub struct Field {
+    /// The start of every occurrence, in the order they appear in the buffer.
+    pub occurrences: Vec<Location>,
+    /// The length of the occurrences in grapheme clusters.
+    pub length: usize,
+}
+
+/// Filling in the placeholders of an inserted snippet one after another, editing the mirrors of
+/// a placeholder along with it.
+#[derive(Debug)]
+pub struct SnippetSession {
+    /// The fields in the order they are visited, ending with the final cursor position.
+    pub fields: Vec<Field>,
+    /// The index of the visited field.
+    pub active: usize,
+    /// Whether the visited field still holds its default, which typing replaces.
+    pub replaces_default: bool,
+}
+
+impl SnippetSession {
+    /// Creates the session for a snippet that was inserted at a location.
+    pub fn new(buffer: &Buffer, start: Location, snippet: &Snippet) -> Self {
+        let mut numbers: Vec<usize> = snippet
+            .placeholders
+            .iter()
+            .map(|(number, _)| *number)
+            .collect();
+        // `$0` is visited last.
+        numbers.sort_by_key(|number| (*number == 0, *number));
+        numbers.dedup();
+
+        let mut fields: Vec<Field> = numbers
+            .iter()
+            .map(|number| {
+                let mut length = 0;
+                let occurrences = snippet
+                    .placeholders
+                    .iter()
+                    .filter(|(other, _)| other == number)
+                    .map(|(_, range)| {
+                        length = Line::new(&snippet.text[range.clone()]).grapheme_count();
+                        get_location(buffer, start, &snippet.text, range.start)
+                    })
+                    .collect();
+
+                Field {
+                    occurrences,
+                    length,
+                }
+            })
+            .collect();
+
+        if numbers.last() != Some(&0) {
+            fields.push(Field {
+                occurrences: vec![get_location(
+                    buffer,
+                    start,
+                    &snippet.text,
+                    snippet.text.len(),
+                )],
+                length: 0,
+            });
+        }
+
+        Self {
+            fields,
+            active: 0,
+            replaces_default: true,
+        }
+    }
+
+    /// Returns the visited field.
+    pub fn active_field(&self) -> &Field {
+        &self.fields[self.active]
+    }
+
+    /// Returns whether the final cursor position is visited, which ends the session.
+    pub fn is_finished(&self) -> bool {
+        self.active + 1 >= self.fields.len()
+    }
+
+    /// Returns the index of the occurrence of the visited field the location lies in, including
+    /// its end.
+    pub fn occurrence_at(&self, location: Location) -> Option<usize> {
+        let field = self.active_field();
+        field.occurrences.iter().position(|occurrence| {
+            occurrence.row == location.row
+                && (occurrence.col..=occurrence.col + field.length).contains(&location.col)
+        })
+    }
+
+    /// Inserts text at the given column of every occurrence of the visited field.
+    pub fn insert(&mut self, buffer: &mut Buffer, offset: usize, text: &str) {
+        let mut inserted = 0;
+
+        self.edit_each(|occurrence| {
+            let location = Location {
+                row: occurrence.row,
+                col: occurrence.col + offset,
+            };
+            inserted = buffer.insert_str(location, text).col - location.col;
+            inserted.cast_signed()
+        });
+
+        self.fields[self.active].length += inserted;
+    }
+
+    /// Deletes the grapheme clusters between two columns of every occurrence of the visited
+    /// field.
+    pub fn delete(&mut self, buffer: &mut Buffer, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+
+        self.edit_each(|occurrence| {
+            buffer.delete(
+                Location {
+                    row: occurrence.row,
+                    col: occurrence.col + start,
+                },
+                Location {
+                    row: occurrence.row,
+                    col: occurrence.col + end,
+                },
+            );
+            -(end - start).cast_signed()
+        });
+
+        self.fields[self.active].length -= end - start;
+    }
+
+    /// Edits every occurrence of the visited field in order. The edit returns the change in
+    /// length in grapheme clusters, by which the fields after it on the same line are moved.
+    fn edit_each(&mut self, mut edit: impl FnMut(Location) -> isize) {
+        for idx in 0..self.fields[self.active].occurrences.len() {
+            let occurrence = self.fields[self.active].occurrences[idx];
+            let shift = edit(occurrence);
+
+            for location in self
+                .fields
+                .iter_mut()
+                .flat_map(|field| field.occurrences.iter_mut())
+            {
+                if location.row == occurrence.row && location.col > occurrence.col {
+                    location.col = location.col.saturating_add_signed(shift);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the location in the buffer of a byte offset into the text of a snippet inserted at
+/// `start`.
+fn get_location(buffer: &Buffer, start: Location, text: &str, offset: usize) -> Location {
+    let before = &text[..offset];
+    let row = start.row + before.matches('\n').count();
+    let Some(line) = buffer.get_line(row) else {
+        return start;
+    };
+
+    let byte_index = match before.rfind('\n') {
+        Some(newline) => offset - newline - 1,
+        None => line.byte_index(start.col) + offset,
+    };
+
+    Location {
+        row,
+        col: line.grapheme_index(byte_index),
+    }
+}
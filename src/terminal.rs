@@ -1,15 +1,20 @@
 use std::{
+    env,
     fmt::Display,
     io::{self, stdout, Write},
+    sync::OnceLock,
 };
 
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     queue,
     style::{Color, Print, SetBackgroundColor, SetForegroundColor},
-    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
+    terminal::{disable_raw_mode, enable_raw_mode, size as crossterm_size, Clear, ClearType},
 };
 
+use vyse_core::transform::Transform;
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Size {
     pub width: u16,
@@ -25,6 +30,7 @@ pub struct Position {
 pub type TResult<T> = Result<T, io::Error>;
 
 pub fn terminate() -> TResult<()> {
+    queue!(stdout(), DisableMouseCapture, DisableBracketedPaste)?;
     execute()?;
     disable_raw_mode()?;
     Ok(())
@@ -32,6 +38,7 @@ pub fn terminate() -> TResult<()> {
 
 pub fn initialize() -> TResult<()> {
     enable_raw_mode()?;
+    queue!(stdout(), EnableMouseCapture, EnableBracketedPaste)?;
     clear_screen()?;
     move_cursor_to(Position { x: 0, y: 0 })?;
     execute()
@@ -61,19 +68,162 @@ pub fn print(text: impl Display) -> TResult<()> {
     queue!(stdout(), Print(text))
 }
 
-// pub fn size() -> TResult<Size> {
-//     let (width, height) = crossterm_size()?;
-//     Ok(Size { width, height })
-// }
+/// Asks the terminal to copy text to the clipboard with an OSC 52 sequence, which reaches the
+/// clipboard of the machine the terminal runs on, e.g. the user's side of an SSH session.
+pub fn copy_to_clipboard(text: &str) -> TResult<()> {
+    queue!(
+        stdout(),
+        Print(format!("\x1b]52;c;{}\x07", Transform::Base64.encode(text)))
+    )
+}
+
+pub fn size() -> TResult<Size> {
+    let (width, height) = crossterm_size()?;
+    Ok(Size { width, height })
+}
 
 pub fn set_foreground_color(color: Color) -> TResult<()> {
-    queue!(stdout(), SetForegroundColor(color))
+    queue!(
+        stdout(),
+        SetForegroundColor(color_support().downsample(color))
+    )
 }
 
 pub fn set_background_color(color: Color) -> TResult<()> {
-    queue!(stdout(), SetBackgroundColor(color))
+    queue!(
+        stdout(),
+        SetBackgroundColor(color_support().downsample(color))
+    )
 }
 
 pub fn execute() -> TResult<()> {
     stdout().flush()
 }
+
+/// The colors the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    /// Any RGB color.
+    TrueColor,
+    /// The 256 color xterm palette.
+    Ansi256,
+    /// Only the 16 basic colors.
+    Ansi16,
+}
+
+/// Returns the colors the terminal supports, detected once from the environment.
+pub fn color_support() -> ColorSupport {
+    static COLOR_SUPPORT: OnceLock<ColorSupport> = OnceLock::new();
+
+    *COLOR_SUPPORT.get_or_init(|| {
+        ColorSupport::detect(
+            env::var("COLORTERM").ok().as_deref(),
+            env::var("TERM").ok().as_deref(),
+        )
+    })
+}
+
+/// The RGB values of the 16 basic colors as shown by xterm.
+const ANSI16_PALETTE: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// The channel values of the 6x6x6 color cube of the 256 color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorSupport {
+    /// Detects the color support from the `COLORTERM` and `TERM` variables. Terminals that do not
+    /// announce more are assumed to only support the basic colors.
+    pub fn detect(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let term = term.unwrap_or_default();
+
+        if matches!(colorterm, Some("truecolor" | "24bit")) || term.ends_with("-direct") {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+
+    /// Replaces a color the terminal cannot show with the closest one it can.
+    pub fn downsample(self, color: Color) -> Color {
+        match (self, color) {
+            (Self::Ansi256, Color::Rgb { r, g, b }) => Color::AnsiValue(closest_ansi256((r, g, b))),
+            (Self::Ansi16, Color::Rgb { r, g, b }) => closest_ansi16((r, g, b)),
+            (Self::Ansi16, Color::AnsiValue(value)) => closest_ansi16(ansi256_to_rgb(value)),
+            (_, color) => color,
+        }
+    }
+}
+
+/// Returns the squared distance between two RGB colors.
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    [(r1, r2), (g1, g2), (b1, b2)]
+        .into_iter()
+        .map(|(a, b)| u32::from(a.abs_diff(b)).pow(2))
+        .sum()
+}
+
+/// Returns the basic color closest to an RGB color.
+fn closest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    ANSI16_PALETTE
+        .iter()
+        .min_by_key(|(_, palette_rgb)| distance(rgb, *palette_rgb))
+        .map_or(Color::White, |(color, _)| *color)
+}
+
+/// Returns the index of the color of the 256 color palette closest to an RGB color, either from
+/// the color cube or the grayscale ramp.
+fn closest_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |channel: u8| {
+        (0u8..6)
+            .min_by_key(|&idx| CUBE_LEVELS[usize::from(idx)].abs_diff(channel))
+            .unwrap_or_default()
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = 16 + 36 * r + 6 * g + b;
+
+    let average = (u16::from(rgb.0) + u16::from(rgb.1) + u16::from(rgb.2)) / 3;
+    let gray = 232
+        + u8::try_from(average.saturating_sub(3) / 10)
+            .unwrap_or(23)
+            .min(23);
+
+    if distance(rgb, ansi256_to_rgb(gray)) < distance(rgb, ansi256_to_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Returns the RGB value of a color of the 256 color palette.
+fn ansi256_to_rgb(value: u8) -> (u8, u8, u8) {
+    match value {
+        0..16 => ANSI16_PALETTE[usize::from(value)].1,
+        16..232 => {
+            let idx = value - 16;
+            let level = |component: u8| CUBE_LEVELS[usize::from(component)];
+            (level(idx / 36), level(idx / 6 % 6), level(idx % 6))
+        }
+        _ => {
+            let gray = 8 + (value - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::fs;
+
+use crossterm::style::Color;
+
+use vyse_core::{highlight::Style, persistence};
+
+/// The colors the editor is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub text: Color,
+    pub background: Color,
+    /// The background of the line the cursor is on.
+    pub current_line: Color,
+    /// The line numbers and the markers for text beyond the edges of the view.
+    pub gutter: Color,
+    pub status_bar_text: Color,
+    pub status_bar_background: Color,
+    /// The background of the bracket under the cursor and its match.
+    pub matching_bracket: Color,
+    /// The symbols shown in place of whitespace.
+    pub whitespace: Color,
+    /// The background of selected text.
+    pub selection: Color,
+    /// The background of the lines pinned to the top of the view.
+    pub sticky_header: Color,
+    /// Control characters, which are shown in caret notation.
+    pub control_char: Color,
+    /// Lists and panels drawn on top of the buffer, like the completion popup.
+    pub popup_text: Color,
+    pub popup_background: Color,
+    /// The selected entry of popups and of the directory listing.
+    pub popup_selected_text: Color,
+    pub popup_selected_background: Color,
+    /// The occurrences of a word being renamed and the placeholders of a snippet.
+    pub occurrence_text: Color,
+    pub occurrence_background: Color,
+    pub keyword: Color,
+    pub type_name: Color,
+    pub string: Color,
+    pub number: Color,
+    pub comment: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            text: Color::White,
+            background: Color::Black,
+            current_line: Color::AnsiValue(236),
+            gutter: Color::DarkGrey,
+            status_bar_text: Color::Black,
+            status_bar_background: Color::White,
+            whitespace: Color::DarkGrey,
+            matching_bracket: Color::DarkCyan,
+            selection: Color::AnsiValue(24),
+            sticky_header: Color::AnsiValue(238),
+            control_char: Color::Red,
+            popup_text: Color::White,
+            popup_background: Color::DarkGrey,
+            popup_selected_text: Color::Black,
+            popup_selected_background: Color::Cyan,
+            occurrence_text: Color::Black,
+            occurrence_background: Color::Cyan,
+            keyword: Color::Magenta,
+            type_name: Color::Yellow,
+            string: Color::Green,
+            number: Color::Cyan,
+            comment: Color::DarkGrey,
+        }
+    }
+}
+
+impl Theme {
+    /// Loads the theme `themes/<name>.toml` from the config directory, `default` is the built-in
+    /// theme.
+    pub fn load(name: &str) -> Result<Self, String> {
+        if name == "default" {
+            return Ok(Self::default());
+        }
+
+        let path = persistence::get_config_dir()
+            .ok_or_else(|| "no config directory".to_string())?
+            .join("themes")
+            .join(format!("{name}.toml"));
+        let text = fs::read_to_string(&path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+
+        Self::parse(&text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// Parses a theme file, colors it leaves out keep their default.
+    ///
+    /// Theme files are a subset of TOML: colors are set in the `[ui]` and `[syntax]` tables to
+    /// either a name like `"dark_grey"`, a hex code like `"#1e1e2e"` or an ANSI color number.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut theme = Self::default();
+        let mut table = String::new();
+
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                table = name.trim().to_string();
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected key = value", idx + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            let color = parse_color(value)
+                .ok_or_else(|| format!("line {}: invalid color {value}", idx + 1))?;
+            *theme
+                .color_mut(&table, key)
+                .ok_or_else(|| format!("line {}: unknown key {key} in [{table}]", idx + 1))? =
+                color;
+        }
+
+        Ok(theme)
+    }
+
+    /// Returns the color of a kind of highlighted text.
+    pub fn syntax_color(&self, style: Style) -> Color {
+        match style {
+            Style::Keyword => self.keyword,
+            Style::Type => self.type_name,
+            Style::String => self.string,
+            Style::Number => self.number,
+            Style::Comment => self.comment,
+            Style::Whitespace => self.whitespace,
+            Style::Control => self.control_char,
+        }
+    }
+
+    /// Returns the color set by a key of a theme file.
+    fn color_mut(&mut self, table: &str, key: &str) -> Option<&mut Color> {
+        match (table, key) {
+            ("ui", "text") => Some(&mut self.text),
+            ("ui", "background") => Some(&mut self.background),
+            ("ui", "current_line") => Some(&mut self.current_line),
+            ("ui", "gutter") => Some(&mut self.gutter),
+            ("ui", "status_bar_text") => Some(&mut self.status_bar_text),
+            ("ui", "status_bar_background") => Some(&mut self.status_bar_background),
+            ("ui", "whitespace") => Some(&mut self.whitespace),
+            ("ui", "matching_bracket") => Some(&mut self.matching_bracket),
+            ("ui", "selection") => Some(&mut self.selection),
+            ("ui", "sticky_header") => Some(&mut self.sticky_header),
+            ("ui", "control_char") => Some(&mut self.control_char),
+            ("ui", "popup_text") => Some(&mut self.popup_text),
+            ("ui", "popup_background") => Some(&mut self.popup_background),
+            ("ui", "popup_selected_text") => Some(&mut self.popup_selected_text),
+            ("ui", "popup_selected_background") => Some(&mut self.popup_selected_background),
+            ("ui", "occurrence_text") => Some(&mut self.occurrence_text),
+            ("ui", "occurrence_background") => Some(&mut self.occurrence_background),
+            ("syntax", "keyword") => Some(&mut self.keyword),
+            ("syntax", "type") => Some(&mut self.type_name),
+            ("syntax", "string") => Some(&mut self.string),
+            ("syntax", "number") => Some(&mut self.number),
+            ("syntax", "comment") => Some(&mut self.comment),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a color value, a quoted name or hex code or an unquoted ANSI color number, followed by
+/// an optional comment.
+fn parse_color(value: &str) -> Option<Color> {
+    let Some(quoted) = value.strip_prefix('"') else {
+        let number = value.split('#').next()?.trim();
+        return number.parse().ok().map(Color::AnsiValue);
+    };
+
+    let (color, rest) = quoted.split_once('"')?;
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return None;
+    }
+
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |idx: usize| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok();
+        return (hex.len() == 6).then_some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+
+    Color::try_from(color).ok()
+}
@@ -0,0 +1,172 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    popup::Popup,
+    terminal::{Position, Size, TResult},
+    theme::Theme,
+};
+use vyse_core::{
+    cancellation::CancellationToken,
+    paths::{self, PathDisplay, WalkOptions},
+};
+
+/// The comment tags collected unless the `todotags` option is set.
+pub const DEFAULT_TAGS: [&str; 3] = ["TODO", "FIXME", "HACK"];
+
+/// Files larger than this are not scanned.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// The maximum number of collected tags, so a huge project does not fill the memory.
+const MAX_ITEMS: usize = 1000;
+
+/// A line containing a comment tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoItem {
+    pub path: PathBuf,
+    pub row: usize,
+    /// The text from the tag to the end of the line.
+    pub text: String,
+}
+
+/// A list of the tagged comments of a project grouped by file, shown at the top of the screen.
+#[derive(Debug, Default)]
+pub struct TodoPanel {
+    pub items: Vec<TodoItem>,
+    pub selected: usize,
+    pub scroll: usize,
+    /// The directory that is scanned.
+    pub root: PathBuf,
+    /// The files are being scanned on a worker thread.
+    pub scanning: bool,
+}
+
+impl TodoPanel {
+    /// Creates an empty panel for a directory, which is filled once it was scanned.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            items: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            root: root.to_path_buf(),
+            scanning: true,
+        }
+    }
+
+    /// Replaces the items with the result of a scan, keeping the selection if possible.
+    pub fn set_items(&mut self, items: Vec<TodoItem>) {
+        self.items = items;
+        self.scanning = false;
+        self.move_selection(0);
+    }
+
+    /// Moves the selection by the given number of items, stopping at either end.
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.items.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// Returns the selected item.
+    pub fn selected_item(&self) -> Option<&TodoItem> {
+        self.items.get(self.selected)
+    }
+
+    /// Renders the items below the path of their file.
+    pub fn render(&mut self, screen: Size, theme: &Theme) -> TResult<()> {
+        let mut lines = Vec::new();
+        let mut selected_line = None;
+
+        for (idx, item) in self.items.iter().enumerate() {
+            if idx == 0 || self.items[idx - 1].path != item.path {
+                lines.push(PathDisplay::Relative.format(&item.path, &self.root));
+            }
+            if idx == self.selected {
+                selected_line = Some(lines.len());
+            }
+            lines.push(format!("  {:>5}  {}", item.row + 1, item.text));
+        }
+
+        if lines.is_empty() {
+            lines.push(if self.scanning {
+                " scanning...".to_string()
+            } else {
+                " no tagged comments found".to_string()
+            });
+        }
+
+        let height = lines.len().min(screen.height.saturating_sub(1) as usize);
+
+        // Keep the selected item and the path above it visible.
+        if let Some(selected_line) = selected_line {
+            if selected_line <= self.scroll {
+                self.scroll = selected_line.saturating_sub(1);
+            } else if selected_line >= self.scroll + height {
+                self.scroll = selected_line + 1 - height;
+            }
+        }
+
+        let popup = Popup {
+            position: Position { x: 0, y: 0 },
+            size: Size {
+                width: screen.width,
+                #[allow(clippy::cast_possible_truncation)]
+                height: height as u16,
+            },
+        };
+
+        popup.render(
+            &lines[self.scroll..],
+            selected_line.map(|line| line - self.scroll),
+            theme,
+        )
+    }
+}
+
+/// Scans the files below a directory for lines containing one of the tags, stopping early if the
+/// token gets cancelled. Files that are large or not text are skipped.
+pub fn scan(
+    root: &Path,
+    tags: &[String],
+    options: &WalkOptions,
+    token: &CancellationToken,
+) -> Vec<TodoItem> {
+    let mut items = Vec::new();
+
+    paths::walk(root, options, |path| {
+        let is_small = fs::metadata(path).is_ok_and(|metadata| metadata.len() <= MAX_FILE_SIZE);
+        if let Some(content) = is_small.then(|| fs::read_to_string(path).ok()).flatten() {
+            for (row, line) in content.lines().enumerate() {
+                if let Some(start) = find_tag(line, tags) {
+                    items.push(TodoItem {
+                        path: path.to_path_buf(),
+                        row,
+                        text: line[start..].trim_end().to_string(),
+                    });
+                }
+            }
+        }
+
+        items.len() < MAX_ITEMS && !token.is_cancelled()
+    });
+
+    items.truncate(MAX_ITEMS);
+    items
+}
+
+/// Returns where the first tag in a line starts, only matching whole words.
+fn find_tag(line: &str, tags: &[String]) -> Option<usize> {
+    let is_word_char = |ch: char| ch.is_alphanumeric() || ch == '_';
+
+    tags.iter()
+        .filter(|tag| !tag.is_empty())
+        .flat_map(|tag| {
+            line.match_indices(tag.as_str()).filter(|(start, tag)| {
+                !line[..*start].ends_with(is_word_char)
+                    && !line[start + tag.len()..].starts_with(is_word_char)
+            })
+        })
+        .map(|(start, _)| start)
+        .min()
+}
@@ -0,0 +1,87 @@
+use std::{
+    env,
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use vyse_core::datetime::DateTime;
+
+/// Moves a file into the trash following the freedesktop.org trash specification.
+pub fn move_to_trash(path: &Path) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let trash_dir = get_trash_dir().ok_or(io::ErrorKind::NotFound)?;
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    fs::create_dir_all(&files_dir)?;
+    fs::create_dir_all(&info_dir)?;
+
+    let file_name = path
+        .file_name()
+        .ok_or(io::ErrorKind::InvalidInput)?
+        .to_string_lossy()
+        .into_owned();
+
+    // Reserve a unique name by creating its info file, then move the file itself.
+    for attempt in 1.. {
+        let name = if attempt == 1 {
+            file_name.clone()
+        } else {
+            format!("{file_name}.{attempt}")
+        };
+
+        let info_file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(info_dir.join(format!("{name}.trashinfo")));
+
+        let mut info_file = match info_file {
+            Ok(info_file) => info_file,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        };
+
+        write!(
+            info_file,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode(&path.to_string_lossy()),
+            DateTime::now().to_iso8601()
+        )?;
+
+        let target = files_dir.join(&name);
+        if fs::rename(&path, &target).is_err() {
+            // Renaming fails across file systems, fall back to copying.
+            fs::copy(&path, &target)?;
+            fs::remove_file(&path)?;
+        }
+
+        return Ok(());
+    }
+
+    unreachable!()
+}
+
+/// Returns the trash directory of the current user.
+fn get_trash_dir() -> Option<PathBuf> {
+    let data_dir = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+
+    Some(data_dir.join("Trash"))
+}
+
+/// Percent-encodes a path for use in a trash info file.
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,143 @@
+use std::time::SystemTime;
+
+use crate::{
+    popup::Popup,
+    terminal::{Position, Size, TResult},
+    theme::Theme,
+};
+use vyse_core::history::{Edit, History, Transaction};
+
+/// The width of the panel.
+const PANEL_WIDTH: u16 = 48;
+/// The number of characters of changed text shown in a summary.
+const SUMMARY_LENGTH: usize = 16;
+
+/// A list of the states of the undo history, shown at the right edge of the screen.
+#[derive(Debug, Default)]
+pub struct UndoPanel {
+    /// The selected state, which is the number of transactions applied to reach it.
+    pub selected: usize,
+    pub scroll: usize,
+}
+
+impl UndoPanel {
+    /// Opens the panel with the current state selected.
+    pub fn new(history: &History) -> Self {
+        Self {
+            selected: history.current_state(),
+            scroll: 0,
+        }
+    }
+
+    /// Moves the selection by the given number of states, stopping at either end.
+    pub fn move_selection(&mut self, delta: isize, history: &History) {
+        let last = history.transactions().count();
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// Renders the states from the newest to the oldest, marking the current one.
+    pub fn render(&mut self, history: &History, screen: Size, theme: &Theme) -> TResult<()> {
+        let now = SystemTime::now();
+        let mut lines = vec![" 0   original".to_string()];
+
+        for (idx, transaction) in history.transactions().enumerate() {
+            lines.push(format!(
+                " {:<3} {:>8}  {} (line {})",
+                idx + 1,
+                format_age(transaction, now),
+                summarize(transaction),
+                transaction
+                    .edits
+                    .first()
+                    .map_or(0, |edit| edit.location().row)
+                    + 1,
+            ));
+        }
+
+        let current = history.current_state();
+        lines[current].replace_range(..1, "*");
+        lines.reverse();
+
+        let height = lines.len().min(screen.height.saturating_sub(1) as usize);
+        let selected_row = lines.len() - 1 - self.selected;
+
+        // Keep the selected state visible.
+        if selected_row < self.scroll {
+            self.scroll = selected_row;
+        } else if selected_row >= self.scroll + height {
+            self.scroll = selected_row + 1 - height;
+        }
+
+        let popup = Popup {
+            position: Position {
+                x: screen.width.saturating_sub(PANEL_WIDTH),
+                y: 0,
+            },
+            size: Size {
+                width: PANEL_WIDTH.min(screen.width),
+                #[allow(clippy::cast_possible_truncation)]
+                height: height as u16,
+            },
+        };
+
+        popup.render(
+            &lines[self.scroll..],
+            Some(selected_row - self.scroll),
+            theme,
+        )
+    }
+}
+
+/// Describes the text inserted or deleted by a transaction.
+fn summarize(transaction: &Transaction) -> String {
+    let inserted: String = transaction
+        .edits
+        .iter()
+        .filter_map(|edit| match edit {
+            Edit::Insert { text, .. } => Some(text.as_str()),
+            Edit::Delete { .. } => None,
+        })
+        .collect();
+    let deleted: String = transaction
+        .edits
+        .iter()
+        .filter_map(|edit| match edit {
+            Edit::Delete { text, .. } => Some(text.as_str()),
+            Edit::Insert { .. } => None,
+        })
+        .collect();
+
+    match (inserted.is_empty(), deleted.is_empty()) {
+        (false, true) => format!("+{}", shorten(&inserted)),
+        (true, false) => format!("-{}", shorten(&deleted)),
+        _ => format!("{} edits", transaction.edits.len()),
+    }
+}
+
+/// Shortens text for a summary, showing newlines as `\n`.
+fn shorten(text: &str) -> String {
+    let mut short: String = text.chars().take(SUMMARY_LENGTH).collect();
+    if short.len() < text.len() {
+        short.push('…');
+    }
+
+    format!("{short:?}")
+}
+
+/// Formats how long ago a transaction was created, e.g. `5m ago`.
+fn format_age(transaction: &Transaction, now: SystemTime) -> String {
+    let Some(age) = transaction
+        .created_at
+        .and_then(|created_at| now.duration_since(created_at).ok())
+    else {
+        return "-".to_string();
+    };
+
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
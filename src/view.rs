@@ -1,180 +1,3623 @@
-use std::path::PathBuf;
+use std::{
+    cmp::Ordering,
+    collections::VecDeque,
+    fs, iter, mem,
+    ops::{Range, RangeInclusive},
+    path::{self, Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{
+        Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
+    style::Color,
+};
+
+use crate::{
+    bookmarks::{Bookmark, BookmarkPicker, Bookmarks},
+    clipboard::{self, Clipboard, ClipboardPicker},
+    completion::{get_word_bounds, Completion},
+    executable::{self, ExecutablePolicy},
+    explorer::Explorer,
+    file_drop::FileDrop,
+    open_files,
+    progress::Progress,
+    prompt::{Prompt, PromptResult},
+    rename::Rename,
+    scroll::ScrollSettings,
+    snippet_session::SnippetSession,
+    terminal::{self, Position, Size, TResult},
+    theme::Theme,
+    todo_panel::{TodoItem, TodoPanel},
+    trash,
+    undo_panel::UndoPanel,
+};
+use vyse_core::{
+    bidi, brackets,
+    buffer::{format_byte_size, Buffer, Location},
+    checksum::Algorithm,
+    command::{self, Command},
+    continuation::{self, Continuation},
+    datetime::DateTime,
+    encoding::Encoding,
+    expression, header, hex, highlight, indent,
+    line::{Line, TruncatedLine, VisibleWhitespace},
+    line_ending::LineEnding,
+    markdown, number,
+    paths::{self, PathDisplay, WalkOptions},
+    random, scope, section,
+    snippet::{self, Snippet},
+};
+
+/// The number of cells between the end of a line and the note of its bookmark.
+const NOTE_SPACING: usize = 2;
+
+/// The modifiers that extend a block selection with the movement keys, Shift extends a normal
+/// selection.
+const BLOCK_SELECTION_MODIFIERS: KeyModifiers = KeyModifiers::SHIFT.union(KeyModifiers::ALT);
+
+/// The longest time between clicks at the same position that counts as a double or triple
+/// click.
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// The maximum number of lines pinned to the top of the view by the sticky header.
+const MAX_STICKY_LINES: usize = 3;
+
+/// Where to place the cursor in the first file, given on the command line as `+LINE` or
+/// `+/pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPosition {
+    /// A line number starting at 1.
+    Line(usize),
+    /// The first occurrence of the text.
+    Pattern(String),
+}
+
+impl StartPosition {
+    /// Parses a `+` argument, a plain `+` moves to the last line.
+    pub fn parse(argument: &str) -> Option<Self> {
+        let argument = argument.strip_prefix('+')?;
+
+        if argument.is_empty() {
+            Some(Self::Line(usize::MAX))
+        } else if let Some(pattern) = argument.strip_prefix('/') {
+            Some(Self::Pattern(pattern.to_string()))
+        } else {
+            argument.parse().ok().map(Self::Line)
+        }
+    }
+}
+
+/// An open buffer that is not shown, together with where the view was when it was hidden.
+#[derive(Debug)]
+pub struct BackgroundBuffer {
+    pub buffer: Buffer,
+    pub cursor_location: Location,
+    pub scroll_offset: Location,
+}
+
+#[derive(Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct View {
+    pub buffer: Buffer,
+    /// Where the selection started, it extends from here to the cursor.
+    pub selection_anchor: Option<Location>,
+    /// Whether the selection is the rectangle between the anchor and the cursor rather than the
+    /// text between them.
+    pub block_selection: bool,
+    /// The time and position of the last click and how many clicks in a row it ended.
+    pub last_click: Option<(Instant, Position, usize)>,
+    /// The other open buffers, in the order they are cycled through after the current one.
+    pub other_buffers: VecDeque<BackgroundBuffer>,
+    /// The buffer replaced by the start of a file that is still loading, restored if loading is
+    /// cancelled.
+    pub replaced_buffer: Option<BackgroundBuffer>,
+    pub needs_redraw: bool,
+    pub current_size: Size,
+    pub cursor_location: Location,
+    pub scroll_offset: Location,
+    pub completion: Option<Completion>,
+    pub progress: Option<Progress>,
+    pub status_message: Option<String>,
+    pub prompt: Option<Prompt>,
+    pub rename: Option<Rename>,
+    pub snippet: Option<SnippetSession>,
+    pub explorer: Option<Explorer>,
+    pub undo_panel: Option<UndoPanel>,
+    pub bookmark_picker: Option<BookmarkPicker>,
+    pub clipboard_picker: Option<ClipboardPicker>,
+    pub todo_panel: Option<TodoPanel>,
+    /// A directory to collect the tagged comments of on a worker thread.
+    pub todo_scan_request: Option<PathBuf>,
+    /// Which files are visited by operations on the whole project, like the TODO panel.
+    pub walk_options: WalkOptions,
+    pub clipboard: Clipboard,
+    /// The comment tags collected by the TODO panel.
+    pub todo_tags: Vec<String>,
+    /// The names of the filetypes whose lists and comments are continued when pressing Enter.
+    pub continued_filetypes: Vec<String>,
+    /// The bookmarks of the project containing the working directory.
+    pub bookmarks: Bookmarks,
+    /// A file chosen in the explorer, which the editor loads.
+    pub open_request: Option<PathBuf>,
+    /// The row to move the cursor to once the requested file is loaded.
+    pub open_request_row: Option<usize>,
+    /// Files the editor loads into background buffers, e.g. dropped onto the terminal.
+    pub background_open_requests: Vec<PathBuf>,
+    /// Dropped files waiting for the user to confirm opening them.
+    pub pending_drop: Option<FileDrop>,
+    /// Makes `delete` remove files instead of moving them to the trash.
+    pub permanent_delete: bool,
+    pub working_dir: PathBuf,
+    pub path_display: PathDisplay,
+    /// The file shown before the current one, `#` on the command line expands to it.
+    pub alternate_path: Option<PathBuf>,
+    pub executable_policy: ExecutablePolicy,
+    /// Draws the line the cursor is on with a different background.
+    pub highlight_current_line: bool,
+    /// Ends changed files with a line ending when saving, even if they did not have one.
+    pub insert_final_newline: bool,
+    /// Removes the empty lines at the end of changed files when saving.
+    pub trim_final_newlines: bool,
+    /// Pins the lines opening the scopes around the top of the view to its first rows, or the
+    /// headings of the sections around it in Markdown files.
+    pub sticky_header: bool,
+    /// Only pins the definitions of functions and types around the top of the view, rather than
+    /// every scope like loops and conditions.
+    pub sticky_definitions: bool,
+    /// Lines up the columns of Markdown tables while typing in them.
+    pub format_tables: bool,
+    /// The number of lines kept visible above and below the cursor.
+    pub scroll_off: usize,
+    /// The colors of the text, gutter, status bar and highlighting.
+    pub theme: Theme,
+    /// The format of dates inserted by `date`, see [`DateTime::format`].
+    pub date_format: String,
+    /// The format of times inserted by `time`.
+    pub time_format: String,
+    /// Pastes the primary selection where the middle mouse button is clicked.
+    pub middle_click_paste: bool,
+    pub scroll_settings: ScrollSettings,
+    /// Which whitespace is shown as symbols.
+    pub visible_whitespace: VisibleWhitespace,
+    /// Shows line numbers next to the buffer.
+    pub line_numbers: bool,
+    /// Warns when saving a file that other processes have open, e.g. a log being tailed.
+    pub warn_open_files: bool,
+    /// Updates "Last modified" dates and copyright years in the header of a file when saving it.
+    pub update_header: bool,
+    /// A newly saved script waiting for the user to confirm making it executable.
+    pub pending_executable: Option<PathBuf>,
+}
+
+impl View {
+    /// Replaces the buffer shown in the view.
+    pub fn set_buffer(&mut self, buffer: Buffer) {
+        let previous = mem::replace(&mut self.buffer, buffer);
+        let cursor_location = mem::take(&mut self.cursor_location);
+        let scroll_offset = mem::take(&mut self.scroll_offset);
+
+        // The cursor stays where it was moved to while the start of the file was shown.
+        let previous = if previous.partial && previous.path == self.buffer.path {
+            self.scroll_offset = scroll_offset;
+            self.move_cursor_to(cursor_location);
+            self.replaced_buffer.take().map(|replaced| replaced.buffer)
+        } else {
+            self.selection_anchor = None;
+            self.snippet = None;
+            (!previous.partial).then_some(previous)
+        };
+
+        if self.buffer.partial {
+            if let Some(buffer) = previous {
+                self.replaced_buffer = Some(BackgroundBuffer {
+                    buffer,
+                    cursor_location,
+                    scroll_offset,
+                });
+            }
+        } else {
+            self.replaced_buffer = None;
+            if let Some(path) = previous.and_then(|previous| previous.path) {
+                self.alternate_path = Some(path);
+            }
+        }
+
+        self.needs_redraw = true;
+
+        let mut notes = Vec::new();
+        if self.buffer.safe_mode {
+            notes.push("opened in safe mode, use `set nosafemode` to override");
+        }
+
+        // Making a lossy buffer writable does not help if the file cannot be written either.
+        match (self.buffer.lossy, self.buffer.write_protected) {
+            (true, true) => notes.push("invalid UTF-8 replaced and no write permission"),
+            (true, false) => notes.push("invalid UTF-8 replaced, `set noreadonly` to edit"),
+            (false, true) => notes.push("no write permission, `set write` to edit"),
+            (false, false) => (),
+        }
+
+        let line_ending_note = format!(
+            "mixed line endings, saving converts them to {}",
+            self.buffer.line_ending
+        );
+        if self.buffer.mixed_line_endings {
+            notes.push(&line_ending_note);
+        }
+
+        if !notes.is_empty() {
+            self.status_message = Some(notes.join("; "));
+        }
+    }
+
+    /// Rendes the whole view to the screen.
+    pub fn render(&mut self) -> TResult<()> {
+        if !self.needs_redraw || !self.is_of_sufficient_size() {
+            return Ok(());
+        }
+
+        if let Some(explorer) = &self.explorer {
+            explorer.render(self.current_size, &self.theme)?;
+
+            #[allow(clippy::cast_possible_truncation)]
+            let y = explorer.selected.saturating_sub(explorer.scroll) as u16;
+            self.render_status_bar()?;
+            terminal::move_cursor_to(Position { x: 0, y })?;
+
+            self.needs_redraw = false;
+            return Ok(());
+        }
+
+        self.sync_folds();
+        self.render_buffer()?;
+        self.render_selection()?;
+        self.render_matching_brackets()?;
+        self.render_sticky_header()?;
+        self.render_status_bar()?;
+
+        if let Some(rename) = &self.rename {
+            self.render_occurrences(&rename.occurrences, rename.length)?;
+        }
+
+        if let Some(snippet) = &self.snippet {
+            let field = snippet.active_field();
+            self.render_occurrences(&field.occurrences, field.length)?;
+        }
+
+        if let Some(completion) = &self.completion {
+            let cursor = self.get_relative_cursor_position();
+            let prefix_width = Line::new(completion.prefix.as_str()).width();
+
+            #[allow(clippy::cast_possible_truncation)]
+            let anchor = Position {
+                x: cursor.x.saturating_sub(prefix_width as u16),
+                y: cursor.y,
+            };
+            completion.render(anchor, self.current_size, &self.theme)?;
+        }
+
+        if let Some(undo_panel) = &mut self.undo_panel {
+            undo_panel.render(&self.buffer.history, self.current_size, &self.theme)?;
+        }
+
+        if let Some(bookmark_picker) = &mut self.bookmark_picker {
+            bookmark_picker.render(&self.bookmarks, self.current_size, &self.theme)?;
+        }
+
+        if let Some(clipboard_picker) = &mut self.clipboard_picker {
+            clipboard_picker.render(&self.clipboard, self.current_size, &self.theme)?;
+        }
+
+        if let Some(todo_panel) = &mut self.todo_panel {
+            todo_panel.render(self.current_size, &self.theme)?;
+        }
+
+        if let Some(prompt) = &self.prompt {
+            render_prompt(self.current_size, prompt)?;
+        } else {
+            terminal::move_cursor_to(self.get_relative_cursor_position())?;
+        }
+
+        self.needs_redraw = false;
+
+        Ok(())
+    }
+
+    /// Saves the buffer and reports the result in the status bar.
+    pub fn save(&mut self) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        // The edits made for saving are rolled back if the file cannot be written, so they do not
+        // linger in the buffer and its history without ever being saved.
+        let checkpoint = self.buffer.checkpoint();
+        if self.update_header && self.buffer.modified {
+            self.update_header_lines();
+        }
+
+        // Only applied to changed files, so saving an unchanged file leaves it byte-identical.
+        if self.buffer.modified {
+            self.apply_final_newline_policy();
+        }
+
+        let is_new_file = self
+            .buffer
+            .path
+            .as_deref()
+            .is_some_and(|path| !path.exists());
+
+        let saved = self.buffer.save();
+        let mut message = match &saved {
+            Ok(()) => {
+                self.refresh_todo_panel();
+                format!("{} lines written", self.buffer.get_line_count())
+            }
+            Err(err) => {
+                self.buffer.rollback(checkpoint);
+                self.move_cursor_to(self.cursor_location);
+                format!("could not save: {err}")
+            }
+        };
+
+        if let Some(path) = self.buffer.path.as_deref().filter(|_| self.warn_open_files) {
+            let users: Vec<String> = open_files::find_users(path)
+                .iter()
+                .map(|user| format!("{} ({})", user.name, user.pid))
+                .collect();
+
+            if !users.is_empty() {
+                message.push_str(", also open in ");
+                message.push_str(&users.join(", "));
+            }
+        }
+
+        if saved.is_ok() && is_new_file {
+            self.apply_executable_policy(&mut message);
+        }
+
+        self.status_message = Some(message);
+        self.needs_redraw = true;
+    }
+
+    /// Makes a newly saved script executable or asks whether to, adding the outcome to the
+    /// message reporting the save.
+    fn apply_executable_policy(&mut self, message: &mut String) {
+        // Other platforms have no executable permission to set.
+        let is_script = cfg!(unix)
+            && self
+                .buffer
+                .get_line(0)
+                .is_some_and(|line| executable::is_shebang(&line));
+        let Some(path) = self.buffer.path.clone().filter(|_| is_script) else {
+            return;
+        };
+
+        match self.executable_policy {
+            ExecutablePolicy::Always => match executable::make_executable(&path) {
+                Ok(()) => message.push_str(", made executable"),
+                Err(err) => {
+                    message.push_str(", could not make executable: ");
+                    message.push_str(&err.to_string());
+                }
+            },
+            ExecutablePolicy::Ask => {
+                message.push_str(", make executable? [y/n]");
+                self.pending_executable = Some(path);
+            }
+            ExecutablePolicy::Never => (),
+        }
+    }
+
+    /// Updates the header fields in the first lines of the buffer to the current time.
+    fn update_header_lines(&mut self) {
+        let now = DateTime::now();
+        let rows = self.buffer.get_line_count().min(header::HEADER_LINES);
+
+        for row in 0..rows {
+            let updated = self
+                .buffer
+                .get_line(row)
+                .and_then(|line| header::update_line(&line, now));
+
+            if let Some(updated) = updated {
+                self.buffer.replace_line(row, &updated);
+            }
+        }
+
+        // The cursor may have been past the end of a line that got shorter.
+        self.move_cursor_to(self.cursor_location);
+    }
+
+    /// Applies the `insert_final_newline` and `trim_final_newlines` options before saving.
+    fn apply_final_newline_policy(&mut self) {
+        if self.trim_final_newlines && self.buffer.trim_final_newlines() {
+            self.move_cursor_to(self.cursor_location);
+        }
+
+        if self.insert_final_newline {
+            self.buffer.final_newline = true;
+        }
+    }
+
+    /// Handles the answer to whether a new script should be made executable, returns whether the
+    /// key was consumed. Any key other than y or n dismisses the question.
+    fn handle_executable_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        let Some(path) = self.pending_executable.take() else {
+            return false;
+        };
+
+        match key_event.code {
+            KeyCode::Char('y') => {
+                self.status_message = Some(match executable::make_executable(&path) {
+                    Ok(()) => format!("made {} executable", path.display()),
+                    Err(err) => format!("could not make executable: {err}"),
+                });
+                true
+            }
+            KeyCode::Char('n') => true,
+            _ => false,
+        }
+    }
+
+    /// Handles the answer to whether dropped files should be opened, returns whether the key was
+    /// consumed. Declining inserts the paths as text instead.
+    fn handle_drop_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        let Some(drop) = self.pending_drop.take() else {
+            return false;
+        };
+
+        match key_event.code {
+            KeyCode::Char('y') => {
+                self.status_message = Some(format!(
+                    "opening {} file(s), Ctrl+PageDown switches buffers",
+                    drop.paths.len()
+                ));
+                self.background_open_requests.extend(drop.paths);
+                true
+            }
+            KeyCode::Char('n') => {
+                self.insert_pasted_text(&drop.text);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the cursor to the start position and scrolls it to the middle of the screen.
+    pub fn move_to_start_position(&mut self, start_position: &StartPosition) {
+        let location = match start_position {
+            StartPosition::Line(line) => Location {
+                row: line.saturating_sub(1),
+                col: 0,
+            },
+            StartPosition::Pattern(pattern) => {
+                let Some(location) = self.buffer.find(pattern) else {
+                    self.status_message = Some(format!("pattern not found: {pattern}"));
+                    self.needs_redraw = true;
+                    return;
+                };
+                location
+            }
+        };
+
+        self.move_cursor_to(location);
+        self.scroll_offset.row = self
+            .cursor_location
+            .row
+            .saturating_sub(self.buffer_height() as usize / 2);
+    }
+
+    /// Shows the buffer that the start of a file replaced again after loading the file failed or
+    /// was cancelled.
+    pub fn restore_replaced_buffer(&mut self) {
+        // An unloaded buffer can be left again, showing it once more retries loading it.
+        if self.buffer.unloaded {
+            self.buffer.partial = false;
+        }
+
+        let Some(replaced) = self.replaced_buffer.take() else {
+            return;
+        };
+
+        if self.buffer.partial {
+            self.buffer = replaced.buffer;
+            self.cursor_location = replaced.cursor_location;
+            self.scroll_offset = replaced.scroll_offset;
+            self.update_scroll();
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Keeps a loaded buffer open behind the current one.
+    pub fn add_buffer(&mut self, buffer: Buffer) {
+        self.other_buffers.push_back(BackgroundBuffer {
+            buffer,
+            cursor_location: Location::default(),
+            scroll_offset: Location::default(),
+        });
+    }
+
+    /// Returns whether any open buffer has unsaved changes.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.buffer.modified
+            || self
+                .other_buffers
+                .iter()
+                .any(|background| background.buffer.modified)
+    }
+
+    /// Shows the next or previous open buffer.
+    fn switch_buffer(&mut self, forward: bool) {
+        // The loaded file would replace whichever buffer is shown when it arrives.
+        if self.buffer.partial {
+            self.status_message = Some("the file is still loading".to_string());
+            self.needs_redraw = true;
+            return;
+        }
+
+        let next = if forward {
+            self.other_buffers.pop_front()
+        } else {
+            self.other_buffers.pop_back()
+        };
+
+        let Some(next) = next else {
+            self.status_message = Some("no other buffers".to_string());
+            self.needs_redraw = true;
+            return;
+        };
+
+        let previous = BackgroundBuffer {
+            buffer: mem::replace(&mut self.buffer, next.buffer),
+            cursor_location: mem::replace(&mut self.cursor_location, next.cursor_location),
+            scroll_offset: mem::replace(&mut self.scroll_offset, next.scroll_offset),
+        };
+        if previous.buffer.path.is_some() {
+            self.alternate_path.clone_from(&previous.buffer.path);
+        }
+
+        if forward {
+            self.other_buffers.push_back(previous);
+        } else {
+            self.other_buffers.push_front(previous);
+        }
+
+        self.completion = None;
+        self.rename = None;
+        self.snippet = None;
+        self.undo_panel = None;
+        self.selection_anchor = None;
+
+        // The loaded file replaces the unloaded buffer, keeping the cursor and scroll position.
+        if self.buffer.unloaded {
+            self.buffer.partial = true;
+            self.open_request.clone_from(&self.buffer.path);
+        }
+
+        // The window may have been resized while the buffer was hidden.
+        self.update_scroll();
+        self.needs_redraw = true;
+    }
+
+    /// Returns whether the buffer may be modified, showing a warning in the status bar if not.
+    fn ensure_writable(&mut self) -> bool {
+        if self.buffer.read_only {
+            self.status_message = Some("buffer is read-only".to_string());
+            self.needs_redraw = true;
+        }
+
+        !self.buffer.read_only
+    }
+
+    /// Returns the path of the buffer formatted for display.
+    fn get_display_path(&self) -> String {
+        self.format_buffer_path(&self.buffer)
+    }
+
+    /// Returns the path of any open buffer formatted for display.
+    fn format_buffer_path(&self, buffer: &Buffer) -> String {
+        buffer
+            .path
+            .as_deref()
+            .map_or("[No Name]".to_string(), |path| {
+                self.path_display.format(path, &self.working_dir)
+            })
+    }
+
+    /// Returns whether or the window is big enough to render the editor.
+    fn is_of_sufficient_size(&self) -> bool {
+        self.current_size.height > 1 && self.current_size.width > 0
+    }
+
+    /// Returns the height of the buffer.
+    fn buffer_height(&self) -> u16 {
+        self.current_size.height.saturating_sub(1)
+    }
+
+    /// Returns the rows shown in the view from the top, skipping the lines hidden by folds. Rows
+    /// past the end of the buffer fill the rest of the view.
+    fn get_visible_rows(&self) -> Vec<usize> {
+        self.buffer
+            .folds
+            .visible_rows(self.scroll_offset.row, true)
+            .take(self.buffer_height() as usize)
+            .collect()
+    }
+
+    /// Returns the row of the view a line is shown in, `None` if it is above the view or hidden
+    /// by a fold. Lines below the view return at least the height of the view.
+    fn get_screen_row(&self, row: usize) -> Option<usize> {
+        if row < self.scroll_offset.row || self.buffer.folds.is_hidden(row) {
+            return None;
+        }
+        if self.buffer.folds.is_empty() {
+            return Some(row - self.scroll_offset.row);
+        }
+
+        Some(
+            self.buffer
+                .folds
+                .visible_rows(self.scroll_offset.row, true)
+                .take_while(|visible| *visible < row)
+                .take(self.buffer_height() as usize)
+                .count(),
+        )
+    }
+
+    /// Returns the width of the line number gutter including the space after the numbers, hex
+    /// dumps show offsets instead.
+    fn gutter_width(&self) -> u16 {
+        if !self.line_numbers || self.buffer.binary.is_some() {
+            return 0;
+        }
+
+        let digits = self.buffer.get_line_count().to_string().len();
+
+        #[allow(clippy::cast_possible_truncation)]
+        (digits as u16 + 1).min(self.current_size.width.saturating_sub(1))
+    }
+
+    /// Returns the width available for the text of the buffer.
+    fn text_width(&self) -> u16 {
+        self.current_size.width.saturating_sub(self.gutter_width())
+    }
+
+    /// Renders the buffer to the screen.
+    fn render_buffer(&mut self) -> TResult<()> {
+        let number_width = (self.gutter_width() as usize).saturating_sub(1);
+
+        // Highlighting is one of the features skipped in safe mode. The runs of rows between
+        // folds are highlighted separately.
+        let rows = self.get_visible_rows();
+        let highlights: Vec<Vec<_>> = self
+            .buffer
+            .path
+            .as_deref()
+            .filter(|_| !self.buffer.safe_mode && self.buffer.binary.is_none())
+            .and_then(highlight::highlighter_for)
+            .map(|highlighter| {
+                rows.chunk_by(|row, next| *next == row + 1)
+                    .flat_map(|run| {
+                        highlighter.highlight(&self.buffer, run[0]..run[run.len() - 1] + 1)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for (pos_y, buffer_row_index) in rows.into_iter().enumerate() {
+            let spans = highlights.get(pos_y).map_or(&[][..], Vec::as_slice);
+            #[allow(clippy::cast_possible_truncation)]
+            let pos_y = pos_y as u16;
+
+            if let Some(line) = self.buffer.get_truncated_line(
+                buffer_row_index,
+                self.scroll_offset.col,
+                self.text_width() as usize,
+                spans,
+                self.visible_whitespace,
+            ) {
+                let gutter = if number_width > 0 {
+                    format!("{:>number_width$} ", buffer_row_index + 1)
+                } else {
+                    String::new()
+                };
+                let background = if self.highlight_current_line
+                    && buffer_row_index == self.cursor_location.row
+                {
+                    self.theme.current_line
+                } else {
+                    self.theme.background
+                };
+                render_line(pos_y, &gutter, &line, &self.theme, background)?;
+
+                let note = if let Some(fold) = self.buffer.folds.get(buffer_row_index) {
+                    let count = fold.hidden_count();
+                    Some(format!(
+                        "… {count} {}",
+                        if count == 1 { "line" } else { "lines" }
+                    ))
+                } else if buffer_row_index == self.cursor_location.row {
+                    self.get_bookmark_note()
+                } else {
+                    None
+                };
+                if let Some(note) = note {
+                    self.render_note(pos_y, &gutter, &line, &note)?;
+                }
+            } else {
+                let tilde = TruncatedLine {
+                    text: "~".into(),
+                    ..TruncatedLine::default()
+                };
+                render_line(pos_y, "", &tilde, &self.theme, self.theme.background)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draws the selected text over the buffer with the selection background.
+    fn render_selection(&self) -> TResult<()> {
+        let height = self.buffer_height() as usize;
+        let is_visible = |row: &usize| self.get_screen_row(*row).is_some_and(|y| y < height);
+
+        // The selected columns of each visible row and whether the selection continues below it.
+        let selected_rows: Vec<(usize, Range<usize>, bool)> =
+            if let Some(block) = self.get_block_selection() {
+                block
+                    .into_iter()
+                    .filter(|(row, _)| is_visible(row))
+                    .map(|(row, cols)| (row, cols, false))
+                    .collect()
+            } else if let Some((start, end)) = self.get_selection() {
+                self.get_visible_rows()
+                    .into_iter()
+                    .filter(|row| (start.row..=end.row).contains(row))
+                    .map(|row| {
+                        let first = if row == start.row { start.col } else { 0 };
+                        let last = if row == end.row {
+                            end.col
+                        } else {
+                            self.buffer.get_line_length(row)
+                        };
+                        (row, first..last, row != end.row)
+                    })
+                    .collect()
+            } else {
+                return Ok(());
+            };
+
+        let text_width = self.text_width() as usize;
+
+        terminal::set_foreground_color(self.theme.text)?;
+        terminal::set_background_color(self.theme.selection)?;
+
+        for (row, cols, continues) in selected_rows {
+            let Some(line) = self.buffer.get_line(row) else {
+                continue;
+            };
+
+            // The selected cells, plus the cell after the line if the selection continues
+            // below. Right-to-left text may split them into several ranges.
+            let mut cells: Vec<(usize, usize)> = Vec::new();
+            for col in cols {
+                let cell = line.display_column(col);
+                let width = line.grapheme_width(col);
+                match cells.last_mut() {
+                    Some(range) if range.1 == cell => range.1 += width,
+                    _ => cells.push((cell, cell + width)),
+                }
+            }
+            if continues {
+                let cell = line.width();
+                cells.push((cell, cell + 1));
+            }
+
+            // The first and last cells may be covered by the markers for hidden text.
+            let line_width = line.width();
+            let visible_start = self.scroll_offset.col + usize::from(self.scroll_offset.col > 0);
+            let visible_end = if line_width > self.scroll_offset.col + text_width {
+                self.scroll_offset.col + text_width - 1
+            } else {
+                self.scroll_offset.col + text_width
+            };
+
+            for (cell_start, cell_end) in cells {
+                let cell_start = cell_start.max(visible_start);
+                let cell_end = cell_end.min(visible_end);
+                if cell_start >= cell_end {
+                    continue;
+                }
+
+                #[allow(clippy::cast_possible_truncation)]
+                terminal::move_cursor_to(Position {
+                    x: (cell_start - self.scroll_offset.col) as u16 + self.gutter_width(),
+                    y: self.get_screen_row(row).unwrap_or_default() as u16,
+                })?;
+                terminal::print(line.clone().into_visible(cell_start, cell_end - cell_start))?;
+            }
+        }
+
+        terminal::set_background_color(self.theme.background)
+    }
+
+    /// Highlights the bracket under the cursor and the one matching it.
+    fn render_matching_brackets(&self) -> TResult<()> {
+        if self.buffer.binary.is_some() {
+            return Ok(());
+        }
+        let Some(matching) = brackets::find_match(&self.buffer, self.cursor_location) else {
+            return Ok(());
+        };
+
+        terminal::set_background_color(self.theme.matching_bracket)?;
+        for location in [self.cursor_location, matching] {
+            let Some(y) = self.get_screen_row(location.row) else {
+                continue;
+            };
+            let Some(x) = self
+                .buffer
+                .get_display_column(location)
+                .checked_sub(self.scroll_offset.col)
+            else {
+                continue;
+            };
+            let Some(line) = self.buffer.get_line(location.row) else {
+                continue;
+            };
+
+            // The first and last cells may be covered by the markers for hidden text.
+            let hidden_before = self.scroll_offset.col > 0;
+            if y >= self.buffer_height() as usize
+                || (x == 0 && hidden_before)
+                || x + 1 >= self.text_width() as usize
+            {
+                continue;
+            }
+
+            #[allow(clippy::cast_possible_truncation)]
+            terminal::move_cursor_to(Position {
+                x: x as u16 + self.gutter_width(),
+                y: y as u16,
+            })?;
+            terminal::print(line.into_slice(location.col, location.col + 1))?;
+        }
+
+        terminal::set_background_color(self.theme.background)
+    }
+
+    /// Renders the lines opening the scopes around the top of the view over its first rows.
+    fn render_sticky_header(&self) -> TResult<()> {
+        let number_width = (self.gutter_width() as usize).saturating_sub(1);
+        let highlighter = self
+            .buffer
+            .path
+            .as_deref()
+            .and_then(highlight::highlighter_for);
+
+        for (pos_y, row) in self.get_sticky_rows().into_iter().enumerate() {
+            let spans = highlighter
+                .as_ref()
+                .and_then(|highlighter| highlighter.highlight(&self.buffer, row..row + 1).pop())
+                .unwrap_or_default();
+
+            let Some(line) = self.buffer.get_truncated_line(
+                row,
+                self.scroll_offset.col,
+                self.text_width() as usize,
+                &spans,
+                self.visible_whitespace,
+            ) else {
+                continue;
+            };
+            let gutter = if number_width > 0 {
+                format!("{:>number_width$} ", row + 1)
+            } else {
+                String::new()
+            };
+
+            #[allow(clippy::cast_possible_truncation)]
+            render_line(
+                pos_y as u16,
+                &gutter,
+                &line,
+                &self.theme,
+                self.theme.sticky_header,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the rows of the lines shown by the sticky header, which are all scrolled out of
+    /// the view.
+    fn get_sticky_rows(&self) -> Vec<usize> {
+        let top = self.scroll_offset.row;
+        if !self.sticky_header || top == 0 || self.buffer.safe_mode || self.buffer.binary.is_some()
+        {
+            return Vec::new();
+        }
+
+        // The header covers the first rows, so it shows the scopes of the first row below it.
+        let limit = self.get_sticky_limit();
+        let covered = self.get_enclosing_rows(top, limit).len();
+        let mut rows = self.get_enclosing_rows(top + covered, limit);
+        rows.retain(|row| *row < top);
+
+        rows
+    }
+
+    /// Returns the maximum number of lines in the sticky header.
+    fn get_sticky_limit(&self) -> usize {
+        MAX_STICKY_LINES.min(self.buffer_height() as usize / 3)
+    }
+
+    /// Returns the rows of the lines the sticky header shows for a row, outermost first, at most
+    /// `limit` of the innermost ones.
+    fn get_enclosing_rows(&self, row: usize, limit: usize) -> Vec<usize> {
+        if self.ensure_markdown().is_ok() {
+            return section::enclosing_headings(&self.buffer, row, limit);
+        }
+        if !self.sticky_definitions {
+            return scope::enclosing_lines(&self.buffer, row, limit);
+        }
+
+        let mut rows = scope::enclosing_lines(&self.buffer, row, usize::MAX);
+        rows.retain(|row| {
+            self.buffer
+                .get_line(*row)
+                .is_some_and(|line| section::is_definition(&line))
+        });
+        rows.drain(..rows.len().saturating_sub(limit));
+
+        rows
+    }
+
+    /// Returns the note of the bookmark on the cursor line, `None` if it has none.
+    fn get_bookmark_note(&self) -> Option<String> {
+        self.buffer
+            .path
+            .as_deref()
+            .and_then(|path| self.bookmarks.get(path, self.cursor_location.row))
+            .filter(|bookmark| !bookmark.note.is_empty())
+            .map(|bookmark| bookmark.note.clone())
+    }
+
+    /// Shows a note after the end of a line, like the note of a bookmark or the number of lines
+    /// in a fold, if it fits.
+    fn render_note(
+        &self,
+        pos_y: u16,
+        gutter: &str,
+        line: &TruncatedLine,
+        note: &str,
+    ) -> TResult<()> {
+        let start = gutter.len() + usize::from(line.hidden_before) + Line::new(&*line.text).width();
+        let available = (self.current_size.width as usize).saturating_sub(start + NOTE_SPACING);
+        if line.hidden_after || available == 0 {
+            return Ok(());
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        terminal::move_cursor_to(Position {
+            x: (start + NOTE_SPACING) as u16,
+            y: pos_y,
+        })?;
+        terminal::set_foreground_color(self.theme.gutter)?;
+        terminal::print(Line::new(note).into_visible(0, available))?;
+        terminal::set_foreground_color(self.theme.text)
+    }
+
+    /// Renders the statusbar to the screen.
+    fn render_status_bar(&mut self) -> TResult<()> {
+        let Size { height, width } = self.current_size;
+
+        terminal::move_cursor_to(Position {
+            x: 0,
+            y: height - 1,
+        })?;
+
+        // Set status bar colors and clear line.
+        terminal::set_foreground_color(self.theme.status_bar_text)?;
+        terminal::set_background_color(self.theme.status_bar_background)?;
+        terminal::clear_line()?;
+
+        // Display the progress of a running operation, the explored directory or the current file
+        // path.
+        let mut status = if let Some(progress) = &self.progress {
+            progress.gauge()
+        } else if let Some(explorer) = &self.explorer {
+            self.path_display.format(&explorer.dir, &self.working_dir)
+        } else {
+            let mut status = self.get_display_path();
+
+            if self.buffer.new_file {
+                status.push_str(" [New File]");
+            }
+
+            if self.buffer.modified {
+                status.push_str(" [+]");
+            }
+
+            if self.buffer.read_only && self.buffer.write_protected {
+                status.push_str(" [read-only, no permission]");
+            } else if self.buffer.read_only {
+                status.push_str(" [read-only]");
+            }
+
+            if self.buffer.safe_mode {
+                status.push_str(" [safe mode]");
+            }
+
+            status
+        };
+
+        // Display the latest status message.
+        if let Some(message) = &self.status_message {
+            status.push_str("  ");
+            status.push_str(message);
+        }
+
+        // Display cursor position, with the display column next to the byte column if they differ
+        // because of tabs, wide or multi-byte characters.
+        let byte_column = self.buffer.get_byte_column(self.cursor_location) + 1;
+        let display_column = self.buffer.get_display_column(self.cursor_location) + 1;
+        let column = if byte_column == display_column {
+            byte_column.to_string()
+        } else {
+            format!("{byte_column}-{display_column}")
+        };
+        let mut current_location = if let Some(explorer) = &self.explorer {
+            format!("ENTRY {}/{}", explorer.selected + 1, explorer.entries.len())
+        } else if self.buffer.binary.is_some() {
+            format!("OFFSET 0x{:08x}", byte_column - 1)
+        } else if let Some(selection_size) = self.get_selection_size() {
+            format!(
+                "{selection_size}  LINE {} COL {column}",
+                self.cursor_location.row + 1
+            )
+        } else {
+            format!("LINE {} COL {column}", self.cursor_location.row + 1)
+        };
+
+        // The file format is left out if it would hide the status message.
+        let status = Line::new(status);
+        let file_format = format!(
+            "{}  {}  {}{}  ",
+            self.buffer.indentation,
+            self.buffer.line_ending,
+            self.buffer.encoding,
+            if self.buffer.byte_order_mark {
+                " BOM"
+            } else {
+                ""
+            }
+        );
+        let fits = status.width() + file_format.len() + current_location.len() < width as usize;
+        if fits && self.buffer.binary.is_none() && self.explorer.is_none() {
+            current_location.insert_str(0, &file_format);
+        }
+
+        // Text past the end of the line would wrap and scroll the whole screen.
+        terminal::print(status.into_visible(0, width as usize))?;
+
+        #[allow(clippy::cast_possible_truncation)]
+        terminal::move_cursor_to(Position {
+            x: width.saturating_sub(current_location.len() as u16),
+            y: height - 1,
+        })?;
+        terminal::print(current_location)?;
+
+        // Reset status bar colors.
+        terminal::set_foreground_color(self.theme.text)?;
+        terminal::set_background_color(self.theme.background)?;
+
+        Ok(())
+    }
+
+    /// Returns the size of the selection for the status bar, the number of rows and columns of
+    /// a block selection or else the number of lines and characters.
+    fn get_selection_size(&self) -> Option<String> {
+        if let Some((rows, cells)) = self.get_block_bounds() {
+            return Some(format!("BLOCK {}x{}", rows.count(), cells.len()));
+        }
+
+        let (start, end) = self.get_selection()?;
+        let chars = self.buffer.get_text(start, end).chars().count();
+
+        Some(if start.row == end.row {
+            format!("SEL {chars} CHARS")
+        } else {
+            format!("SEL {} LINES {chars} CHARS", end.row - start.row + 1)
+        })
+    }
+
+    /// Calculates the cursor position relative to the current scroll position.
+    fn get_relative_cursor_position(&self) -> Position {
+        #[allow(clippy::cast_possible_truncation)]
+        Position {
+            x: self
+                .buffer
+                .get_display_column(self.cursor_location)
+                .saturating_sub(self.scroll_offset.col) as u16
+                + self.gutter_width(),
+            y: self
+                .get_screen_row(self.cursor_location.row)
+                .unwrap_or_default() as u16,
+        }
+    }
+
+    /// Handles events, e.g. input or resizing.
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::Key(key_event) => self.handle_key_event(key_event),
+            Event::Resize(width, height) => self.handle_resize_event(*width, *height),
+            Event::Mouse(mouse_event) => self.handle_mouse_event(*mouse_event),
+            Event::Paste(text) => self.handle_paste_event(text.clone()),
+
+            _ => (),
+        }
+    }
+
+    /// Handles a mouse event.
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        if self.prompt.is_some() || self.explorer.is_some() {
+            return;
+        }
+
+        let position = Position {
+            x: mouse_event.column,
+            y: mouse_event.row,
+        };
+
+        let natural = self.scroll_settings.natural;
+
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                // Dragging with Alt held selects a block.
+                self.block_selection = mouse_event.modifiers.contains(KeyModifiers::ALT);
+                self.handle_click(position);
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.selection_anchor.is_some() => {
+                self.drag_selection(position);
+            }
+            // A click without dragging only places the cursor.
+            MouseEventKind::Up(MouseButton::Left)
+                if self.selection_anchor == Some(self.cursor_location) =>
+            {
+                self.selection_anchor = None;
+            }
+            MouseEventKind::Down(MouseButton::Middle) if self.middle_click_paste => {
+                if let Some(location) = self.get_location_at(position) {
+                    self.paste_primary_selection(location);
+                }
+            }
+            MouseEventKind::ScrollLeft => self.scroll_horizontally(natural),
+            MouseEventKind::ScrollRight => self.scroll_horizontally(!natural),
+
+            // Many terminals only send vertical wheel events, Shift turns them sideways.
+            MouseEventKind::ScrollUp if mouse_event.modifiers == KeyModifiers::SHIFT => {
+                self.scroll_horizontally(natural);
+            }
+            MouseEventKind::ScrollDown if mouse_event.modifiers == KeyModifiers::SHIFT => {
+                self.scroll_horizontally(!natural);
+            }
+            MouseEventKind::ScrollUp => self.scroll_vertically(natural),
+            MouseEventKind::ScrollDown => self.scroll_vertically(!natural),
+            _ => (),
+        }
+    }
+
+    /// Places the cursor where the left button is pressed, a double click selects the word and
+    /// a triple click the line under the pointer.
+    fn handle_click(&mut self, position: Position) {
+        let now = Instant::now();
+        let clicks = match self.last_click {
+            Some((time, last_position, clicks))
+                if last_position == position && now - time <= MULTI_CLICK_INTERVAL =>
+            {
+                clicks % 3 + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some((now, position, clicks));
+
+        let Some(location) = self.get_location_at(position) else {
+            return;
+        };
+
+        match clicks {
+            1 => {
+                self.selection_anchor = Some(location);
+                self.move_cursor_to(location);
+            }
+            2 => self.select_word(location),
+            _ => self.select_line(location.row),
+        }
+    }
+
+    /// Selects the word at a location, or only places the cursor if there is none.
+    fn select_word(&mut self, location: Location) {
+        let Some(line) = self.buffer.get_line(location.row) else {
+            return;
+        };
+        let (start, end) = get_word_bounds(&line, line.byte_index(location.col));
+
+        self.selection_anchor = Some(Location {
+            row: location.row,
+            col: line.grapheme_index(start),
+        });
+        self.move_cursor_to(Location {
+            row: location.row,
+            col: line.grapheme_index(end),
+        });
+    }
+
+    /// Selects a line including its line break.
+    fn select_line(&mut self, row: usize) {
+        self.selection_anchor = Some(Location { row, col: 0 });
+
+        if row < self.buffer.get_last_line_index() {
+            self.move_cursor_to(Location {
+                row: row + 1,
+                col: 0,
+            });
+        } else {
+            self.move_cursor_to(Location {
+                row,
+                col: self.buffer.get_line_length(row),
+            });
+        }
+    }
+
+    /// Extends the selection to the location under the pointer, scrolling when it is dragged to
+    /// the first or last row of the view.
+    fn drag_selection(&mut self, position: Position) {
+        let last_row = self.buffer_height().saturating_sub(1);
+        let Some(mut location) = self.get_location_at(Position {
+            x: position.x,
+            y: position.y.min(last_row),
+        }) else {
+            return;
+        };
+
+        if position.y == 0 && location.row > 0 {
+            location.row -= 1;
+        } else if position.y >= last_row {
+            location.row += 1;
+        }
+
+        self.move_cursor_to(location);
+    }
+
+    /// Scrolls the view up or down until the last line is at the bottom. The cursor stays where
+    /// it is like when scrolling sideways.
+    fn scroll_vertically(&mut self, down: bool) {
+        let folds = &self.buffer.folds;
+        let max_scroll = folds
+            .visible_rows(self.buffer.get_last_line_index(), false)
+            .nth(self.buffer_height().saturating_sub(1) as usize)
+            .unwrap_or_default();
+        let step = self.scroll_settings.vertical_step;
+        let scroll = if down {
+            let scroll = folds
+                .visible_rows(self.scroll_offset.row, true)
+                .nth(step)
+                .unwrap_or(usize::MAX);
+            scroll.min(max_scroll.max(self.scroll_offset.row))
+        } else {
+            folds
+                .visible_rows(self.scroll_offset.row, false)
+                .nth(step)
+                .unwrap_or_default()
+        };
+
+        if scroll != self.scroll_offset.row {
+            self.scroll_offset.row = scroll;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Scrolls the view sideways as far as the longest visible line allows. The cursor stays
+    /// where it is and is scrolled back into view when it is moved.
+    fn scroll_horizontally(&mut self, right: bool) {
+        let width = self.text_width() as usize;
+        let visible_rows =
+            self.scroll_offset.row..self.scroll_offset.row + self.buffer_height() as usize;
+        let max_width = visible_rows
+            .filter(|row| *row < self.buffer.get_line_count())
+            .map(|row| {
+                self.buffer.get_display_column(Location {
+                    row,
+                    col: self.buffer.get_line_length(row),
+                })
+            })
+            .max()
+            .unwrap_or_default();
+
+        // The cell after the end of the longest line stays visible for the cursor.
+        let max_scroll = (max_width + 1).saturating_sub(width);
+        let step = self.scroll_settings.horizontal_step;
+        let scroll = if right {
+            (self.scroll_offset.col + step).min(max_scroll.max(self.scroll_offset.col))
+        } else {
+            self.scroll_offset.col.saturating_sub(step)
+        };
+
+        if scroll != self.scroll_offset.col {
+            self.scroll_offset.col = scroll;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Returns whether the cursor is within the visible part of the buffer.
+    pub fn is_cursor_visible(&self) -> bool {
+        if self.prompt.is_some() || self.explorer.is_some() {
+            return true;
+        }
+
+        let display_column = self.buffer.get_display_column(self.cursor_location);
+        let visible_columns =
+            self.scroll_offset.col..self.scroll_offset.col + self.text_width() as usize;
+        visible_columns.contains(&display_column)
+            && self
+                .get_screen_row(self.cursor_location.row)
+                .is_some_and(|y| y < self.buffer_height() as usize)
+    }
+
+    /// Inserts the primary selection at a location and moves the cursor past it, or replaces the
+    /// selected text with it like any other paste.
+    fn paste_primary_selection(&mut self, location: Location) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        let Some(text) = clipboard::read_primary_selection() else {
+            self.status_message = Some("could not read the primary selection".to_string());
+            self.needs_redraw = true;
+            return;
+        };
+
+        if self.get_selection().is_none() || self.block_selection {
+            self.selection_anchor = None;
+            self.cursor_location = location;
+        }
+        self.insert_pasted_text(&text);
+    }
+
+    /// Returns the buffer location shown at a position on the screen, `None` for the status bar.
+    fn get_location_at(&self, position: Position) -> Option<Location> {
+        if position.y >= self.buffer_height() {
+            return None;
+        }
+
+        let row = self
+            .get_sticky_rows()
+            .get(position.y as usize)
+            .copied()
+            .or_else(|| self.get_visible_rows().get(position.y as usize).copied())
+            .unwrap_or_default()
+            .min(self.buffer.get_last_line_index());
+        let x = position.x.saturating_sub(self.gutter_width());
+        let col = self
+            .buffer
+            .get_column_at_width(row, self.scroll_offset.col + x as usize);
+
+        Some(Location { row, col })
+    }
+
+    /// Handles pasted text, offering to open it instead if it consists of dropped files.
+    fn handle_paste_event(&mut self, text: String) {
+        if self.prompt.is_some() || self.explorer.is_some() {
+            return;
+        }
+
+        match FileDrop::detect(text) {
+            Ok(drop) => {
+                self.status_message =
+                    Some(format!("open {} dropped file(s)? [y/n]", drop.paths.len()));
+                self.pending_drop = Some(drop);
+                self.needs_redraw = true;
+            }
+            Err(text) => self.insert_pasted_text(&text),
+        }
+    }
+
+    /// Inserts pasted text at the cursor as its own undo step and moves the cursor past it.
+    ///
+    /// The text is inserted as is, without indenting its lines, and terminals that send line
+    /// breaks as carriage returns are handled.
+    fn insert_pasted_text(&mut self, text: &str) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        self.delete_selection();
+
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        self.buffer.history.close_group();
+        self.cursor_location = self.buffer.insert_str(self.cursor_location, &text);
+        self.buffer.history.close_group();
+        self.update_scroll();
+        self.needs_redraw = true;
+    }
+
+    /// Handles a resize event.
+    fn handle_resize_event(&mut self, width: u16, height: u16) {
+        self.current_size = Size { width, height };
+        self.needs_redraw = true;
+    }
+
+    /// Handles an input event.
+    fn handle_key_event(&mut self, key_event: &KeyEvent) {
+        if key_event.kind != KeyEventKind::Press {
+            return;
+        }
+
+        // Status messages are only shown until the next key press.
+        if self.status_message.take().is_some() {
+            self.needs_redraw = true;
+        }
+
+        if self.pending_executable.is_some() && self.handle_executable_key_event(key_event) {
+            return;
+        }
+
+        if self.pending_drop.is_some() && self.handle_drop_key_event(key_event) {
+            return;
+        }
+
+        if self.prompt.is_some() {
+            self.handle_prompt_key_event(key_event);
+            return;
+        }
+
+        if self.explorer.is_some() {
+            self.handle_explorer_key_event(key_event);
+            return;
+        }
+
+        if self.handle_panel_key_event(key_event) {
+            return;
+        }
+
+        if self.handle_editing_key_event(key_event) {
+            return;
+        }
+
+        if self.selection_anchor.is_some() && self.handle_selection_key_event(key_event) {
+            return;
+        }
+
+        if self.handle_clipboard_key_event(key_event) {
+            return;
+        }
+
+        match key_event.code {
+            KeyCode::PageDown if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.switch_buffer(true);
+            }
+            KeyCode::PageUp if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.switch_buffer(false);
+            }
+            KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End => match key_event.modifiers {
+                KeyModifiers::SHIFT => self.extend_selection(key_event.code, false),
+                BLOCK_SELECTION_MODIFIERS => self.extend_selection(key_event.code, true),
+                _ => self.move_cursor(key_event.code),
+            },
+            KeyCode::Char(' ') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.open_completion();
+            }
+            KeyCode::Char('p') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.prompt = Some(Prompt::new(":"));
+                self.needs_redraw = true;
+            }
+            KeyCode::Char('z') if key_event.modifiers == KeyModifiers::CONTROL => self.undo(),
+            KeyCode::Char('y') if key_event.modifiers == KeyModifiers::CONTROL => self.redo(),
+            KeyCode::Char(';') if key_event.modifiers == KeyModifiers::ALT => {
+                self.jump_to_change(true);
+            }
+            KeyCode::Char(',') if key_event.modifiers == KeyModifiers::ALT => {
+                self.jump_to_change(false);
+            }
+            // Terminals differ in whether Shift is reported for `{` and `}`.
+            KeyCode::Char('}') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+                self.jump_to_section(true);
+            }
+            KeyCode::Char('{') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+                self.jump_to_section(false);
+            }
+            // Terminals differ in whether Shift is reported for `%`.
+            KeyCode::Char('%') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+                self.jump_to_matching_bracket();
+            }
+            // Terminals differ in whether Shift is reported for `+`.
+            KeyCode::Char('=' | '+') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+                self.increment_number(1);
+            }
+            KeyCode::Char('-') if key_event.modifiers == KeyModifiers::ALT => {
+                self.increment_number(-1);
+            }
+            KeyCode::Enter if key_event.modifiers == KeyModifiers::ALT => self.split_line(false),
+            KeyCode::Enter => self.split_line(true),
+            KeyCode::Tab => self.expand_snippet_or_indent(),
+            KeyCode::Char(ch)
+                if key_event.modifiers == KeyModifiers::NONE
+                    || key_event.modifiers == KeyModifiers::SHIFT =>
+            {
+                self.insert_char(ch);
+            }
+            _ => (),
+        }
+    }
+
+    /// Handles the keys that copy, cut, delete or paste text while nothing is selected, returns
+    /// whether the key was one of them.
+    fn handle_clipboard_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        match key_event.code {
+            // Without a selection the current line is copied or cut.
+            KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.copy_line(false);
+            }
+            KeyCode::Char('x') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.copy_line(true);
+            }
+            // Terminals report Ctrl+Shift+K as Ctrl+K unless keyboard enhancements are enabled.
+            KeyCode::Char('k' | 'K') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.delete_line();
+            }
+            KeyCode::Char('v') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.paste_clipboard();
+            }
+            KeyCode::Char('v') if key_event.modifiers == KeyModifiers::ALT => {
+                self.clipboard_picker = Some(ClipboardPicker::default());
+                self.needs_redraw = true;
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Handles a key press in the open panel or picker, returns whether the event was consumed.
+    fn handle_panel_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        (self.todo_panel.is_some() && self.handle_todo_panel_key_event(key_event))
+            || (self.bookmark_picker.is_some() && self.handle_bookmark_picker_key_event(key_event))
+            || (self.clipboard_picker.is_some()
+                && self.handle_clipboard_picker_key_event(key_event))
+            || (self.undo_panel.is_some() && self.handle_undo_panel_key_event(key_event))
+    }
+
+    /// Handles a key while renaming, filling in a snippet or completing a word, returns whether
+    /// the event was consumed.
+    fn handle_editing_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        (self.rename.is_some() && self.handle_rename_key_event(key_event))
+            || (self.snippet.is_some() && self.handle_snippet_key_event(key_event))
+            || (self.completion.is_some() && self.handle_completion_key_event(key_event))
+    }
+
+    /// Handles a key while text is selected, returns whether the key was consumed. Typing
+    /// replaces the selection, Shift with a movement key extends it, Tab and Shift+Tab indent
+    /// the selected lines and any other key clears it.
+    fn handle_selection_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        if self.block_selection {
+            return self.handle_block_selection_key_event(key_event);
+        }
+
+        let is_typing = key_event.code == KeyCode::Enter
+            || (matches!(key_event.code, KeyCode::Char(_))
+                && (key_event.modifiers == KeyModifiers::NONE
+                    || key_event.modifiers == KeyModifiers::SHIFT));
+
+        match key_event.code {
+            KeyCode::Backspace | KeyCode::Delete => {
+                // Deleted text can be pasted again from the clipboard history.
+                if let Some((start, end)) = self.get_selection() {
+                    self.clipboard.remember(&self.buffer.get_text(start, end));
+                }
+                self.delete_selection();
+                true
+            }
+            KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.copy_selection();
+                true
+            }
+            KeyCode::Char('x') if key_event.modifiers == KeyModifiers::CONTROL => {
+                if self.copy_selection() {
+                    self.delete_selection();
+                }
+                true
+            }
+            // Pasting replaces the selection, commands like `encode` work on it.
+            KeyCode::Char('v' | 'p') if key_event.modifiers == KeyModifiers::CONTROL => false,
+            KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End
+                if key_event.modifiers == KeyModifiers::SHIFT
+                    || key_event.modifiers == BLOCK_SELECTION_MODIFIERS =>
+            {
+                let block = key_event.modifiers == BLOCK_SELECTION_MODIFIERS;
+                self.extend_selection(key_event.code, block);
+                true
+            }
+            KeyCode::Tab => {
+                self.indent_selection(false);
+                true
+            }
+            KeyCode::BackTab => {
+                self.indent_selection(true);
+                true
+            }
+            KeyCode::Esc => {
+                self.selection_anchor = None;
+                self.needs_redraw = true;
+                true
+            }
+            _ if is_typing => {
+                self.delete_selection();
+                false
+            }
+            _ => {
+                self.selection_anchor = None;
+                self.needs_redraw = true;
+                false
+            }
+        }
+    }
+
+    /// Handles a key while a block is selected, returns whether the key was consumed. Typing
+    /// inserts into every row of the block and keeps it selected to continue typing, Shift with a
+    /// movement key resizes it and any other key clears it.
+    fn handle_block_selection_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        match key_event.code {
+            KeyCode::Backspace | KeyCode::Delete => {
+                if let Some(text) = self.get_block_text() {
+                    self.clipboard.remember(&text);
+                }
+                self.delete_block();
+                self.selection_anchor = None;
+                true
+            }
+            KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
+                if let Some(text) = self.get_block_text() {
+                    self.copy_text(&text);
+                }
+                true
+            }
+            KeyCode::Char('x') if key_event.modifiers == KeyModifiers::CONTROL => {
+                if let Some(text) = self.get_block_text() {
+                    self.copy_text(&text);
+                    self.delete_block();
+                }
+                self.selection_anchor = None;
+                true
+            }
+            // Commands like `encode` work on the block.
+            KeyCode::Char('p') if key_event.modifiers == KeyModifiers::CONTROL => false,
+            KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End
+                if key_event.modifiers == KeyModifiers::SHIFT
+                    || key_event.modifiers == BLOCK_SELECTION_MODIFIERS =>
+            {
+                self.extend_selection(key_event.code, true);
+                true
+            }
+            KeyCode::Char(ch)
+                if key_event.modifiers == KeyModifiers::NONE
+                    || key_event.modifiers == KeyModifiers::SHIFT =>
+            {
+                self.insert_into_block(ch);
+                true
+            }
+            KeyCode::Esc => {
+                self.selection_anchor = None;
+                self.needs_redraw = true;
+                true
+            }
+            _ => {
+                self.selection_anchor = None;
+                self.needs_redraw = true;
+                false
+            }
+        }
+    }
+
+    /// Returns the rows of the block selection and the cells between its left and right edges,
+    /// `None` if no block is selected.
+    fn get_block_bounds(&self) -> Option<(RangeInclusive<usize>, Range<usize>)> {
+        let anchor = self.selection_anchor.filter(|_| self.block_selection)?;
+        let anchor_row = anchor.row.min(self.buffer.get_last_line_index());
+        let anchor_cell = self.buffer.get_display_column(Location {
+            row: anchor_row,
+            col: anchor.col.min(self.buffer.get_line_length(anchor_row)),
+        });
+        let cursor_cell = self.buffer.get_display_column(self.cursor_location);
+
+        Some((
+            anchor_row.min(self.cursor_location.row)..=anchor_row.max(self.cursor_location.row),
+            anchor_cell.min(cursor_cell)..anchor_cell.max(cursor_cell),
+        ))
+    }
+
+    /// Returns the rows of the block selection with the columns selected in each, which are
+    /// empty where a line ends before the block.
+    fn get_block_selection(&self) -> Option<Vec<(usize, Range<usize>)>> {
+        let (rows, cells) = self.get_block_bounds()?;
+
+        Some(
+            rows.map(|row| {
+                let start = self.buffer.get_column_at_width(row, cells.start);
+                let end = self.buffer.get_column_at_width(row, cells.end);
+                (row, start..end)
+            })
+            .collect(),
+        )
+    }
+
+    /// Returns the text of the block selection, a line for every row.
+    fn get_block_text(&self) -> Option<String> {
+        let text: Vec<String> = self
+            .get_block_selection()?
+            .into_iter()
+            .map(|(row, cols)| {
+                self.buffer.get_text(
+                    Location {
+                        row,
+                        col: cols.start,
+                    },
+                    Location { row, col: cols.end },
+                )
+            })
+            .collect();
+
+        Some(text.join("\n"))
+    }
+
+    /// Deletes the text of the block selection as its own undo step and moves the cursor to its
+    /// top left corner, keeping the now empty block selected.
+    fn delete_block(&mut self) {
+        let (Some((_, cells)), Some(block)) = (self.get_block_bounds(), self.get_block_selection())
+        else {
+            return;
+        };
+        if !self.ensure_writable() {
+            return;
+        }
+
+        self.buffer.history.close_group();
+        for (row, cols) in block {
+            self.buffer.delete(
+                Location {
+                    row,
+                    col: cols.start,
+                },
+                Location { row, col: cols.end },
+            );
+        }
+        self.buffer.history.close_group();
+
+        self.move_block_to_cell(cells.start);
+    }
+
+    /// Replaces the text of the block selection with a character on every row, skipping lines that
+    /// end before the block. The empty block after the character stays selected, so typing
+    /// continues on every row.
+    fn insert_into_block(&mut self, ch: char) {
+        let Some((rows, cells)) = self.get_block_bounds() else {
+            return;
+        };
+        if !self.ensure_writable() {
+            return;
+        }
+
+        if !cells.is_empty() {
+            self.delete_block();
+        }
+
+        let text = ch.to_string();
+        for row in rows {
+            if self
+                .buffer
+                .get_line(row)
+                .is_some_and(|line| line.width() >= cells.start)
+            {
+                let col = self.buffer.get_column_at_width(row, cells.start);
+                self.buffer.insert_str(Location { row, col }, &text);
+            }
+        }
+
+        self.move_block_to_cell(cells.start + Line::new(text.as_str()).width());
+    }
+
+    /// Moves both corners of the block selection to a cell of their rows, leaving an empty
+    /// block.
+    fn move_block_to_cell(&mut self, cell: usize) {
+        if let Some(anchor) = &mut self.selection_anchor {
+            anchor.col = self.buffer.get_column_at_width(anchor.row, cell);
+        }
+        self.cursor_location.col = self
+            .buffer
+            .get_column_at_width(self.cursor_location.row, cell);
+
+        self.update_scroll();
+        self.needs_redraw = true;
+    }
+
+    /// Moves the cursor based on a pressed key, selecting the text it passes over, or the block
+    /// between the cursor and where the selection started if `block` is set. The selection
+    /// starts at the cursor if nothing is selected yet.
+    fn extend_selection(&mut self, key_code: KeyCode, block: bool) {
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.cursor_location);
+        }
+        self.block_selection = block;
+
+        self.move_cursor(key_code);
+        self.needs_redraw = true;
+    }
+
+    /// Indents the selected lines by one level, or unindents them, as a single undo step. A
+    /// selection ending at the start of a line leaves that line alone.
+    fn indent_selection(&mut self, unindent: bool) {
+        let Some((start, end)) = self.get_selection() else {
+            return;
+        };
+        if !self.ensure_writable() {
+            return;
+        }
+
+        let mut anchor = if start == self.cursor_location {
+            end
+        } else {
+            start
+        };
+        let last_row = if end.col == 0 && end.row > start.row {
+            end.row - 1
+        } else {
+            end.row
+        };
+        let indentation = self.buffer.indentation;
+
+        self.buffer.history.close_group();
+        for row in start.row..=last_row {
+            let Some(line) = self.buffer.get_line(row) else {
+                continue;
+            };
+
+            let shift = if unindent {
+                let len = indentation.level_len(&line);
+                self.buffer
+                    .delete(Location { row, col: 0 }, Location { row, col: len });
+                -len.cast_signed()
+            } else if line.trim().is_empty() {
+                0
+            } else {
+                let text = indentation.text_at(0);
+                self.buffer.insert_str(Location { row, col: 0 }, &text);
+                text.len().cast_signed()
+            };
+
+            for location in [&mut anchor, &mut self.cursor_location] {
+                if location.row == row {
+                    location.col = location.col.saturating_add_signed(shift);
+                }
+            }
+        }
+        self.buffer.history.close_group();
+
+        self.selection_anchor = Some(anchor);
+        self.update_scroll();
+        self.needs_redraw = true;
+    }
+
+    /// Returns the start and end of the selection, `None` if nothing is selected.
+    fn get_selection(&self) -> Option<(Location, Location)> {
+        let anchor = self.selection_anchor?;
+        let row = anchor.row.min(self.buffer.get_last_line_index());
+        let anchor = Location {
+            row,
+            col: anchor.col.min(self.buffer.get_line_length(row)),
+        };
+
+        match anchor.cmp(&self.cursor_location) {
+            Ordering::Less => Some((anchor, self.cursor_location)),
+            Ordering::Greater => Some((self.cursor_location, anchor)),
+            Ordering::Equal => None,
+        }
+    }
+
+    /// Deletes the selected text as its own undo step, returns whether anything was selected.
+    fn delete_selection(&mut self) -> bool {
+        let Some((start, end)) = self.get_selection() else {
+            return false;
+        };
+        self.selection_anchor = None;
+
+        if !self.ensure_writable() {
+            return true;
+        }
+
+        self.buffer.history.close_group();
+        self.cursor_location = self.buffer.delete(start, end);
+        self.update_scroll();
+        self.needs_redraw = true;
+
+        true
+    }
+
+    /// Copies the selected text to the clipboard, returns whether anything was selected.
+    fn copy_selection(&mut self) -> bool {
+        let Some((start, end)) = self.get_selection() else {
+            return false;
+        };
+        let text = self.buffer.get_text(start, end);
+        self.copy_text(&text);
+
+        true
+    }
+
+    /// Copies text to the clipboard. Without a local clipboard, e.g. over SSH, the terminal is
+    /// asked to copy it instead.
+    fn copy_text(&mut self, text: &str) {
+        if self.clipboard.copy(text) {
+            return;
+        }
+
+        if let Err(err) = terminal::copy_to_clipboard(text) {
+            self.status_message = Some(format!("could not copy: {err}"));
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Copies the current line including its line break, or cuts it if `cut` is set.
+    fn copy_line(&mut self, cut: bool) {
+        let row = self.cursor_location.row;
+        let Some(line) = self.buffer.get_line(row) else {
+            return;
+        };
+        let text = format!("{}\n", &*line);
+        self.copy_text(&text);
+        self.clipboard.copied_line = Some(text);
+
+        if cut {
+            self.delete_line();
+        }
+    }
+
+    /// Deletes the current line as its own undo step, keeping the cursor in the same cell of the
+    /// line that takes its place. The deleted line can be pasted again from the clipboard
+    /// history.
+    fn delete_line(&mut self) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        let row = self.cursor_location.row;
+        let display_column = self.buffer.get_display_column(self.cursor_location);
+        let line_end = |row| Location {
+            row,
+            col: self.buffer.get_line_length(row),
+        };
+
+        // The last line takes the line break before it, so no empty line is left behind.
+        let (start, end) = if row < self.buffer.get_last_line_index() {
+            (
+                Location { row, col: 0 },
+                Location {
+                    row: row + 1,
+                    col: 0,
+                },
+            )
+        } else if row > 0 {
+            (line_end(row - 1), line_end(row))
+        } else {
+            (Location { row, col: 0 }, line_end(row))
+        };
+
+        let text = self.buffer.get_text(start, end);
+        self.clipboard
+            .remember(text.strip_prefix('\n').unwrap_or(&text));
+
+        self.buffer.history.close_group();
+        self.buffer.delete(start, end);
+        self.buffer.history.close_group();
+
+        let row = row.min(self.buffer.get_last_line_index());
+        self.move_cursor_to(Location {
+            row,
+            col: self.buffer.get_column_at_width(row, display_column),
+        });
+    }
+
+    /// Inserts the text on the clipboard at the cursor, replacing the selection. A line copied
+    /// without a selection is inserted above the current line instead.
+    fn paste_clipboard(&mut self) {
+        let Some(text) = self.clipboard.paste() else {
+            self.status_message = Some("clipboard is empty".to_string());
+            self.needs_redraw = true;
+            return;
+        };
+
+        if self.selection_anchor.is_some() || self.clipboard.copied_line.as_ref() != Some(&text) {
+            self.insert_pasted_text(&text);
+            return;
+        }
+
+        // A copied line goes above the current line, which keeps the cursor.
+        let col = self.cursor_location.col;
+        self.cursor_location.col = 0;
+        self.insert_pasted_text(&text);
+        self.cursor_location.col = col;
+    }
+
+    /// Returns whether typed text goes into the buffer, rather than into a prompt, panel or an
+    /// answer to a question.
+    pub fn accepts_typed_text(&self) -> bool {
+        self.pending_executable.is_none()
+            && self.pending_drop.is_none()
+            && self.prompt.is_none()
+            && self.explorer.is_none()
+            && self.todo_panel.is_none()
+            && self.bookmark_picker.is_none()
+            && self.clipboard_picker.is_none()
+            && self.undo_panel.is_none()
+            && self.rename.is_none()
+            && self.snippet.is_none()
+            && self.completion.is_none()
+            && !(self.block_selection && self.selection_anchor.is_some())
+    }
+
+    /// Inserts text typed at once, like the text committed by an input method, replacing the
+    /// selection.
+    pub fn insert_typed_text(&mut self, text: &str) {
+        if self.status_message.take().is_some() {
+            self.needs_redraw = true;
+        }
+
+        if !self.ensure_writable() {
+            return;
+        }
+
+        self.delete_selection();
+        self.buffer.history.close_group();
+        self.cursor_location = self.buffer.insert_str(self.cursor_location, text);
+        self.buffer.history.close_group();
+        self.format_table_while_typing();
+        self.update_scroll();
+        self.needs_redraw = true;
+    }
+
+    /// Inserts a character at the cursor and moves the cursor past it.
+    fn insert_char(&mut self, ch: char) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        self.cursor_location = self.buffer.insert_char(self.cursor_location, ch);
+        self.format_table_while_typing();
+
+        self.update_scroll();
+        self.needs_redraw = true;
+    }
+
+    /// Expands the snippet named like the word before the cursor, or indents if there is none.
+    fn expand_snippet_or_indent(&mut self) {
+        if !self.expand_snippet() {
+            self.insert_indentation();
+        }
+    }
+
+    /// Inserts one level of indentation at the cursor.
+    fn insert_indentation(&mut self) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        let display_column = self.buffer.get_display_column(self.cursor_location);
+        let text = self.buffer.indentation.text_at(display_column);
+        self.cursor_location = self.buffer.insert_str(self.cursor_location, &text);
+
+        self.update_scroll();
+        self.needs_redraw = true;
+    }
+
+    /// Splits the line at the cursor and moves the cursor past the indentation of the new line.
+    ///
+    /// If `continue_line` is set, list items and comments of continued filetypes start the new
+    /// line with their marker, while pressing Enter on an empty item removes its marker instead.
+    fn split_line(&mut self, continue_line: bool) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        let continuation = self
+            .get_continuation()
+            .filter(|_| continue_line && self.buffer.binary.is_none());
+        let row = self.cursor_location.row;
+
+        self.cursor_location = match continuation {
+            Some(Continuation::Prefix(prefix)) => self
+                .buffer
+                .insert_str(self.cursor_location, &format!("\n{prefix}")),
+            Some(Continuation::End(range)) => {
+                let Some(line) = self.buffer.get_line(row) else {
+                    return;
+                };
+                let start = Location {
+                    row,
+                    col: line.grapheme_index(range.start),
+                };
+                let end = Location {
+                    row,
+                    col: line.grapheme_index(range.end),
+                };
+                self.buffer.delete(start, end)
+            }
+            None => self.buffer.split_line(self.cursor_location),
+        };
+
+        self.update_scroll();
+        self.needs_redraw = true;
+    }
+
+    /// Returns how the line at the cursor is continued, if its filetype is continued.
+    fn get_continuation(&self) -> Option<Continuation> {
+        let filetype = continuation::get_filetype(self.buffer.path.as_deref()?)?;
+        if !self
+            .continued_filetypes
+            .iter()
+            .any(|name| name == filetype.name)
+        {
+            return None;
+        }
+
+        let line = self.buffer.get_line(self.cursor_location.row)?;
+        filetype.continue_line(&line, line.byte_index(self.cursor_location.col))
+    }
+
+    /// Handles an input event while the command line is open.
+    fn handle_prompt_key_event(&mut self, key_event: &KeyEvent) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+
+        self.needs_redraw = true;
+
+        match prompt.handle_key_event(key_event) {
+            PromptResult::Pending => (),
+            PromptResult::Cancelled => self.prompt = None,
+            PromptResult::Submitted(input) => {
+                self.prompt = None;
+
+                let current = self.buffer.path.as_deref().map(Path::to_string_lossy);
+                let alternate = self.alternate_path.as_deref().map(Path::to_string_lossy);
+
+                // Expressions use `%` for the remainder, so placeholders are not expanded in them.
+                let input = if input.trim_start().starts_with('=') {
+                    Ok(input)
+                } else {
+                    command::expand_placeholders(&input, current.as_deref(), alternate.as_deref())
+                };
+                let result = input
+                    .and_then(|input| Command::parse(&input))
+                    .and_then(|command| self.execute_command(command));
+
+                if let Err(err) = result {
+                    self.status_message = Some(err);
+                }
+            }
+        }
+    }
+
+    /// Executes a command entered on the command line.
+    fn execute_command(&mut self, command: Command) -> Result<(), String> {
+        match command {
+            Command::Set { option, enabled } => self.set_option(&option, enabled)?,
+            Command::SetValue { option, value } => self.set_option_value(&option, &value)?,
+            Command::Buffers => self.list_buffers(),
+            Command::Unload => self.unload_buffers(),
+            Command::Delete { permanently } => self.delete_file(permanently)?,
+            Command::ChangeDirectory(dir) => {
+                let dir = paths::resolve(&dir, &self.working_dir);
+                self.working_dir = fs::canonicalize(&dir)
+                    .ok()
+                    .filter(|dir| dir.is_dir())
+                    .ok_or_else(|| format!("not a directory: {}", dir.display()))?;
+                self.bookmarks = Bookmarks::load(&self.working_dir);
+            }
+            Command::PrintDirectory => {
+                self.status_message = Some(self.working_dir.display().to_string());
+            }
+            Command::Rename => self.start_rename()?,
+            Command::Evaluate(expression) => self.insert_expression_result(&expression)?,
+            Command::Date(format) => {
+                let format = format.as_deref().unwrap_or(&self.date_format);
+                self.insert_generated_text(&DateTime::now().format(format));
+            }
+            Command::Time(format) => {
+                let format = format.as_deref().unwrap_or(&self.time_format);
+                self.insert_generated_text(&DateTime::now().format(format));
+            }
+            Command::Uuid => {
+                let uuid = random::uuid().map_err(|err| format!("could not generate: {err}"))?;
+                self.insert_generated_text(&uuid);
+            }
+            Command::Encode(transform) => {
+                self.transform_selection(|text| Ok(transform.encode(text)))?;
+            }
+            Command::Decode(transform) => {
+                self.transform_selection(|text| transform.decode(text))?;
+            }
+            Command::Checksum { algorithm, line } => self.show_checksum(algorithm, line),
+            Command::Theme(name) => self.theme = Theme::load(&name)?,
+            Command::ToggleCheckbox
+            | Command::ChangeHeadingLevel(_)
+            | Command::FollowLink
+            | Command::FormatTable => self.execute_markdown_command(&command)?,
+            Command::Fold
+            | Command::FoldTopLevel
+            | Command::FoldComments
+            | Command::Unfold
+            | Command::UnfoldAll => self.fold(&command)?,
+            Command::Snippet(name) => {
+                let source = snippet::load(&name, self.buffer.path.as_deref())
+                    .ok_or_else(|| format!("no snippet named {name}"))?;
+                self.insert_snippet(&source);
+            }
+            Command::Random(length) => {
+                let string = random::alphanumeric(length)
+                    .map_err(|err| format!("could not generate: {err}"))?;
+                self.insert_generated_text(&string);
+            }
+            Command::Mark(note) => self.set_bookmark(note)?,
+            Command::Unmark => self.remove_bookmark()?,
+            Command::Marks => self.bookmark_picker = Some(BookmarkPicker::default()),
+            Command::Clipboard => self.clipboard_picker = Some(ClipboardPicker::default()),
+            Command::Todos => {
+                if self.todo_panel.take().is_none() {
+                    self.todo_panel = Some(TodoPanel::new(&self.bookmarks.project_root));
+                    self.refresh_todo_panel();
+                }
+            }
+            Command::UndoList => {
+                self.undo_panel = match self.undo_panel {
+                    Some(_) => None,
+                    None => Some(UndoPanel::new(&self.buffer.history)),
+                };
+            }
+            Command::GotoOffset(offset) => {
+                let Some(bytes) = &self.buffer.binary else {
+                    return Err("offsets can only be used in binary files".to_string());
+                };
+                if offset >= bytes.len() {
+                    return Err(format!("offset past the end of the file: {offset}"));
+                }
+
+                self.move_cursor_to(Location {
+                    row: offset / hex::BYTES_PER_ROW,
+                    col: offset % hex::BYTES_PER_ROW,
+                });
+            }
+        }
+
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Turns an option on or off (`set name` or `set noname`).
+    fn set_option(&mut self, option: &str, enabled: bool) -> Result<(), String> {
+        match option {
+            "safemode" => self.buffer.safe_mode = enabled,
+            "readonly" => self.set_read_only(enabled)?,
+            "write" => self.set_read_only(!enabled)?,
+            "permanentdelete" => self.permanent_delete = enabled,
+            "updateheader" => self.update_header = enabled,
+            "middleclickpaste" => self.middle_click_paste = enabled,
+            "naturalscroll" => self.scroll_settings.natural = enabled,
+            "number" => self.line_numbers = enabled,
+            "warnopen" => self.warn_open_files = enabled,
+            "whitespace" => self.visible_whitespace.tabs_and_trailing = enabled,
+            "eol" => self.visible_whitespace.newlines = enabled,
+            "cursorline" => self.highlight_current_line = enabled,
+            "insert_final_newline" => self.insert_final_newline = enabled,
+            "trim_final_newlines" => self.trim_final_newlines = enabled,
+            "stickyheader" => self.sticky_header = enabled,
+            "stickydefinitions" => self.sticky_definitions = enabled,
+            "autotable" => self.format_tables = enabled,
+            "hidden" => {
+                self.walk_options.hidden = enabled;
+                self.refresh_todo_panel();
+            }
+            "bidi" => {
+                bidi::set_enabled(enabled);
+                self.needs_redraw = true;
+            }
+            "bom" => self.set_byte_order_mark(enabled)?,
+            _ => return Err(format!("unknown option: {option}")),
+        }
+
+        Ok(())
+    }
+
+    /// Makes the buffer read-only or writable. Hex dumps and files that are still loading stay
+    /// read-only, since they cannot be saved.
+    fn set_read_only(&mut self, read_only: bool) -> Result<(), String> {
+        if !read_only && self.buffer.binary.is_some() {
+            return Err("binary files cannot be edited".to_string());
+        }
+
+        if !read_only && self.buffer.partial {
+            return Err("the file is still loading".to_string());
+        }
+
+        if !read_only && self.buffer.unloaded {
+            return Err("the file is not loaded".to_string());
+        }
+
+        self.buffer.read_only = read_only;
+        Ok(())
+    }
+
+    /// Adds or removes the byte order mark written at the start of the file.
+    fn set_byte_order_mark(&mut self, enabled: bool) -> Result<(), String> {
+        if enabled && self.buffer.encoding == Encoding::Latin1 {
+            return Err("Latin-1 files cannot have a byte order mark".to_string());
+        }
+
+        if enabled != self.buffer.byte_order_mark {
+            self.buffer.byte_order_mark = enabled;
+            self.buffer.modified = true;
+        }
+
+        Ok(())
+    }
+
+    /// Sets an option to a value (`set name=value`).
+    fn set_option_value(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "pathdisplay" => {
+                self.path_display = PathDisplay::parse(value)
+                    .ok_or_else(|| format!("invalid path display: {value}"))?;
+            }
+            "lineending" => {
+                let line_ending = LineEnding::parse(value)
+                    .ok_or_else(|| format!("invalid line ending: {value}"))?;
+
+                if line_ending != self.buffer.line_ending {
+                    self.buffer.line_ending = line_ending;
+                    self.buffer.modified = true;
+                }
+            }
+            "wheelstep" => {
+                self.scroll_settings.vertical_step = value
+                    .parse()
+                    .map_err(|_| format!("invalid scroll step: {value}"))?;
+            }
+            "hscrollstep" => {
+                self.scroll_settings.horizontal_step = value
+                    .parse()
+                    .map_err(|_| format!("invalid scroll step: {value}"))?;
+            }
+            "continuation" => {
+                self.continued_filetypes = value
+                    .split(',')
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            "ignore" => {
+                self.walk_options.ignore = value
+                    .split(',')
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                self.refresh_todo_panel();
+            }
+            "todotags" => {
+                self.todo_tags = value.split(',').map(str::to_string).collect();
+                self.refresh_todo_panel();
+            }
+            "scrolloff" => {
+                self.scroll_off = value
+                    .parse()
+                    .map_err(|_| format!("invalid number of lines: {value}"))?;
+                self.update_scroll();
+                self.needs_redraw = true;
+            }
+            "dateformat" => self.date_format = value.to_string(),
+            "timeformat" => self.time_format = value.to_string(),
+            "executable" => {
+                self.executable_policy = ExecutablePolicy::parse(value)
+                    .ok_or_else(|| format!("invalid executable policy: {value}"))?;
+            }
+            _ => return Err(format!("unknown option: {option}")),
+        }
+
+        Ok(())
+    }
+
+    /// Inserts the result of an arithmetic expression at the cursor.
+    fn insert_expression_result(&mut self, expression: &str) -> Result<(), String> {
+        let value = expression::evaluate(expression)?;
+        self.insert_generated_text(&expression::format_value(value));
+
+        Ok(())
+    }
+
+    /// Inserts text produced by a command at the cursor as its own undo step.
+    fn insert_generated_text(&mut self, text: &str) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        self.buffer.history.close_group();
+        self.cursor_location = self.buffer.insert_str(self.cursor_location, text);
+        self.update_scroll();
+    }
+
+    /// Shows the checksum of the file or of the current line in the status bar.
+    fn show_checksum(&mut self, algorithm: Algorithm, line_only: bool) {
+        let selected = self.get_block_text().or_else(|| {
+            self.get_selection()
+                .map(|(start, end)| self.buffer.get_text(start, end))
+        });
+
+        let bytes = if let Some(text) = selected {
+            text.into_bytes()
+        } else if line_only {
+            let line = self.buffer.get_line(self.cursor_location.row);
+            line.map(|line| line.to_string().into_bytes())
+                .unwrap_or_default()
+        } else {
+            self.buffer.to_bytes()
+        };
+
+        let digest = algorithm.digest(&bytes);
+        self.copy_text(&digest);
+        self.status_message = Some(format!("{digest} (copied)"));
+    }
+
+    /// Replaces the selected text with the result of a transform as a single undo step, or the
+    /// current line if nothing is selected. Each row of a block selection is transformed on its
+    /// own.
+    fn transform_selection(
+        &mut self,
+        transform: impl Fn(&str) -> Result<String, String>,
+    ) -> Result<(), String> {
+        if let Some(rows) = self.get_block_selection() {
+            let transformed = rows
+                .iter()
+                .map(|(row, cols)| {
+                    let start = Location {
+                        row: *row,
+                        col: cols.start,
+                    };
+                    transform(&self.buffer.get_text(
+                        start,
+                        Location {
+                            col: cols.end,
+                            ..start
+                        },
+                    ))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if !self.ensure_writable() {
+                return Ok(());
+            }
+
+            self.buffer.history.close_group();
+            for ((row, cols), text) in rows.into_iter().zip(transformed) {
+                let start = Location {
+                    row,
+                    col: cols.start,
+                };
+                self.buffer.delete(
+                    start,
+                    Location {
+                        col: cols.end,
+                        ..start
+                    },
+                );
+                self.buffer.insert_str(start, &text);
+            }
+            self.buffer.history.close_group();
+
+            self.selection_anchor = None;
+            self.move_cursor_to(self.cursor_location);
+            return Ok(());
+        }
+
+        let Some((start, end)) = self.get_selection() else {
+            return self.transform_line(transform);
+        };
+        let transformed = transform(&self.buffer.get_text(start, end))?;
+        if !self.ensure_writable() {
+            return Ok(());
+        }
+
+        // The transformed text stays selected.
+        self.buffer.history.close_group();
+        self.buffer.delete(start, end);
+        self.cursor_location = self.buffer.insert_str(start, &transformed);
+        self.buffer.history.close_group();
+        self.selection_anchor = Some(start);
+        self.update_scroll();
+        self.needs_redraw = true;
+
+        Ok(())
+    }
+
+    /// Replaces the current line with the result of a transform as a single undo step.
+    fn transform_line(
+        &mut self,
+        transform: impl FnOnce(&str) -> Result<String, String>,
+    ) -> Result<(), String> {
+        let row = self.cursor_location.row;
+        let Some(line) = self.buffer.get_line(row) else {
+            return Ok(());
+        };
+        let transformed = transform(&line)?;
+        if transformed == *line || !self.ensure_writable() {
+            return Ok(());
+        }
+
+        self.buffer.history.close_group();
+        self.buffer.replace_line(row, &transformed);
+        self.buffer.history.close_group();
+        self.cursor_location = Location { row, col: 0 };
+        self.update_scroll();
+
+        Ok(())
+    }
+
+    /// Shows the open buffers together with their memory usage in the status bar.
+    fn list_buffers(&mut self) {
+        let buffers: Vec<String> = iter::once(&self.buffer)
+            .chain(
+                self.other_buffers
+                    .iter()
+                    .map(|background| &background.buffer),
+            )
+            .enumerate()
+            .map(|(idx, buffer)| {
+                let size = if buffer.unloaded {
+                    "unloaded".to_string()
+                } else {
+                    format_byte_size(buffer.memory_usage())
+                };
+
+                format!("{}: {} ({size})", idx + 1, self.format_buffer_path(buffer))
+            })
+            .collect();
+        self.status_message = Some(buffers.join("  "));
+    }
+
+    /// Frees the memory of the background buffers whose contents can be loaded from their files
+    /// again, keeping their cursor and scroll position.
+    fn unload_buffers(&mut self) {
+        let mut count = 0;
+        let mut freed = 0;
+
+        for background in &mut self.other_buffers {
+            let memory_usage = background.buffer.memory_usage();
+
+            if let Some(unloaded) = background.buffer.unload() {
+                background.buffer = unloaded;
+                count += 1;
+                freed += memory_usage;
+            }
+        }
+
+        self.status_message = Some(format!(
+            "unloaded {count} buffers, freed {}",
+            format_byte_size(freed)
+        ));
+    }
+
+    /// Fails unless the buffer is a Markdown file.
+    fn ensure_markdown(&self) -> Result<(), String> {
+        let filetype = self
+            .buffer
+            .path
+            .as_deref()
+            .and_then(continuation::get_filetype);
+        if filetype.is_some_and(|filetype| filetype.name == "markdown") {
+            Ok(())
+        } else {
+            Err("not a Markdown file".to_string())
+        }
+    }
+
+    /// Executes one of the commands editing or following the Markdown on the current line.
+    fn execute_markdown_command(&mut self, command: &Command) -> Result<(), String> {
+        self.ensure_markdown()?;
+
+        match *command {
+            Command::ToggleCheckbox => self.transform_line(|line| {
+                markdown::toggle_checkbox(line).ok_or_else(|| "not a list item".to_string())
+            }),
+            Command::ChangeHeadingLevel(delta) => self.transform_line(|line| {
+                Ok(markdown::change_heading_level(line, delta).unwrap_or_else(|| line.to_string()))
+            }),
+            Command::FormatTable if !self.format_table() => Err("not in a table".to_string()),
+            Command::FollowLink => self.follow_link(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Folds the block the cursor is in, the blocks opened by lines that are not indented or the
+    /// comments spanning several lines, or opens folds, depending on the command. Blocks are
+    /// derived from indentation like the scopes of the sticky header.
+    fn fold(&mut self, command: &Command) -> Result<(), String> {
+        if self.buffer.binary.is_some() {
+            return Err("hex dumps cannot be folded".to_string());
+        }
+
+        let ranges = match command {
+            Command::Unfold => {
+                return if self.buffer.folds.remove_at(self.cursor_location.row) {
+                    Ok(())
+                } else {
+                    Err("no fold here".to_string())
+                };
+            }
+            Command::UnfoldAll => {
+                self.buffer.folds.clear();
+                return Ok(());
+            }
+            Command::FoldTopLevel => self.get_top_level_blocks(),
+            Command::FoldComments => self.get_comment_blocks()?,
+            _ => self.get_block_around_cursor().into_iter().collect(),
+        };
+        if ranges.is_empty() {
+            return Err("nothing to fold".to_string());
+        }
+
+        let line_count = self.buffer.get_line_count();
+        for (start, end) in ranges {
+            self.buffer.folds.add(start, end, line_count);
+        }
+
+        // The cursor moves out of the folded lines, which would open the fold again otherwise.
+        let row = self.cursor_location.row;
+        let start = self.buffer.folds.visible_rows(row, false).next();
+        if let Some(start) = start.filter(|start| *start != row) {
+            self.move_cursor_to(Location { row: start, col: 0 });
+        }
+        self.update_scroll();
+
+        Ok(())
+    }
+
+    /// Returns the first and last row of the innermost block around the cursor, which may be
+    /// opened by the cursor line itself.
+    fn get_block_around_cursor(&self) -> Option<(usize, usize)> {
+        let row = self.cursor_location.row;
+        let enclosing = scope::enclosing_lines(&self.buffer, row, usize::MAX);
+
+        iter::once(row)
+            .chain(enclosing.into_iter().rev())
+            .find_map(|start| scope::block_end(&self.buffer, start).map(|end| (start, end)))
+    }
+
+    /// Returns the first and last row of each block opened by a line that is not indented.
+    fn get_top_level_blocks(&self) -> Vec<(usize, usize)> {
+        let mut blocks = Vec::new();
+        let mut row = 0;
+
+        while row < self.buffer.get_line_count() {
+            let is_top_level = self
+                .buffer
+                .get_line(row)
+                .is_some_and(|line| !line.is_empty() && !line.starts_with([' ', '\t']));
+
+            match scope::block_end(&self.buffer, row).filter(|_| is_top_level) {
+                Some(end) => {
+                    blocks.push((row, end));
+                    row = end + 1;
+                }
+                None => row += 1,
+            }
+        }
+
+        blocks
+    }
+
+    /// Returns the first and last row of each run of comment lines longer than one line.
+    fn get_comment_blocks(&self) -> Result<Vec<(usize, usize)>, String> {
+        let filetype = self
+            .buffer
+            .path
+            .as_deref()
+            .and_then(continuation::get_filetype)
+            .ok_or("the comments of this file type are not known")?;
+
+        let mut blocks = Vec::new();
+        let mut start = None;
+        for (row, line) in self.buffer.lines().enumerate() {
+            match (filetype.is_comment(&line), start) {
+                (true, None) => start = Some(row),
+                (false, Some(first)) => {
+                    if row - first > 1 {
+                        blocks.push((first, row - 1));
+                    }
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+        if let Some(first) = start {
+            let last = self.buffer.get_last_line_index();
+            if last > first {
+                blocks.push((first, last));
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    /// Lines up the columns of the Markdown table around the cursor, keeping the cursor in its
+    /// cell. Returns whether the cursor is in a table.
+    fn format_table(&mut self) -> bool {
+        let row = self.cursor_location.row;
+        let is_table_row = |row: usize| {
+            self.buffer
+                .get_line(row)
+                .is_some_and(|line| markdown::is_table_row(&line))
+        };
+        if !is_table_row(row) {
+            return false;
+        }
+
+        let first = (0..row)
+            .rev()
+            .take_while(|row| is_table_row(*row))
+            .last()
+            .unwrap_or(row);
+        let last = (row + 1..=self.buffer.get_last_line_index())
+            .take_while(|row| is_table_row(*row))
+            .last()
+            .unwrap_or(row);
+
+        let rows: Vec<String> = (first..=last)
+            .filter_map(|row| self.buffer.get_line(row).map(|line| line.to_string()))
+            .collect();
+        let row_refs: Vec<&str> = rows.iter().map(String::as_str).collect();
+        if !self.ensure_writable() {
+            return true;
+        }
+
+        let Some(line) = self.buffer.get_line(row) else {
+            return false;
+        };
+        let cursor = (row - first, line.byte_index(self.cursor_location.col));
+        let Some((formatted, cursor_byte)) = markdown::format_table(&row_refs, cursor) else {
+            return true;
+        };
+
+        for (idx, (old, new)) in rows.iter().zip(&formatted).enumerate() {
+            if old != new {
+                self.buffer.replace_line(first + idx, new);
+            }
+        }
+
+        if let Some(line) = self.buffer.get_line(row) {
+            self.cursor_location.col = line.grapheme_index(cursor_byte);
+        }
+        self.needs_redraw = true;
+
+        true
+    }
+
+    /// Lines up the table being typed in if tables are formatted while typing. The change is
+    /// part of the undo step of the typing.
+    fn format_table_while_typing(&mut self) {
+        if self.format_tables && self.ensure_markdown().is_ok() {
+            self.format_table();
+        }
+    }
+
+    /// Opens the file the Markdown link under the cursor points to, relative to the directory of
+    /// the buffer. Files that do not exist yet are created, so notes can link to new notes.
+    fn follow_link(&mut self) -> Result<(), String> {
+        let line = self
+            .buffer
+            .get_line(self.cursor_location.row)
+            .ok_or("no link on this line")?;
+        let target = markdown::find_link(&line, line.byte_index(self.cursor_location.col))
+            .ok_or("no link on this line")?;
+        if target.contains("://") || target.starts_with("mailto:") {
+            return Err(format!("not a link to a file: {target}"));
+        }
+
+        let target = target.split('#').next().unwrap_or_default();
+        if target.is_empty() {
+            return Err("the link points into this file".to_string());
+        }
+        if self.buffer.modified {
+            return Err("unsaved changes, save them first".to_string());
+        }
+
+        let dir = self
+            .buffer
+            .path
+            .as_deref()
+            .and_then(Path::parent)
+            .map_or_else(|| self.working_dir.clone(), Path::to_path_buf);
+        self.open_request = Some(paths::resolve(target, &dir));
+
+        Ok(())
+    }
+
+    /// Deletes the file of the buffer, keeping its contents open so it can be saved again.
+    fn delete_file(&mut self, permanently: bool) -> Result<(), String> {
+        let path = self.buffer.path.as_ref().ok_or("no file name")?;
+
+        let message = if permanently || self.permanent_delete {
+            fs::remove_file(path).map_err(|err| format!("could not delete: {err}"))?;
+            format!("deleted {}", path.display())
+        } else {
+            trash::move_to_trash(path).map_err(|err| format!("could not move to trash: {err}"))?;
+            format!("moved {} to the trash", path.display())
+        };
+
+        self.buffer.modified = true;
+        self.status_message = Some(message);
+
+        Ok(())
+    }
+
+    /// Reverts the most recent group of edits and moves the cursor to where it happened.
+    fn undo(&mut self) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        if let Some(location) = self.buffer.undo() {
+            self.move_cursor_to(location);
+        } else {
+            self.status_message = Some("already at oldest change".to_string());
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Applies the most recently undone group of edits again.
+    fn redo(&mut self) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        if let Some(location) = self.buffer.redo() {
+            self.move_cursor_to(location);
+        } else {
+            self.status_message = Some("already at newest change".to_string());
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Moves the cursor to the location of an older or newer edit.
+    fn jump_to_change(&mut self, older: bool) {
+        let location = if older {
+            self.buffer.changes.previous()
+        } else {
+            self.buffer.changes.next()
+        };
+
+        if let Some(location) = location {
+            self.move_cursor_to(location);
+        } else {
+            self.status_message = Some(if older {
+                "already at oldest change".to_string()
+            } else {
+                "already at newest change".to_string()
+            });
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Moves the cursor to the start of the next or previous section, like a function or a
+    /// Markdown heading.
+    fn jump_to_section(&mut self, forward: bool) {
+        let markdown = self.ensure_markdown().is_ok();
+        let row = section::find(&self.buffer, self.cursor_location.row, forward, markdown);
+
+        if let Some(row) = row {
+            self.move_cursor_to(Location { row, col: 0 });
+        } else {
+            self.status_message = Some(if forward {
+                "no section below".to_string()
+            } else {
+                "no section above".to_string()
+            });
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Bookmarks the current line, replacing the note of an existing bookmark.
+    fn set_bookmark(&mut self, note: String) -> Result<(), String> {
+        let path = self.buffer.path.as_deref().ok_or("no file name")?;
+        self.bookmarks.set(path, self.cursor_location.row, note);
+        self.save_bookmarks();
+
+        Ok(())
+    }
+
+    /// Removes the bookmark on the current line.
+    fn remove_bookmark(&mut self) -> Result<(), String> {
+        let path = self.buffer.path.as_deref().ok_or("no file name")?;
+        if !self.bookmarks.remove(path, self.cursor_location.row) {
+            return Err("no bookmark on this line".to_string());
+        }
+        self.save_bookmarks();
+
+        Ok(())
+    }
+
+    /// Writes the bookmarks, reporting failures in the status bar.
+    fn save_bookmarks(&mut self) {
+        if let Err(err) = self.bookmarks.save() {
+            self.status_message = Some(format!("could not save bookmarks: {err}"));
+        }
+    }
+
+    /// Handles a key press while the bookmark picker is open, returns whether the event was
+    /// consumed.
+    fn handle_bookmark_picker_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        let Some(bookmark_picker) = &mut self.bookmark_picker else {
+            return false;
+        };
+
+        match key_event.code {
+            KeyCode::Up => bookmark_picker.move_selection(-1, &self.bookmarks),
+            KeyCode::Down => bookmark_picker.move_selection(1, &self.bookmarks),
+            KeyCode::Delete => {
+                if bookmark_picker.selected < self.bookmarks.entries.len() {
+                    self.bookmarks.entries.remove(bookmark_picker.selected);
+                    bookmark_picker.move_selection(0, &self.bookmarks);
+                    self.save_bookmarks();
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(bookmark) = self.bookmarks.entries.get(bookmark_picker.selected) {
+                    let Bookmark { path, row, .. } = bookmark.clone();
+                    if self.jump_to_line(path, row) {
+                        self.bookmark_picker = None;
+                    }
+                }
+            }
+            KeyCode::Esc => self.bookmark_picker = None,
+            _ => return false,
+        }
 
-use crossterm::{
-    event::{Event, KeyCode, KeyEvent, KeyEventKind},
-    style::Color,
-};
+        self.needs_redraw = true;
+        true
+    }
 
-use crate::{
-    buffer::Buffer,
-    terminal::{self, Position, Size, TResult},
-};
+    /// Handles a key press while the clipboard picker is open, returns whether the event was
+    /// consumed.
+    fn handle_clipboard_picker_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        let Some(clipboard_picker) = &mut self.clipboard_picker else {
+            return false;
+        };
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-pub struct Location {
-    pub row: usize,
-    pub col: usize,
-}
+        match key_event.code {
+            KeyCode::Up => clipboard_picker.move_selection(-1, &self.clipboard),
+            KeyCode::Down => clipboard_picker.move_selection(1, &self.clipboard),
+            KeyCode::Delete => {
+                if self
+                    .clipboard
+                    .history
+                    .remove(clipboard_picker.selected)
+                    .is_some()
+                {
+                    clipboard_picker.move_selection(0, &self.clipboard);
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(text) = self
+                    .clipboard
+                    .history
+                    .get(clipboard_picker.selected)
+                    .cloned()
+                {
+                    self.clipboard_picker = None;
+                    self.clipboard.remember(&text);
+                    self.insert_pasted_text(&text);
+                }
+            }
+            KeyCode::Esc => self.clipboard_picker = None,
+            _ => return false,
+        }
 
-#[derive(Debug, Default)]
-pub struct View {
-    pub buffer: Buffer,
-    pub needs_redraw: bool,
-    pub current_size: Size,
-    pub cursor_location: Location,
-    pub scroll_offset: Location,
-}
+        self.needs_redraw = true;
+        true
+    }
+
+    /// Moves the cursor to a line of a file, loading the file if it is not the current one.
+    /// Returns whether the jump happened, which needs the current buffer to be saved first.
+    fn jump_to_line(&mut self, path: PathBuf, row: usize) -> bool {
+        let is_current = self.buffer.path.as_deref().is_some_and(|current| {
+            path::absolute(current)
+                .is_ok_and(|current| path::absolute(&path).is_ok_and(|path| path == current))
+        });
+
+        if is_current {
+            self.move_to_start_position(&StartPosition::Line(row + 1));
+        } else if self.buffer.modified {
+            self.status_message = Some("unsaved changes, save them first".to_string());
+            return false;
+        } else {
+            self.open_request = Some(path);
+            self.open_request_row = Some(row);
+        }
+
+        true
+    }
+
+    /// Handles a key press while the TODO panel is open, returns whether the event was consumed.
+    fn handle_todo_panel_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        let Some(todo_panel) = &mut self.todo_panel else {
+            return false;
+        };
+
+        match key_event.code {
+            KeyCode::Up => todo_panel.move_selection(-1),
+            KeyCode::Down => todo_panel.move_selection(1),
+            KeyCode::Enter => {
+                if let Some(TodoItem { path, row, .. }) = todo_panel.selected_item().cloned() {
+                    if self.jump_to_line(path, row) {
+                        self.todo_panel = None;
+                    }
+                }
+            }
+            KeyCode::Esc => self.todo_panel = None,
+            _ => return false,
+        }
 
-impl View {
-    /// Loads a new file into the view.
-    pub fn load<P: Into<PathBuf>>(&mut self, path: P) -> TResult<()> {
-        self.buffer = Buffer::from_path(path)?;
         self.needs_redraw = true;
+        true
+    }
 
-        Ok(())
+    /// Scans the project for the tagged comments again if the TODO panel is open.
+    fn refresh_todo_panel(&mut self) {
+        if let Some(todo_panel) = &self.todo_panel {
+            self.todo_scan_request = Some(todo_panel.root.clone());
+        }
     }
 
-    /// Rendes the whole view to the screen.
-    pub fn render(&mut self) -> TResult<()> {
-        if !self.needs_redraw && !self.is_of_sufficient_size() {
-            return Ok(());
+    /// Shows the tagged comments collected below a directory in the TODO panel, unless it was
+    /// closed or opened for another directory since.
+    pub fn show_todo_items(&mut self, root: &Path, items: Vec<TodoItem>) {
+        if let Some(todo_panel) = self
+            .todo_panel
+            .as_mut()
+            .filter(|todo_panel| todo_panel.root == root)
+        {
+            todo_panel.set_items(items);
+            self.needs_redraw = true;
         }
+    }
 
-        self.render_buffer()?;
-        self.render_status_bar()?;
+    /// Handles a key press while the undo panel is open, returns whether the event was consumed.
+    fn handle_undo_panel_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        let Some(undo_panel) = &mut self.undo_panel else {
+            return false;
+        };
 
-        terminal::move_cursor_to(self.get_relative_cursor_position())?;
+        match key_event.code {
+            KeyCode::Up => undo_panel.move_selection(1, &self.buffer.history),
+            KeyCode::Down => undo_panel.move_selection(-1, &self.buffer.history),
+            KeyCode::Enter => {
+                let target = undo_panel.selected;
+                self.restore_undo_state(target);
+            }
+            KeyCode::Esc => self.undo_panel = None,
+            _ => return false,
+        }
 
-        self.needs_redraw = false;
+        self.needs_redraw = true;
+        true
+    }
 
-        Ok(())
+    /// Undoes or redoes edits until the given number of transactions is applied.
+    fn restore_undo_state(&mut self, target: usize) {
+        if !self.ensure_writable() {
+            return;
+        }
+
+        let mut location = None;
+        while self.buffer.history.current_state() > target {
+            location = self.buffer.undo().or(location);
+        }
+        while self.buffer.history.current_state() < target {
+            location = self.buffer.redo().or(location);
+        }
+
+        if let Some(location) = location {
+            self.move_cursor_to(location);
+        }
     }
 
-    /// Returns whether or the window is big enough to render the editor.
-    fn is_of_sufficient_size(&self) -> bool {
-        self.current_size.height > 1 && self.current_size.width > 0
+    /// Moves the cursor to the bracket matching the one under it.
+    fn jump_to_matching_bracket(&mut self) {
+        let matching = brackets::find_match(&self.buffer, self.cursor_location)
+            .filter(|_| self.buffer.binary.is_none());
+
+        if let Some(location) = matching {
+            self.move_cursor_to(location);
+        } else {
+            self.status_message = Some("no matching bracket".to_string());
+            self.needs_redraw = true;
+        }
     }
 
-    /// Returns the height of the buffer.
-    fn buffer_height(&self) -> u16 {
-        self.current_size.height.saturating_sub(1)
+    /// Adds a delta to the number under or after the cursor on its line and moves the cursor to
+    /// its last digit.
+    fn increment_number(&mut self, delta: i64) {
+        let row = self.cursor_location.row;
+        let Some(line) = self
+            .buffer
+            .get_line(row)
+            .filter(|_| self.buffer.binary.is_none())
+        else {
+            return;
+        };
+        let Some(range) = number::find_number(&line, line.byte_index(self.cursor_location.col))
+        else {
+            self.status_message = Some("no number on this line".to_string());
+            self.needs_redraw = true;
+            return;
+        };
+        let Some(incremented) = number::increment(&line[range.clone()], delta) else {
+            self.status_message = Some("number is too large".to_string());
+            self.needs_redraw = true;
+            return;
+        };
+        let start = Location {
+            row,
+            col: line.grapheme_index(range.start),
+        };
+        let end = Location {
+            row,
+            col: line.grapheme_index(range.end),
+        };
+
+        if !self.ensure_writable() {
+            return;
+        }
+
+        self.buffer.history.close_group();
+        self.buffer.delete(start, end);
+        let end = self.buffer.insert_str(start, &incremented);
+        self.buffer.history.close_group();
+        self.move_cursor_to(Location {
+            row,
+            col: end.col - 1,
+        });
     }
 
-    /// Renders the buffer to the screen.
-    fn render_buffer(&mut self) -> TResult<()> {
-        for pos_y in 0..self.buffer_height() {
-            let buffer_row_index = pos_y as usize + self.scroll_offset.row;
+    /// Moves the cursor to a location, clamping it to the buffer contents.
+    fn move_cursor_to(&mut self, location: Location) {
+        self.cursor_location.row = location.row.min(self.buffer.get_last_line_index());
+        self.cursor_location.col = location
+            .col
+            .min(self.buffer.get_line_length(self.cursor_location.row));
 
-            if let Some(line) = self.buffer.get_truncated_line(
-                buffer_row_index,
-                self.scroll_offset.col,
-                self.current_size.width as usize,
-            ) {
-                render_line(pos_y, line)?;
-            } else {
-                render_line(pos_y, "~")?;
+        self.update_scroll();
+        self.needs_redraw = true;
+    }
+
+    /// Shows a listing of the given directory in place of the buffer.
+    pub fn open_explorer(&mut self, dir: &Path) {
+        match Explorer::read(dir) {
+            Ok(explorer) => self.explorer = Some(explorer),
+            Err(err) => {
+                self.status_message = Some(format!("could not list {}: {err}", dir.display()));
             }
         }
 
-        Ok(())
+        self.needs_redraw = true;
     }
 
-    /// Renders the statusbar to the screen.
-    fn render_status_bar(&mut self) -> TResult<()> {
-        let Size { height, width } = self.current_size;
+    /// Handles an input event while the explorer is shown.
+    fn handle_explorer_key_event(&mut self, key_event: &KeyEvent) {
+        let page = isize::try_from(self.buffer_height()).unwrap_or(isize::MAX);
+        let height = self.buffer_height() as usize;
+        let Some(explorer) = &mut self.explorer else {
+            return;
+        };
 
-        terminal::move_cursor_to(Position {
-            x: 0,
-            y: height - 1,
-        })?;
+        self.needs_redraw = true;
 
-        // Set status bar colors and clear line.
-        terminal::set_foreground_color(Color::Black)?;
-        terminal::set_background_color(Color::White)?;
-        terminal::clear_line()?;
+        match key_event.code {
+            KeyCode::Up => explorer.move_selection(-1, height),
+            KeyCode::Down => explorer.move_selection(1, height),
+            KeyCode::PageUp => explorer.move_selection(-page, height),
+            KeyCode::PageDown => explorer.move_selection(page, height),
+            KeyCode::Home => explorer.move_selection(isize::MIN, height),
+            KeyCode::End => explorer.move_selection(isize::MAX, height),
+            KeyCode::Enter => {
+                let Some(path) = explorer.selected_path() else {
+                    return;
+                };
 
-        // Display current file path.
-        terminal::print(self.buffer.path.to_str().unwrap())?;
+                if explorer.is_dir_selected() {
+                    self.open_explorer(&path);
+                } else if self.buffer.modified {
+                    self.status_message = Some("unsaved changes, save them first".to_string());
+                } else {
+                    self.explorer = None;
+                    self.open_request = Some(path);
+                }
+            }
+            KeyCode::Backspace => {
+                if let Some(parent) = explorer.dir.parent().map(Path::to_path_buf) {
+                    self.open_explorer(&parent);
+                }
+            }
+            KeyCode::Esc => self.explorer = None,
+            _ => (),
+        }
+    }
 
-        // Display cursor position.
-        let current_location = format!(
-            "LINE {} COL {}",
-            self.cursor_location.row + 1,
-            self.cursor_location.col + 1
-        );
-        terminal::move_cursor_to(Position {
-            x: width.saturating_sub(current_location.len() as u16),
-            y: height - 1,
-        })?;
-        terminal::print(current_location)?;
+    /// Starts editing all occurrences of the word under the cursor.
+    fn start_rename(&mut self) -> Result<(), String> {
+        if !self.ensure_writable() {
+            return Ok(());
+        }
 
-        // Reset status bar colors.
-        terminal::set_foreground_color(Color::White)?;
-        terminal::set_background_color(Color::Black)?;
+        let rename = Rename::from_buffer(&self.buffer, self.cursor_location)
+            .ok_or("no word under the cursor")?;
+
+        self.buffer.history.close_group();
+        self.status_message = Some(format!(
+            "renaming {} occurrence(s), Esc to finish",
+            rename.occurrences.len()
+        ));
+        self.rename = Some(rename);
 
         Ok(())
     }
 
-    /// Calculates the cursor position relative to the current scroll position.
-    fn get_relative_cursor_position(&self) -> Position {
-        #[allow(clippy::cast_possible_truncation)]
-        Position {
-            x: self
-                .cursor_location
-                .col
-                .saturating_sub(self.scroll_offset.col) as u16,
-            y: self
-                .cursor_location
-                .row
-                .saturating_sub(self.scroll_offset.row) as u16,
+    /// Handles an input event while renaming, returns whether the event was consumed.
+    fn handle_rename_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        let Some(rename) = &mut self.rename else {
+            return false;
+        };
+        let Some(primary) = rename.occurrence_at(self.cursor_location) else {
+            self.rename = None;
+            return false;
+        };
+
+        self.needs_redraw = true;
+        let offset = self.cursor_location.col - rename.occurrences[primary].col;
+
+        let offset = match key_event.code {
+            KeyCode::Char(ch)
+                if key_event.modifiers == KeyModifiers::NONE
+                    || key_event.modifiers == KeyModifiers::SHIFT =>
+            {
+                let length = rename.length;
+                rename.insert(&mut self.buffer, offset, ch.encode_utf8(&mut [0; 4]));
+                offset + rename.length - length
+            }
+            KeyCode::Backspace if offset > 0 => {
+                rename.delete_before(&mut self.buffer, offset);
+                offset - 1
+            }
+            KeyCode::Left if offset > 0 => offset - 1,
+            KeyCode::Right if offset < rename.length => offset + 1,
+            KeyCode::Esc | KeyCode::Enter => {
+                self.rename = None;
+                self.buffer.history.close_group();
+                return true;
+            }
+
+            // Any other key finishes renaming and is handled as usual.
+            _ => {
+                self.rename = None;
+                self.buffer.history.close_group();
+                return false;
+            }
+        };
+
+        self.cursor_location = Location {
+            row: rename.occurrences[primary].row,
+            col: rename.occurrences[primary].col + offset,
+        };
+        self.update_scroll();
+
+        true
+    }
+
+    /// Expands the word before the cursor if a snippet is named like it, returns whether it was
+    /// expanded.
+    fn expand_snippet(&mut self) -> bool {
+        let Some(line) = self
+            .buffer
+            .get_line(self.cursor_location.row)
+            .filter(|_| self.buffer.binary.is_none())
+        else {
+            return false;
+        };
+        let cursor_byte = line.byte_index(self.cursor_location.col);
+        let (start, end) = get_word_bounds(&line, cursor_byte);
+        if start == end || end != cursor_byte {
+            return false;
+        }
+
+        let Some(source) = snippet::load(&line[start..end], self.buffer.path.as_deref()) else {
+            return false;
+        };
+        let start = Location {
+            row: self.cursor_location.row,
+            col: line.grapheme_index(start),
+        };
+        if !self.ensure_writable() {
+            return true;
+        }
+
+        self.buffer.history.close_group();
+        self.cursor_location = self.buffer.delete(start, self.cursor_location);
+        self.insert_snippet(&source);
+
+        true
+    }
+
+    /// Inserts a snippet at the cursor, indenting its lines like the current one, and visits its
+    /// first placeholder.
+    fn insert_snippet(&mut self, source: &str) {
+        if !self.ensure_writable() {
+            return;
         }
+
+        self.delete_selection();
+        self.rename = None;
+
+        let indent = self
+            .buffer
+            .get_line(self.cursor_location.row)
+            .map(|line| indent::leading_whitespace(&line).to_string())
+            .unwrap_or_default();
+        let snippet = Snippet::parse(source, &indent);
+
+        let start = self.cursor_location;
+        self.cursor_location = self.buffer.insert_str(start, &snippet.text);
+        self.snippet = Some(SnippetSession::new(&self.buffer, start, &snippet));
+        self.visit_snippet_field(0);
     }
 
-    /// Handles events, e.g. input or resizing.
-    pub fn handle_event(&mut self, event: &Event) {
-        match event {
-            Event::Key(key_event) => self.handle_key_event(key_event),
-            Event::Resize(width, height) => self.handle_resize_event(*width, *height),
+    /// Moves the cursor to the end of a field of the snippet, ending the snippet at its final
+    /// cursor position.
+    fn visit_snippet_field(&mut self, idx: usize) {
+        let Some(snippet) = &mut self.snippet else {
+            return;
+        };
 
-            _ => (),
+        snippet.active = idx.min(snippet.fields.len() - 1);
+        snippet.replaces_default = true;
+
+        let field = snippet.active_field();
+        if let Some(occurrence) = field.occurrences.first() {
+            self.cursor_location = Location {
+                row: occurrence.row,
+                col: occurrence.col + field.length,
+            };
+        }
+
+        if snippet.is_finished() {
+            self.snippet = None;
+            self.buffer.history.close_group();
         }
+
+        self.update_scroll();
+        self.needs_redraw = true;
     }
 
-    /// Handles a resize event.
-    fn handle_resize_event(&mut self, width: u16, height: u16) {
-        self.current_size = Size { width, height };
+    /// Handles an input event while filling in a snippet, returns whether the event was
+    /// consumed. Typing edits the placeholder and its mirrors, Tab and Shift+Tab move between
+    /// placeholders.
+    fn handle_snippet_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        let Some(snippet) = &mut self.snippet else {
+            return false;
+        };
+
+        match key_event.code {
+            KeyCode::Tab => {
+                let next = snippet.active + 1;
+                self.visit_snippet_field(next);
+                return true;
+            }
+            KeyCode::BackTab => {
+                let previous = snippet.active.saturating_sub(1);
+                self.visit_snippet_field(previous);
+                return true;
+            }
+            KeyCode::Esc => {
+                self.snippet = None;
+                self.buffer.history.close_group();
+                self.needs_redraw = true;
+                return true;
+            }
+            _ => (),
+        }
+
+        let Some(primary) = snippet.occurrence_at(self.cursor_location) else {
+            self.snippet = None;
+            return false;
+        };
+
         self.needs_redraw = true;
+        let length = snippet.active_field().length;
+        let offset = self.cursor_location.col - snippet.active_field().occurrences[primary].col;
+
+        let offset = match key_event.code {
+            KeyCode::Char(ch)
+                if key_event.modifiers == KeyModifiers::NONE
+                    || key_event.modifiers == KeyModifiers::SHIFT =>
+            {
+                let offset = if snippet.replaces_default {
+                    snippet.delete(&mut self.buffer, 0, length);
+                    0
+                } else {
+                    offset
+                };
+                let length = snippet.active_field().length;
+                snippet.insert(&mut self.buffer, offset, ch.encode_utf8(&mut [0; 4]));
+                offset + snippet.active_field().length - length
+            }
+            KeyCode::Backspace if snippet.replaces_default => {
+                snippet.delete(&mut self.buffer, 0, length);
+                0
+            }
+            KeyCode::Backspace if offset > 0 => {
+                snippet.delete(&mut self.buffer, offset - 1, offset);
+                offset - 1
+            }
+            KeyCode::Left if offset > 0 => offset - 1,
+            KeyCode::Right if offset < length => offset + 1,
+
+            // Any other key ends the snippet and is handled as usual.
+            _ => {
+                self.snippet = None;
+                self.buffer.history.close_group();
+                return false;
+            }
+        };
+
+        snippet.replaces_default = false;
+        let occurrence = snippet.active_field().occurrences[primary];
+        self.cursor_location = Location {
+            row: occurrence.row,
+            col: occurrence.col + offset,
+        };
+        self.update_scroll();
+
+        true
     }
 
-    /// Handles an input event.
-    fn handle_key_event(&mut self, key_event: &KeyEvent) {
-        if key_event.kind != KeyEventKind::Press {
+    /// Highlights the occurrences that are edited at once, i.e. the ones being renamed or the
+    /// placeholder of a snippet and its mirrors.
+    fn render_occurrences(&self, occurrences: &[Location], length: usize) -> TResult<()> {
+        terminal::set_foreground_color(self.theme.occurrence_text)?;
+        terminal::set_background_color(self.theme.occurrence_background)?;
+
+        for occurrence in occurrences {
+            let Some(y) = self.get_screen_row(occurrence.row) else {
+                continue;
+            };
+            let Some(x) = self
+                .buffer
+                .get_display_column(*occurrence)
+                .checked_sub(self.scroll_offset.col)
+            else {
+                continue;
+            };
+            let Some(line) = self.buffer.get_line(occurrence.row) else {
+                continue;
+            };
+
+            let text = line.into_slice(occurrence.col, occurrence.col + length);
+            let width = Line::new(text.as_ref()).width();
+            if y >= self.buffer_height() as usize || x + width > self.text_width() as usize {
+                continue;
+            }
+
+            #[allow(clippy::cast_possible_truncation)]
+            terminal::move_cursor_to(Position {
+                x: x as u16 + self.gutter_width(),
+                y: y as u16,
+            })?;
+            terminal::print(text)?;
+        }
+
+        terminal::set_foreground_color(self.theme.text)?;
+        terminal::set_background_color(self.theme.background)
+    }
+
+    /// Opens the completion popup for the word before the cursor.
+    fn open_completion(&mut self) {
+        if !self.ensure_writable() {
             return;
         }
 
+        if self.buffer.safe_mode {
+            self.status_message = Some("completion is disabled in safe mode".to_string());
+        } else {
+            self.completion = Completion::from_buffer(&self.buffer, self.cursor_location);
+        }
+
+        self.needs_redraw = true;
+    }
+
+    /// Handles an input event while the completion popup is open, returns whether the event was consumed.
+    fn handle_completion_key_event(&mut self, key_event: &KeyEvent) -> bool {
+        let Some(completion) = &mut self.completion else {
+            return false;
+        };
+
+        self.needs_redraw = true;
+
         match key_event.code {
-            KeyCode::Left
-            | KeyCode::Right
-            | KeyCode::Up
-            | KeyCode::Down
-            | KeyCode::PageUp
-            | KeyCode::PageDown
-            | KeyCode::Home
-            | KeyCode::End => self.move_cursor(key_event.code),
-            _ => (),
+            KeyCode::Down => completion.select_next(),
+            KeyCode::Up => completion.select_previous(),
+            KeyCode::Enter | KeyCode::Tab => {
+                if let Some(text) = completion.completion_text() {
+                    self.cursor_location = self.buffer.insert_str(self.cursor_location, text);
+                    self.update_scroll();
+                }
+
+                self.completion = None;
+            }
+            KeyCode::Esc => self.completion = None,
+
+            // Any other key closes the popup and is handled as usual.
+            _ => {
+                self.completion = None;
+                return false;
+            }
         }
+
+        true
     }
 
     /// Moves the cursor based on a pressed key.
     fn move_cursor(&mut self, key_code: KeyCode) {
+        // Typing after moving the cursor starts a new undo step.
+        self.buffer.history.close_group();
+
+        // Moving between lines keeps the cursor in the same cell rather than the same grapheme.
+        let display_column = self.buffer.get_display_column(self.cursor_location);
+
         match key_code {
             KeyCode::Left => {
                 // If we are at the beginning of a line, go to the end of the previous line.
                 if self.cursor_location.col == 0 {
-                    if self.cursor_location.row != 0 {
-                        self.cursor_location.row = self.cursor_location.row.saturating_sub(1);
-                        self.cursor_location.col =
-                            self.buffer.get_line_length(self.cursor_location.row);
+                    if let Some(row) = self.get_adjacent_row(false) {
+                        self.cursor_location.row = row;
+                        self.cursor_location.col = self.buffer.get_line_length(row);
                     }
                 } else {
                     self.cursor_location.col -= 1;
@@ -185,14 +3628,24 @@ impl View {
 
                 // If we are at the end of the line go to the beginning of the next line.
                 if self.cursor_location.col == current_line_length {
-                    self.cursor_location.row = self.cursor_location.row.saturating_add(1);
+                    let Some(row) = self.get_adjacent_row(true) else {
+                        return;
+                    };
+
+                    self.cursor_location.row = row;
                     self.cursor_location.col = 0;
                 } else {
                     self.cursor_location.col = self.cursor_location.col.saturating_add(1);
                 }
             }
-            KeyCode::Up => self.cursor_location.row = self.cursor_location.row.saturating_sub(1),
-            KeyCode::Down => self.cursor_location.row = self.cursor_location.row.saturating_add(1),
+            KeyCode::Up | KeyCode::Down => {
+                self.cursor_location.row = self
+                    .get_adjacent_row(key_code == KeyCode::Down)
+                    .unwrap_or(self.cursor_location.row);
+                self.cursor_location.col = self
+                    .buffer
+                    .get_column_at_width(self.cursor_location.row, display_column);
+            }
             KeyCode::PageUp => self.cursor_location = Location { row: 0, col: 0 },
             KeyCode::PageDown => {
                 self.cursor_location = Location {
@@ -219,45 +3672,171 @@ impl View {
         self.needs_redraw = true;
     }
 
+    /// Returns the line below or above the cursor line, skipping the lines hidden by folds.
+    /// `None` at either end of the buffer.
+    fn get_adjacent_row(&self, down: bool) -> Option<usize> {
+        self.buffer
+            .folds
+            .visible_rows(self.cursor_location.row, down)
+            .nth(1)
+            .filter(|row| *row <= self.buffer.get_last_line_index())
+    }
+
+    /// Moves the folds along with the edits made since they were last updated, and opens the
+    /// fold hiding the cursor, e.g. after jumping to a line inside it.
+    fn sync_folds(&mut self) {
+        let row = self.cursor_location.row;
+        self.buffer.folds.update(self.buffer.get_line_count(), row);
+
+        if self.buffer.folds.is_hidden(row) {
+            self.buffer.folds.remove_at(row);
+        }
+    }
+
     /// Updates the scroll status to ensure we can always see the cursor.
     fn update_scroll(&mut self) {
+        self.sync_folds();
+
+        // Keep some lines around the cursor visible, at most half of the view and not past the
+        // ends of the buffer. Lines hidden by folds are not counted.
+        let margin = self
+            .scroll_off
+            .min(self.buffer_height().saturating_sub(1) as usize / 2);
+        let margin_above = margin.min(self.cursor_location.row);
+        let margin_below = margin.min(
+            self.buffer
+                .get_last_line_index()
+                .saturating_sub(self.cursor_location.row),
+        );
+
+        let folds = &self.buffer.folds;
+        let first_row = folds
+            .visible_rows(self.cursor_location.row, false)
+            .nth(margin_above)
+            .unwrap_or_default();
+        let last_row = folds
+            .visible_rows(self.cursor_location.row, true)
+            .nth(margin_below)
+            .unwrap_or(self.cursor_location.row)
+            .min(self.buffer.get_last_line_index());
+
         // If we scroll up and are outside the view, readjust to include the cursor.
-        if self.scroll_offset.row > self.cursor_location.row {
-            self.scroll_offset.row = self.cursor_location.row;
+        if self.scroll_offset.row > first_row {
+            self.scroll_offset.row = first_row;
         }
 
-        let view_end_row = self
-            .scroll_offset
-            .row
-            .saturating_add(self.buffer_height().saturating_sub(1) as usize);
-
         // If we scroll down and are now outside the view, readjust to include the cursor.
-        if self.cursor_location.row > view_end_row {
-            let offset = self.cursor_location.row.saturating_sub(view_end_row);
-            self.scroll_offset.row = self.scroll_offset.row.saturating_add(offset);
+        let min_scroll = folds
+            .visible_rows(last_row, false)
+            .nth(self.buffer_height().saturating_sub(1) as usize)
+            .unwrap_or_default();
+        if self.scroll_offset.row < min_scroll {
+            self.scroll_offset.row = min_scroll;
+        }
+
+        // The first row of the view may have been folded away.
+        self.scroll_offset.row = folds
+            .visible_rows(self.scroll_offset.row, false)
+            .next()
+            .unwrap_or_default();
+
+        // Keep the cursor below the sticky header. Finding its rows is expensive, so they are
+        // only looked up if the cursor is close enough to the top for the header to cover it, and
+        // the view scrolls up by the whole overlap at once. The header changes with the first
+        // row, so it is checked again afterwards.
+        while self.scroll_offset.row > 0 {
+            let screen_row = self
+                .get_screen_row(self.cursor_location.row)
+                .unwrap_or_default();
+            if screen_row >= self.get_sticky_limit() + margin_above {
+                break;
+            }
+
+            let overlap = (self.get_sticky_rows().len() + margin_above).saturating_sub(screen_row);
+            if overlap == 0 {
+                break;
+            }
+
+            self.scroll_offset.row = self
+                .buffer
+                .folds
+                .visible_rows(self.scroll_offset.row, false)
+                .nth(overlap)
+                .unwrap_or_default();
         }
 
+        // Columns are scrolled by cells, so wide characters are always shown completely.
+        let cursor_start = self.buffer.get_display_column(self.cursor_location);
+        let cursor_end = cursor_start + self.buffer.get_grapheme_width(self.cursor_location);
+
         // If we scroll left and are now outside the view, readjust to include the cursor.
-        if self.scroll_offset.col > self.cursor_location.col {
-            self.scroll_offset.col = self.cursor_location.col;
+        if self.scroll_offset.col > cursor_start {
+            self.scroll_offset.col = cursor_start;
         }
 
         let view_end_col = self
             .scroll_offset
             .col
-            .saturating_add(self.current_size.width as usize);
+            .saturating_add(self.text_width() as usize);
 
         // If we scroll right and are now outside of the view, readjust to include the cursor.
-        if self.cursor_location.col > view_end_col {
-            let offset = self.cursor_location.col.saturating_sub(view_end_col);
+        if cursor_end > view_end_col {
+            let offset = cursor_end.saturating_sub(view_end_col);
             self.scroll_offset.col = self.scroll_offset.col.saturating_add(offset);
         }
     }
 }
 
-/// Rendes a line of text at the given y position.
-fn render_line(pos_y: u16, line_text: &str) -> TResult<()> {
+/// Rendes a line of text at the given y position, after its line number if there is one and
+/// between markers for text hidden beyond the edges of the view.
+fn render_line(
+    pos_y: u16,
+    gutter: &str,
+    line: &TruncatedLine,
+    theme: &Theme,
+    background: Color,
+) -> TResult<()> {
     terminal::move_cursor_to(Position { x: 0, y: pos_y })?;
+    terminal::set_background_color(background)?;
+    terminal::clear_line()?;
+
+    terminal::set_foreground_color(theme.gutter)?;
+    terminal::print(gutter)?;
+    if line.hidden_before {
+        terminal::print('<')?;
+    }
+    terminal::set_foreground_color(theme.text)?;
+
+    let mut printed = 0;
+    for span in &line.styles {
+        terminal::print(&line.text[printed..span.start])?;
+        terminal::set_foreground_color(theme.syntax_color(span.style))?;
+        terminal::print(&line.text[span.start..span.end])?;
+        terminal::set_foreground_color(theme.text)?;
+        printed = span.end;
+    }
+    terminal::print(&line.text[printed..])?;
+
+    if line.hidden_after {
+        terminal::set_foreground_color(theme.gutter)?;
+        terminal::print('>')?;
+        terminal::set_foreground_color(theme.text)?;
+    }
+
+    Ok(())
+}
+
+/// Renders the command line in place of the status bar and places the cursor in it.
+fn render_prompt(size: Size, prompt: &Prompt) -> TResult<()> {
+    let y = size.height.saturating_sub(1);
+
+    terminal::move_cursor_to(Position { x: 0, y })?;
     terminal::clear_line()?;
-    terminal::print(line_text)
+    terminal::print(prompt.text())?;
+
+    #[allow(clippy::cast_possible_truncation)]
+    terminal::move_cursor_to(Position {
+        x: prompt.cursor_column() as u16,
+        y,
+    })
 }
@@ -0,0 +1,234 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::unicode::Grapheme;
+
+/// Whether lines are reordered, turned off for terminals that reorder right-to-left text
+/// themselves.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// The blocks of right-to-left scripts: Hebrew, Arabic, Syriac, Thaana, N'Ko, Samaritan, Mandaic
+/// and their presentation forms.
+const RIGHT_TO_LEFT_RANGES: &[(u32, u32)] = &[
+    (0x0590, 0x065F),
+    (0x066A, 0x06EF),
+    (0x06FA, 0x08FF),
+    (0xFB1D, 0xFDFF),
+    (0xFE70, 0xFEFC),
+    (0x10800, 0x10FFF),
+    (0x1E800, 0x1EFFF),
+];
+
+/// Arabic-Indic and extended Arabic-Indic digits.
+const ARABIC_DIGIT_RANGES: &[(u32, u32)] = &[(0x0660, 0x0669), (0x06F0, 0x06F9)];
+
+/// Brackets that are drawn mirrored in right-to-left text.
+const MIRRORED_PAIRS: [(char, char); 5] =
+    [('(', ')'), ('[', ']'), ('{', '}'), ('<', '>'), ('«', '»')];
+
+/// The direction a grapheme cluster has on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    LeftToRight,
+    RightToLeft,
+    /// Digits, which are written left to right even within right-to-left text.
+    Number,
+    /// Spaces and punctuation, which take the direction of the text around them.
+    Neutral,
+    /// Tabs, which separate the line into parts that are ordered separately.
+    Separator,
+}
+
+/// The order in which the grapheme clusters of a line are shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reordering {
+    /// The column of the grapheme shown at each position, from left to right.
+    pub order: Vec<usize>,
+    /// The position each column is shown at, the inverse of `order`.
+    pub positions: Vec<usize>,
+    /// Whether each column is part of right-to-left text and is drawn mirrored.
+    pub right_to_left: Vec<bool>,
+}
+
+/// Turns reordering on or off for all lines.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns the order a line is shown in, `None` if it contains no right-to-left text.
+///
+/// This is a simplification of the Unicode bidirectional algorithm for lines of code, which
+/// always start left to right: runs of right-to-left text, including the spaces, punctuation
+/// and numbers between them, are reversed, while the numbers in them keep their order.
+pub fn reorder(text: &str, graphemes: &[Grapheme]) -> Option<Reordering> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let classes: Vec<Class> = graphemes
+        .iter()
+        .map(|grapheme| classify(text[grapheme.start..grapheme.end].chars().next()))
+        .collect();
+    if !classes.contains(&Class::RightToLeft) {
+        return None;
+    }
+
+    // European digits only continue right-to-left text, Arabic digits always do.
+    let mut after_right_to_left = false;
+    let continues_right_to_left: Vec<bool> = classes
+        .iter()
+        .zip(graphemes)
+        .map(|(class, grapheme)| match class {
+            Class::RightToLeft => {
+                after_right_to_left = true;
+                true
+            }
+            Class::LeftToRight | Class::Separator => {
+                after_right_to_left = false;
+                false
+            }
+            Class::Number => {
+                after_right_to_left
+                    || is_arabic_digit(text[grapheme.start..].chars().next().unwrap_or_default())
+            }
+            Class::Neutral => false,
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..graphemes.len()).collect();
+    let mut right_to_left = vec![false; graphemes.len()];
+    let mut col = 0;
+
+    while col < graphemes.len() {
+        if !continues_right_to_left[col] {
+            col += 1;
+            continue;
+        }
+
+        // The run ends at the last grapheme continuing it before left-to-right text.
+        let mut end = col;
+        for next in col..graphemes.len() {
+            match classes[next] {
+                Class::LeftToRight | Class::Separator => break,
+                _ if continues_right_to_left[next] => end = next,
+                _ => (),
+            }
+        }
+
+        // Reverse the run, then put the numbers in it back in order.
+        order[col..=end].reverse();
+        let mut start = col;
+        while start <= end {
+            let mut number_end = start;
+            while number_end <= end && classes[order[number_end]] == Class::Number {
+                number_end += 1;
+            }
+            order[start..number_end].reverse();
+            start = number_end.max(start + 1);
+        }
+
+        for idx in col..=end {
+            right_to_left[idx] = classes[idx] != Class::Number;
+        }
+        col = end + 1;
+    }
+
+    let mut positions = vec![0; graphemes.len()];
+    for (position, col) in order.iter().enumerate() {
+        positions[*col] = position;
+    }
+
+    Some(Reordering {
+        order,
+        positions,
+        right_to_left,
+    })
+}
+
+/// Returns the mirrored form of a bracket.
+pub fn mirror(grapheme: &str) -> Option<char> {
+    let mut chars = grapheme.chars();
+    let ch = chars.next().filter(|_| chars.next().is_none())?;
+
+    MIRRORED_PAIRS.iter().find_map(|&(open, close)| {
+        if ch == open {
+            Some(close)
+        } else if ch == close {
+            Some(open)
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the direction of a grapheme cluster from its first character.
+fn classify(ch: Option<char>) -> Class {
+    match ch {
+        Some('\t') => Class::Separator,
+        Some(ch) if ch.is_ascii_digit() || is_arabic_digit(ch) => Class::Number,
+        Some(ch) if is_in_ranges(ch, RIGHT_TO_LEFT_RANGES) => Class::RightToLeft,
+        Some(ch) if ch.is_alphanumeric() => Class::LeftToRight,
+        _ => Class::Neutral,
+    }
+}
+
+/// Returns whether a character is an Arabic-Indic digit.
+fn is_arabic_digit(ch: char) -> bool {
+    is_in_ranges(ch, ARABIC_DIGIT_RANGES)
+}
+
+/// Returns whether the character lies in one of the inclusive ranges.
+fn is_in_ranges(ch: char, ranges: &[(u32, u32)]) -> bool {
+    let code = u32::from(ch);
+    ranges
+        .iter()
+        .any(|(start, end)| (*start..=*end).contains(&code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unicode::split_graphemes;
+
+    fn reorder_text(text: &str) -> Option<Reordering> {
+        reorder(text, &split_graphemes(text))
+    }
+
+    #[test]
+    fn keeps_left_to_right_lines() {
+        assert_eq!(reorder_text("let x = (1, 2);"), None);
+    }
+
+    #[test]
+    fn reverses_right_to_left_runs() {
+        let reordering = reorder_text("a אב c").unwrap();
+        assert_eq!(reordering.order, [0, 1, 3, 2, 4, 5]);
+        assert_eq!(reordering.positions, [0, 1, 3, 2, 4, 5]);
+        assert_eq!(
+            reordering.right_to_left,
+            [false, false, true, true, false, false]
+        );
+    }
+
+    #[test]
+    fn keeps_numbers_in_order() {
+        let reordering = reorder_text("אב 12").unwrap();
+        assert_eq!(reordering.order, [3, 4, 2, 1, 0]);
+        assert_eq!(reordering.positions, [4, 3, 2, 0, 1]);
+        assert_eq!(reordering.right_to_left, [true, true, true, false, false]);
+    }
+
+    #[test]
+    fn orders_tab_separated_parts_separately() {
+        let reordering = reorder_text("א\tב").unwrap();
+        assert_eq!(reordering.order, [0, 1, 2]);
+        assert_eq!(reordering.right_to_left, [true, false, true]);
+    }
+
+    #[test]
+    fn mirrors_brackets() {
+        assert_eq!(mirror("("), Some(')'));
+        assert_eq!(mirror("»"), Some('«'));
+        assert_eq!(mirror("a"), None);
+        assert_eq!(mirror("()"), None);
+    }
+}
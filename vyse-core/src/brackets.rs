@@ -0,0 +1,93 @@
+use crate::buffer::{Buffer, Location};
+
+/// The pairs of brackets that are matched.
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// The maximum number of lines searched for a matching bracket, so the search stays fast in large
+/// files.
+const MAX_SEARCHED_LINES: usize = 10_000;
+
+/// Returns the location of the bracket matching the one at the given location, skipping the
+/// pairs nested between them. Brackets in strings and comments are counted as well.
+pub fn find_match(buffer: &Buffer, location: Location) -> Option<Location> {
+    let line = buffer.get_line(location.row)?;
+    let byte_index = line.byte_index(location.col);
+    let bracket = line[byte_index..].chars().next()?;
+    let (open, close) = PAIRS
+        .into_iter()
+        .find(|&(open, close)| bracket == open || bracket == close)?;
+
+    let mut depth = 0usize;
+    let mut step = |ch: char, increase: char, decrease: char| {
+        if ch == increase {
+            depth += 1;
+        } else if ch == decrease {
+            depth = depth.saturating_sub(1);
+            return depth == 0;
+        }
+        false
+    };
+
+    if bracket == open {
+        let last_row = location.row.saturating_add(MAX_SEARCHED_LINES);
+        for row in location.row..buffer.get_line_count().min(last_row) {
+            let line = buffer.get_line(row)?;
+            let start = if row == location.row { byte_index } else { 0 };
+
+            for (idx, ch) in line[start..].char_indices() {
+                if step(ch, open, close) {
+                    return Some(Location {
+                        row,
+                        col: line.grapheme_index(start + idx),
+                    });
+                }
+            }
+        }
+    } else {
+        for row in (location.row.saturating_sub(MAX_SEARCHED_LINES)..=location.row).rev() {
+            let line = buffer.get_line(row)?;
+            let end = if row == location.row {
+                byte_index + bracket.len_utf8()
+            } else {
+                line.len()
+            };
+
+            for (idx, ch) in line[..end].char_indices().rev() {
+                if step(ch, close, open) {
+                    return Some(Location {
+                        row,
+                        col: line.grapheme_index(idx),
+                    });
+                }
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(row: usize, col: usize) -> Location {
+        Location { row, col }
+    }
+
+    #[test]
+    fn matches_brackets_in_both_directions() {
+        let buffer = Buffer::from_reader("fn a(b: [u8]) {\n    (c)\n}".as_bytes()).unwrap();
+        assert_eq!(find_match(&buffer, location(0, 4)), Some(location(0, 12)));
+        assert_eq!(find_match(&buffer, location(0, 12)), Some(location(0, 4)));
+        assert_eq!(find_match(&buffer, location(0, 14)), Some(location(2, 0)));
+        assert_eq!(find_match(&buffer, location(2, 0)), Some(location(0, 14)));
+        assert_eq!(find_match(&buffer, location(0, 0)), None);
+    }
+
+    #[test]
+    fn counts_columns_in_graphemes() {
+        let buffer = Buffer::from_reader("é(x)\n(".as_bytes()).unwrap();
+        assert_eq!(find_match(&buffer, location(0, 1)), Some(location(0, 3)));
+        assert_eq!(find_match(&buffer, location(1, 0)), None);
+    }
+}
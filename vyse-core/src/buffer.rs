@@ -0,0 +1,919 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::{
+    cancellation::CancellationToken,
+    change_list::ChangeList,
+    encoding::{Encoding, BYTE_ORDER_MARK},
+    fold::Folds,
+    hex,
+    highlight::Span,
+    history::{Edit, History, Transaction},
+    indent::{self, Indentation},
+    line::{Line, TruncatedLine, VisibleWhitespace},
+    line_ending::LineEnding,
+    lock::FileLock,
+    persistence::{self, ContentHash},
+    rope::Rope,
+    template,
+};
+
+/// The number of bytes read between two progress reports while loading.
+const LOAD_CHUNK_SIZE: usize = 1024 * 1024;
+/// Buffers with a line longer than this many bytes are opened in safe mode.
+const SAFE_MODE_LINE_LENGTH: usize = 100_000;
+/// Buffers with more lines than this are opened in safe mode.
+const SAFE_MODE_LINE_COUNT: usize = 1_000_000;
+
+/// A position in a buffer, the column counts grapheme clusters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub row: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Buffer {
+    /// The lines of the buffer, separated by `\n`.
+    pub text: Rope,
+    /// The file the buffer is saved to, unnamed buffers have none.
+    pub path: Option<PathBuf>,
+    pub modified: bool,
+    /// Disables expensive features for pathological files, e.g. huge minified files.
+    pub safe_mode: bool,
+    pub history: History,
+    /// The locations of recent edits, which the cursor can jump between.
+    pub changes: ChangeList,
+    pub read_only: bool,
+    /// The file was not writable when it was loaded, so the buffer was made read-only.
+    pub write_protected: bool,
+    /// Invalid sequences were replaced when loading, so saving would not restore the file.
+    pub lossy: bool,
+    /// Marks the file as being edited, so other instances can warn about concurrent edits.
+    pub lock: Option<FileLock>,
+    /// The indentation detected when loading, used for Tab.
+    pub indentation: Indentation,
+    pub line_ending: LineEnding,
+    /// The file mixes CRLF and LF line endings, which are all written as `line_ending`.
+    pub mixed_line_endings: bool,
+    /// The file ends with a line ending, which is not part of the text.
+    pub final_newline: bool,
+    pub encoding: Encoding,
+    /// The file starts with a byte order mark, which is not part of the text.
+    pub byte_order_mark: bool,
+    /// Only the start of the file is loaded, the complete buffer replaces it once loading finishes.
+    pub partial: bool,
+    /// The contents were dropped to free memory, they are loaded from the file again when shown.
+    pub unloaded: bool,
+    /// The file does not exist yet and is created when saving.
+    pub new_file: bool,
+    /// The contents of a file with NUL bytes, which is shown as a hex dump instead of text.
+    pub binary: Option<Vec<u8>>,
+    /// The ranges of lines collapsed into their first line.
+    pub folds: Folds,
+}
+
+/// The state of a buffer's edits that [`Buffer::rollback`] returns to.
+#[derive(Debug)]
+pub struct Checkpoint {
+    undo_len: usize,
+    redo_stack: Vec<Transaction>,
+    changes: ChangeList,
+    final_newline: bool,
+}
+
+impl Buffer {
+    /// Loads a buffer from a path, reporting the number of bytes read and the file size after every chunk.
+    /// Files larger than a single chunk also report the first chunk, so it can be shown early.
+    ///
+    /// Fails with [`io::ErrorKind::Interrupted`] if the token gets cancelled while reading.
+    pub fn from_path<P: Into<PathBuf>>(
+        path: P,
+        token: &CancellationToken,
+        mut on_progress: impl FnMut(u64, u64),
+        on_first_chunk: impl FnOnce(&[u8]),
+    ) -> Result<Self, io::Error> {
+        let path = path.into();
+        let mut file = File::open(&path)?;
+        let total = file.metadata()?.len();
+
+        let mut bytes = Vec::with_capacity(usize::try_from(total).unwrap_or_default());
+        let mut chunk = vec![0; LOAD_CHUNK_SIZE];
+        let mut on_first_chunk = Some(on_first_chunk);
+
+        loop {
+            if token.is_cancelled() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "loading cancelled",
+                ));
+            }
+
+            let read = match file.read(&mut chunk) {
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            if read == 0 {
+                break;
+            }
+
+            bytes.extend_from_slice(&chunk[..read]);
+            on_progress(bytes.len() as u64, total);
+
+            if (bytes.len() as u64) < total {
+                if let Some(on_first_chunk) = on_first_chunk.take() {
+                    on_first_chunk(&bytes);
+                }
+            }
+        }
+
+        // Opening the file for writing without truncating it checks the permissions like saving.
+        let writable = OpenOptions::new().write(true).open(&path).is_ok();
+
+        let mut buffer = Self::from_bytes(Some(path), bytes);
+        buffer.write_protected = !writable;
+        buffer.read_only |= !writable;
+
+        Ok(buffer)
+    }
+
+    /// Reads a buffer without a file name until the end of the reader, e.g. from stdin.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        Ok(Self::from_bytes(None, bytes))
+    }
+
+    /// Creates a buffer from the contents of a file, detecting its encoding and format.
+    fn from_bytes(path: Option<PathBuf>, bytes: Vec<u8>) -> Self {
+        // UTF-16 text contains NUL bytes as well, in the high bytes of ASCII characters.
+        if bytes.contains(&0) && Encoding::detect_utf16(&bytes).is_none() {
+            return Self::binary(path, bytes);
+        }
+
+        // Files in an unknown encoding, e.g. logs mixed with binary data, are opened with invalid
+        // sequences replaced, so they can at least be inspected.
+        let (mut content, encoding, lossy) = match Encoding::decode(bytes) {
+            Ok((content, encoding)) => (content, encoding, false),
+            Err(bytes) => (
+                String::from_utf8_lossy(&bytes).into_owned(),
+                Encoding::Utf8,
+                true,
+            ),
+        };
+        let safe_mode = is_pathological(&content);
+
+        // Restore the undo history of the last session, unless the file was changed since.
+        let history = path
+            .as_deref()
+            .and_then(|path| persistence::load_history(path, ContentHash::of(content.as_bytes())))
+            .unwrap_or_default();
+
+        // Split lines like `str::lines`, without a trailing newline and carriage returns. The
+        // line ending is restored when saving.
+        let byte_order_mark = content.starts_with(BYTE_ORDER_MARK);
+        if byte_order_mark {
+            content.drain(..BYTE_ORDER_MARK.len());
+        }
+
+        let line_ending = LineEnding::detect(&content);
+        let mixed_line_endings = LineEnding::is_mixed(&content);
+        let final_newline = content.ends_with('\n');
+        if content.contains('\r') {
+            content = content.lines().collect::<Vec<_>>().join("\n");
+        } else if content.ends_with('\n') {
+            content.pop();
+        }
+
+        let indentation = Indentation::detect(content.lines()).unwrap_or_default();
+
+        Self {
+            text: Rope::from(content),
+            path,
+            modified: false,
+            safe_mode,
+            history,
+            changes: ChangeList::default(),
+            read_only: lossy,
+            write_protected: false,
+            lossy,
+            lock: None,
+            indentation,
+            line_ending,
+            mixed_line_endings,
+            final_newline,
+            encoding,
+            byte_order_mark,
+            partial: false,
+            unloaded: false,
+            new_file: false,
+            binary: None,
+            folds: Folds::default(),
+        }
+    }
+
+    /// Creates a read-only buffer showing the bytes of a binary file.
+    fn binary(path: Option<PathBuf>, bytes: Vec<u8>) -> Self {
+        Self {
+            path,
+            read_only: true,
+            binary: Some(bytes),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a read-only buffer showing the complete lines at the start of a file that is still
+    /// being loaded.
+    pub fn partial(path: PathBuf, bytes: &[u8]) -> Self {
+        let utf16 = Encoding::detect_utf16(bytes);
+        if bytes.contains(&0) && utf16.is_none() {
+            return Self {
+                partial: true,
+                ..Self::binary(Some(path), bytes.to_vec())
+            };
+        }
+
+        let mut content = match utf16 {
+            Some(encoding) => encoding.decode_utf16_lossy(bytes),
+            None => String::from_utf8_lossy(bytes).into_owned(),
+        };
+        content.truncate(content.rfind('\n').unwrap_or(content.len()));
+        let content = content
+            .trim_start_matches(BYTE_ORDER_MARK)
+            .replace('\r', "");
+
+        Self {
+            text: Rope::from(content),
+            path: Some(path),
+            read_only: true,
+            partial: true,
+            ..Self::default()
+        }
+    }
+
+    /// Creates an empty buffer for a file that does not exist yet, prefilled from the template
+    /// for its file type as an edit that can be undone.
+    pub fn new_file(path: PathBuf) -> Self {
+        let template = template::load(&path);
+        let mut buffer = Self {
+            path: Some(path),
+            final_newline: true,
+            new_file: true,
+            ..Self::default()
+        };
+
+        if let Some(template) = template {
+            buffer.indentation = Indentation::detect(template.lines()).unwrap_or_default();
+            buffer.insert_str(Location::default(), &template);
+        }
+
+        buffer
+    }
+
+    /// Writes the lines of the buffer back to its path and persists the undo history.
+    pub fn save(&mut self) -> Result<(), io::Error> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+
+        // The text of a hex dump is empty, writing it would truncate the file.
+        if self.binary.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "binary files cannot be saved",
+            ));
+        }
+
+        // Only the start of the file is loaded, writing it would truncate the file.
+        if self.partial {
+            return Err(io::Error::new(
+                io::ErrorKind::ResourceBusy,
+                "the file is still loading",
+            ));
+        }
+
+        if self.unloaded {
+            return Err(io::Error::new(
+                io::ErrorKind::ResourceBusy,
+                "the file is not loaded",
+            ));
+        }
+
+        // Check before writing anything, so no temporary file is left behind.
+        if !self
+            .text
+            .chunks()
+            .all(|chunk| self.encoding.can_encode(chunk))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("text cannot be encoded as {}", self.encoding),
+            ));
+        }
+
+        // New files may be opened in directories that do not exist yet either.
+        if self.new_file {
+            if let Some(parent) = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        // The contents are written to a temporary file that replaces the file once it is complete,
+        // so a failed write leaves the file as it was. Symlinks are followed to keep them intact.
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        let temp_path = get_temp_path(&target);
+        let content_hash = match File::create(&temp_path) {
+            Ok(file) => {
+                let written = self.write_to(file).and_then(|content_hash| {
+                    if let Ok(metadata) = fs::metadata(&target) {
+                        fs::set_permissions(&temp_path, metadata.permissions())?;
+                    }
+                    fs::rename(&temp_path, &target)?;
+
+                    Ok(content_hash)
+                });
+
+                if written.is_err() {
+                    let _ = fs::remove_file(&temp_path);
+                }
+                written?
+            }
+
+            // Files in directories that cannot be written to are overwritten in place instead.
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                self.write_to(File::create(&target)?)?
+            }
+            Err(err) => return Err(err),
+        };
+
+        self.modified = false;
+        self.new_file = false;
+        self.mixed_line_endings = false;
+
+        // Losing the undo history is not worth failing the save for.
+        let _ = persistence::save_history(path, content_hash, &self.history);
+
+        Ok(())
+    }
+
+    /// Writes the contents of the buffer to a file as they are saved, returns their hash.
+    fn write_to(&self, file: File) -> io::Result<ContentHash> {
+        let mut writer = BufWriter::new(file);
+        let mut content_hash = ContentHash::default();
+
+        for chunk in self.get_file_chunks() {
+            let chunk = self.line_ending.apply(chunk);
+
+            writer.write_all(&self.encoding.encode(&chunk))?;
+            content_hash.update(chunk.as_bytes());
+        }
+
+        writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?
+            .sync_all()?;
+
+        Ok(content_hash)
+    }
+
+    /// Returns an empty buffer standing in for this one to free the memory of its contents, which
+    /// are loaded from the file again when it is shown. The lock stays with the stand-in and the
+    /// undo history is saved, so it is restored on loading.
+    ///
+    /// Returns `None` if the contents cannot be loaded again as they are, e.g. if they are modified.
+    pub fn unload(&mut self) -> Option<Self> {
+        if self.modified || self.new_file || self.partial || self.unloaded {
+            return None;
+        }
+
+        let path = self.path.clone()?;
+        if self.binary.is_none() {
+            let _ = persistence::save_history(&path, self.get_content_hash(), &self.history);
+        }
+
+        Some(Self {
+            path: Some(path),
+            read_only: true,
+            unloaded: true,
+            lock: self.lock.take(),
+            ..Self::default()
+        })
+    }
+
+    /// Returns the contents of the buffer as they would be written to its file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if let Some(bytes) = &self.binary {
+            return bytes.clone();
+        }
+
+        let mut bytes = Vec::with_capacity(self.text.len());
+        for chunk in self.get_file_chunks() {
+            bytes.extend_from_slice(&self.encoding.encode(&self.line_ending.apply(chunk)));
+        }
+
+        bytes
+    }
+
+    /// Returns the text of the buffer in chunks together with the byte order mark and final line
+    /// ending of its file.
+    fn get_file_chunks(&self) -> impl Iterator<Item = &str> {
+        self.byte_order_mark
+            .then_some(BYTE_ORDER_MARK)
+            .into_iter()
+            .chain(self.text.chunks())
+            .chain(self.final_newline.then_some("\n"))
+    }
+
+    /// Returns the hash of the contents as they would be written to the file, which identifies the
+    /// undo history saved for them.
+    fn get_content_hash(&self) -> ContentHash {
+        let mut content_hash = ContentHash::default();
+        for chunk in self.get_file_chunks() {
+            content_hash.update(self.line_ending.apply(chunk).as_bytes());
+        }
+
+        content_hash
+    }
+
+    /// Estimates the number of bytes allocated for the text of the buffer.
+    pub fn memory_usage(&self) -> usize {
+        self.text.memory_usage() + self.binary.as_ref().map_or(0, Vec::capacity)
+    }
+
+    /// Returns the line at the given index without its newline.
+    pub fn get_line(&self, index: usize) -> Option<Line<'_>> {
+        self.text.line(index).map(Line::new)
+    }
+
+    /// Returns the location of the first occurrence of the text, which may not span lines.
+    pub fn find(&self, text: &str) -> Option<Location> {
+        self.lines().enumerate().find_map(|(row, line)| {
+            let byte_index = line.find(text)?;
+            Some(Location {
+                row,
+                col: line.grapheme_index(byte_index),
+            })
+        })
+    }
+
+    /// Returns an iterator over all lines of the buffer.
+    pub fn lines(&self) -> impl Iterator<Item = Line<'_>> {
+        (0..self.get_line_count()).filter_map(|index| self.get_line(index))
+    }
+
+    /// Returns the number of lines in the buffer, or of rows in the hex dump of a binary file.
+    pub fn get_line_count(&self) -> usize {
+        match &self.binary {
+            Some(bytes) => bytes.len().div_ceil(hex::BYTES_PER_ROW).max(1),
+            None => self.text.line_count(),
+        }
+    }
+
+    /// Calculates the line length in grapheme clusters for the line at a given index.
+    ///
+    /// In binary files the cursor stays on the bytes of a row, so the length is the column of
+    /// its last byte.
+    pub fn get_line_length(&self, index: usize) -> usize {
+        match &self.binary {
+            Some(bytes) => bytes
+                .len()
+                .saturating_sub(index * hex::BYTES_PER_ROW)
+                .min(hex::BYTES_PER_ROW)
+                .saturating_sub(1),
+            None => self.get_line(index).map_or(0, |line| line.grapheme_count()),
+        }
+    }
+
+    /// Computes the truncated line, considering the first visible cell and the window width, and
+    /// where the highlighted spans of the line are shown.
+    pub fn get_truncated_line(
+        &self,
+        row: usize,
+        start: usize,
+        width: usize,
+        spans: &[Span],
+        whitespace: VisibleWhitespace,
+    ) -> Option<TruncatedLine<'_>> {
+        if let Some(bytes) = &self.binary {
+            let offset = row * hex::BYTES_PER_ROW;
+            let row_bytes = bytes.get(offset..(offset + hex::BYTES_PER_ROW).min(bytes.len()))?;
+            let line = Line::new(hex::format_row(offset, row_bytes));
+
+            return Some(line.into_truncated(start, width, &[], VisibleWhitespace::default()));
+        }
+
+        let line = self.get_line(row)?;
+        Some(line.into_truncated(start, width, spans, whitespace))
+    }
+
+    /// Returns the cell at which the grapheme at the given location is shown on screen.
+    pub fn get_display_column(&self, location: Location) -> usize {
+        if self.binary.is_some() {
+            return hex::cursor_column(location.col);
+        }
+
+        self.get_line(location.row)
+            .map_or(0, |line| line.display_column(location.col))
+    }
+
+    /// Returns the byte index of the given location within its line, or within the whole file for
+    /// binary files.
+    pub fn get_byte_column(&self, location: Location) -> usize {
+        if self.binary.is_some() {
+            return location.row * hex::BYTES_PER_ROW + location.col;
+        }
+
+        self.get_line(location.row)
+            .map_or(0, |line| line.byte_index(location.col))
+    }
+
+    /// Returns the number of cells the grapheme at the given location takes up, one past its end.
+    pub fn get_grapheme_width(&self, location: Location) -> usize {
+        if self.binary.is_some() {
+            return 2;
+        }
+
+        self.get_line(location.row)
+            .map_or(1, |line| line.grapheme_width(location.col).max(1))
+    }
+
+    /// Returns the column of the grapheme shown at the given cell of a line.
+    pub fn get_column_at_width(&self, row: usize, width: usize) -> usize {
+        if self.binary.is_some() {
+            return hex::column_at(width);
+        }
+
+        self.get_line(row)
+            .map_or(0, |line| line.column_at_width(width))
+    }
+
+    /// Inserts a character at the given location, returns the location after it.
+    pub fn insert_char(&mut self, location: Location, ch: char) -> Location {
+        self.insert_str(location, ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Inserts a string at the given location, returns the location after it.
+    pub fn insert_str(&mut self, location: Location, text: &str) -> Location {
+        self.apply(Edit::Insert {
+            location,
+            text: text.to_string(),
+        })
+    }
+
+    /// Splits the line at the given location, moving the rest of the line onto a new line below
+    /// that is indented like the current one.
+    pub fn split_line(&mut self, location: Location) -> Location {
+        let indent = self.get_line(location.row).map_or(String::new(), |line| {
+            let before = &line[..line.byte_index(location.col)];
+            indent::leading_whitespace(before).to_string()
+        });
+
+        self.insert_str(location, &format!("\n{indent}"))
+    }
+
+    /// Replaces the contents of a line, which must not contain newlines.
+    pub fn replace_line(&mut self, row: usize, text: &str) {
+        let Some(line) = self.get_line(row) else {
+            return;
+        };
+        let old_text = line.to_string();
+        let location = Location { row, col: 0 };
+
+        self.apply(Edit::Delete {
+            location,
+            text: old_text,
+        });
+        self.insert_str(location, text);
+    }
+
+    /// Deletes the empty lines at the end of the buffer, returns whether there were any.
+    pub fn trim_final_newlines(&mut self) -> bool {
+        let last_row = self.get_last_line_index();
+        let row = (0..=last_row)
+            .rev()
+            .find(|row| self.get_line_length(*row) > 0)
+            .unwrap_or(0);
+
+        if row == last_row {
+            return false;
+        }
+
+        let start = Location {
+            row,
+            col: self.get_line_length(row),
+        };
+        self.delete(
+            start,
+            Location {
+                row: last_row,
+                col: 0,
+            },
+        );
+
+        true
+    }
+
+    /// Returns the text between two locations.
+    pub fn get_text(&self, start: Location, end: Location) -> String {
+        self.text
+            .slice(
+                self.get_offset(self.clamp_location(start)),
+                self.get_offset(self.clamp_location(end)),
+            )
+            .into_owned()
+    }
+
+    /// Deletes the text between two locations, returns the location where it started.
+    pub fn delete(&mut self, start: Location, end: Location) -> Location {
+        let start = self.clamp_location(start);
+        let text = self.get_text(start, end);
+
+        if !text.is_empty() {
+            self.apply(Edit::Delete {
+                location: start,
+                text,
+            });
+        }
+
+        start
+    }
+
+    /// Applies an edit and records it in the history, returns the location after the edit.
+    fn apply(&mut self, edit: Edit) -> Location {
+        let location = self.apply_without_history(&edit);
+        self.history.record(edit);
+        self.changes.record(location);
+
+        location
+    }
+
+    /// Applies an edit without recording it, returns the location after the edit.
+    fn apply_without_history(&mut self, edit: &Edit) -> Location {
+        self.modified = true;
+
+        match edit {
+            Edit::Insert { location, text } => self.insert_text(*location, text),
+            Edit::Delete { location, text } => {
+                self.delete_text(*location, text);
+                *location
+            }
+        }
+    }
+
+    /// Returns a checkpoint to roll back the following edits to, e.g. those made for saving.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.history.close_group();
+
+        Checkpoint {
+            undo_len: self.history.undo_stack.len(),
+            redo_stack: self.history.redo_stack.clone(),
+            changes: self.changes.clone(),
+            final_newline: self.final_newline,
+        }
+    }
+
+    /// Reverts the edits made since the checkpoint and removes them from the history, as if they
+    /// had never been made. Whether the file ends with a newline is restored as well.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        while self.history.undo_stack.len() > checkpoint.undo_len {
+            let Some(transaction) = self.history.undo_stack.pop() else {
+                break;
+            };
+
+            for edit in transaction.edits.iter().rev() {
+                self.apply_without_history(&edit.inverse());
+            }
+        }
+
+        self.history.redo_stack = checkpoint.redo_stack;
+        self.history.close_group();
+        self.changes = checkpoint.changes;
+        self.final_newline = checkpoint.final_newline;
+    }
+
+    /// Reverts the most recent group of edits, returns the location where the first edit happened.
+    pub fn undo(&mut self) -> Option<Location> {
+        let transaction = self.history.undo()?;
+        let mut location = None;
+
+        for edit in transaction.edits.iter().rev() {
+            location = Some(self.apply_without_history(&edit.inverse()));
+        }
+
+        location
+    }
+
+    /// Applies the most recently undone group of edits again, returns the location after the last edit.
+    pub fn redo(&mut self) -> Option<Location> {
+        let transaction = self.history.redo()?;
+        let mut location = None;
+
+        for edit in &transaction.edits {
+            location = Some(self.apply_without_history(edit));
+        }
+
+        location
+    }
+
+    /// Inserts text which may span multiple lines, returns the location after the inserted text.
+    fn insert_text(&mut self, location: Location, text: &str) -> Location {
+        let location = self.clamp_location(location);
+        let offset = self.get_offset(location);
+        self.text.insert(offset, text);
+
+        // Inserted characters may combine with their neighbours, so the column after the text
+        // is computed from the resulting line instead of the inserted text.
+        let row = location.row + text.matches('\n').count();
+        let line_start = self.text.line_start(row).unwrap_or_default();
+        let col = self.get_line(row).map_or(0, |line| {
+            line.grapheme_index(offset + text.len() - line_start)
+        });
+
+        Location { row, col }
+    }
+
+    /// Deletes the given text, which may span multiple lines, starting at the location.
+    fn delete_text(&mut self, location: Location, text: &str) {
+        let offset = self.get_offset(self.clamp_location(location));
+        self.text.remove(offset, offset + text.len());
+    }
+
+    /// Clamps a location to the last line and the end of its line.
+    fn clamp_location(&self, location: Location) -> Location {
+        let row = location.row.min(self.get_last_line_index());
+
+        Location {
+            row,
+            col: location.col.min(self.get_line_length(row)),
+        }
+    }
+
+    /// Returns the byte offset of a location within the text.
+    fn get_offset(&self, location: Location) -> usize {
+        let line_start = self
+            .text
+            .line_start(location.row)
+            .unwrap_or(self.text.len());
+        let byte_index = self
+            .get_line(location.row)
+            .map_or(0, |line| line.byte_index(location.col));
+
+        line_start + byte_index
+    }
+
+    /// Returns the index of the last line.
+    pub fn get_last_line_index(&self) -> usize {
+        self.get_line_count().saturating_sub(1)
+    }
+}
+
+/// Formats a number of bytes in a human readable way, e.g. `12.3 KiB`.
+pub fn format_byte_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut unit = 0;
+    let mut tenths = bytes * 10;
+    while tenths >= 10 * 1024 && unit < UNITS.len() - 1 {
+        tenths /= 1024;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{}.{} {}", tenths / 10, tenths % 10, UNITS[unit])
+    }
+}
+
+/// Returns whether the lines are too long or too many to enable all features.
+fn is_pathological(content: &str) -> bool {
+    let mut line_count = 0;
+
+    for line in content.split('\n') {
+        line_count += 1;
+
+        if line_count > SAFE_MODE_LINE_COUNT || line.len() > SAFE_MODE_LINE_LENGTH {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Returns the temporary file the contents of a file are written to before replacing it.
+fn get_temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.vyse-{}", process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn text(buffer: &Buffer) -> String {
+        buffer.text.chunks().collect()
+    }
+
+    #[test]
+    fn rollback_forgets_edits_since_checkpoint() {
+        let mut buffer = Buffer::from_reader("one\ntwo\n".as_bytes()).unwrap();
+        buffer.insert_str(Location { row: 1, col: 3 }, "!");
+        buffer.undo();
+
+        let checkpoint = buffer.checkpoint();
+        buffer.replace_line(0, "ONE");
+        assert_eq!(text(&buffer), "ONE\ntwo");
+        assert!(buffer.history.redo_stack.is_empty());
+
+        buffer.rollback(checkpoint);
+        assert_eq!(text(&buffer), "one\ntwo");
+        assert!(buffer.history.undo_stack.is_empty());
+        assert_eq!(buffer.history.redo_stack.len(), 1);
+
+        buffer.redo();
+        assert_eq!(text(&buffer), "one\ntwo!");
+    }
+
+    #[test]
+    fn keeps_buffers_that_cannot_be_reloaded() {
+        let mut unnamed = Buffer::default();
+        assert!(unnamed.unload().is_none());
+
+        let mut modified = Buffer {
+            path: Some(PathBuf::from("modified.txt")),
+            modified: true,
+            ..Buffer::default()
+        };
+        assert!(modified.unload().is_none());
+
+        let mut unloaded = Buffer {
+            path: Some(PathBuf::from("unloaded.txt")),
+            unloaded: true,
+            ..Buffer::default()
+        };
+        assert!(unloaded.unload().is_none());
+    }
+
+    #[test]
+    fn refuses_to_save_unloaded_buffers() {
+        let path = env::temp_dir().join(format!("vyse-unloaded-{}", process::id()));
+        let mut buffer = Buffer {
+            path: Some(path.clone()),
+            unloaded: true,
+            ..Buffer::default()
+        };
+
+        let err = buffer.save().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ResourceBusy);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn saving_replaces_file_keeping_permissions() {
+        let dir = env::temp_dir().join(format!("vyse-save-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("script.sh");
+        fs::write(&path, "old\n").unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+        }
+
+        let mut buffer = Buffer::from_reader("new\n".as_bytes()).unwrap();
+        buffer.path = Some(path.clone());
+        buffer.save().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o750);
+        }
+
+        buffer.encoding = Encoding::Latin1;
+        buffer.insert_str(Location::default(), "€");
+        assert!(buffer.save().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
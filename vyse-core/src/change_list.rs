@@ -0,0 +1,97 @@
+use crate::buffer::Location;
+
+/// The number of change locations kept per buffer.
+const MAX_CHANGES: usize = 100;
+
+/// The locations of recent edits in a buffer, oldest first.
+#[derive(Debug, Default, Clone)]
+pub struct ChangeList {
+    locations: Vec<Location>,
+    /// The change the cursor was last moved to, past the end if it has not been moved yet.
+    position: usize,
+}
+
+impl ChangeList {
+    /// Records the location of an edit, merging it with the previous change on the same line.
+    pub fn record(&mut self, location: Location) {
+        match self.locations.last_mut() {
+            Some(last) if last.row == location.row => *last = location,
+            _ => self.locations.push(location),
+        }
+
+        if self.locations.len() > MAX_CHANGES {
+            self.locations.remove(0);
+        }
+
+        self.position = self.locations.len();
+    }
+
+    /// Moves to the next older change, returns `None` at the oldest one.
+    pub fn previous(&mut self) -> Option<Location> {
+        self.position = self.position.checked_sub(1)?;
+        self.locations.get(self.position).copied()
+    }
+
+    /// Moves to the next newer change, returns `None` at the newest one.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Location> {
+        let location = self.locations.get(self.position + 1).copied()?;
+        self.position += 1;
+
+        Some(location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use super::*;
+
+    fn location(row: usize, col: usize) -> Location {
+        Location { row, col }
+    }
+
+    #[test]
+    fn merges_changes_on_one_line() {
+        let mut changes = ChangeList::default();
+        changes.record(location(1, 0));
+        changes.record(location(1, 4));
+        changes.record(location(3, 2));
+
+        assert_eq!(changes.previous(), Some(location(3, 2)));
+        assert_eq!(changes.previous(), Some(location(1, 4)));
+        assert_eq!(changes.previous(), None);
+    }
+
+    #[test]
+    fn moves_between_changes() {
+        let mut changes = ChangeList::default();
+        assert_eq!(changes.previous(), None);
+
+        for row in 0..3 {
+            changes.record(location(row, 0));
+        }
+        assert_eq!(changes.next(), None);
+        changes.previous();
+        changes.previous();
+        assert_eq!(changes.next(), Some(location(2, 0)));
+        assert_eq!(changes.next(), None);
+
+        // Recording a change moves past the newest one again.
+        changes.previous();
+        changes.record(location(5, 0));
+        assert_eq!(changes.previous(), Some(location(5, 0)));
+    }
+
+    #[test]
+    fn drops_oldest_changes() {
+        let mut changes = ChangeList::default();
+        for row in 0..=MAX_CHANGES {
+            changes.record(location(row, 0));
+        }
+
+        let oldest = iter::from_fn(|| changes.previous()).last();
+        assert_eq!(oldest, Some(location(1, 0)));
+    }
+}
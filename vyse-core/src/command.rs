@@ -0,0 +1,204 @@
+use crate::{checksum::Algorithm, hex, transform::Transform};
+
+/// The length of random strings if none is given.
+const DEFAULT_RANDOM_LENGTH: usize = 16;
+
+/// A command entered on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Turns an option on (`set name`) or off (`set noname`).
+    Set { option: String, enabled: bool },
+    /// Sets an option to a value (`set name=value`).
+    SetValue { option: String, value: String },
+    /// Lists the open buffers together with their memory usage.
+    Buffers,
+    /// Frees the memory of unmodified background buffers, they are loaded again when shown.
+    Unload,
+    /// Deletes the file of the current buffer, `delete!` skips the trash.
+    Delete { permanently: bool },
+    /// Changes the working directory of the view.
+    ChangeDirectory(String),
+    /// Shows the working directory of the view.
+    PrintDirectory,
+    /// Moves the cursor to a byte offset in a binary buffer.
+    GotoOffset(usize),
+    /// Edits all occurrences of the word under the cursor at once.
+    Rename,
+    /// Toggles the panel listing the states of the undo history.
+    UndoList,
+    /// Inserts the result of an arithmetic expression at the cursor (`=1 + 2`).
+    Evaluate(String),
+    /// Inserts the current date, optionally in the given format, e.g. `date DD.MM.YYYY`.
+    Date(Option<String>),
+    /// Inserts the current time, optionally in the given format, e.g. `time hh:mm`.
+    Time(Option<String>),
+    /// Inserts a random UUID.
+    Uuid,
+    /// Inserts a random string of letters and digits with the given length.
+    Random(usize),
+    /// Encodes the selection or the current line, e.g. `encode base64`.
+    Encode(Transform),
+    /// Decodes the selection or the current line, e.g. `decode url`.
+    Decode(Transform),
+    /// Shows and copies the checksum of the selection or the file, or of the current line with
+    /// `md5 line`.
+    Checksum { algorithm: Algorithm, line: bool },
+    /// Bookmarks the current line with an optional note, e.g. `mark fix the parser`.
+    Mark(String),
+    /// Removes the bookmark on the current line.
+    Unmark,
+    /// Opens the list of the bookmarks of the project.
+    Marks,
+    /// Toggles the panel listing the TODO, FIXME and HACK comments of the project.
+    Todos,
+    /// Switches to a theme from the config directory or back to the `default` one.
+    Theme(String),
+    /// Opens the list of copied and deleted texts to paste one of them.
+    Clipboard,
+    /// Inserts a snippet from the config directory, e.g. `snippet fn`.
+    Snippet(String),
+    /// Checks or unchecks the task of the Markdown list item on the current line.
+    ToggleCheckbox,
+    /// Demotes the Markdown heading on the current line by the given number of levels (`demote`),
+    /// or promotes it for a negative number (`promote`).
+    ChangeHeadingLevel(isize),
+    /// Opens the file a Markdown link on the current line points to.
+    FollowLink,
+    /// Lines up the columns of the Markdown table around the cursor.
+    FormatTable,
+    /// Folds the block the cursor is in, i.e. the lines indented more than the line opening it.
+    Fold,
+    /// Folds the blocks opened by lines that are not indented, like functions and types.
+    FoldTopLevel,
+    /// Folds the comments spanning several lines.
+    FoldComments,
+    /// Opens the fold around the cursor.
+    Unfold,
+    /// Opens every fold.
+    UnfoldAll,
+}
+
+impl Command {
+    /// Parses a command from the text entered on the command line.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        if let Some(expression) = input.trim_start().strip_prefix('=') {
+            return Ok(Self::Evaluate(expression.trim().to_string()));
+        }
+
+        let mut words = input.split_whitespace();
+        let name = words.next().ok_or_else(|| "no command given".to_string())?;
+        let arguments: Vec<&str> = words.collect();
+
+        match (name, arguments.as_slice()) {
+            ("set", [assignment]) if assignment.contains('=') => {
+                let (option, value) = assignment.split_once('=').unwrap_or_default();
+                Ok(Self::SetValue {
+                    option: option.to_string(),
+                    value: value.to_string(),
+                })
+            }
+            ("set", [option]) => Ok(match option.strip_prefix("no") {
+                Some(option) => Self::Set {
+                    option: option.to_string(),
+                    enabled: false,
+                },
+                None => Self::Set {
+                    option: (*option).to_string(),
+                    enabled: true,
+                },
+            }),
+            ("set", _) => Err("usage: set [no]<option> or set <option>=<value>".to_string()),
+            ("buffers", []) => Ok(Self::Buffers),
+            ("unload", []) => Ok(Self::Unload),
+            ("delete", []) => Ok(Self::Delete { permanently: false }),
+            ("delete!", []) => Ok(Self::Delete { permanently: true }),
+            ("cd", [dir]) => Ok(Self::ChangeDirectory((*dir).to_string())),
+            ("cd", _) => Err("usage: cd <dir>".to_string()),
+            ("pwd", []) => Ok(Self::PrintDirectory),
+            ("goto", [offset]) => hex::parse_offset(offset)
+                .map(Self::GotoOffset)
+                .ok_or_else(|| format!("invalid offset: {offset}")),
+            ("goto", _) => Err("usage: goto <offset>".to_string()),
+            ("rename", []) => Ok(Self::Rename),
+            ("undolist", []) => Ok(Self::UndoList),
+            ("date", []) => Ok(Self::Date(None)),
+            ("date", format) => Ok(Self::Date(Some(format.join(" ")))),
+            ("time", []) => Ok(Self::Time(None)),
+            ("time", format) => Ok(Self::Time(Some(format.join(" ")))),
+            ("uuid", []) => Ok(Self::Uuid),
+            ("random", []) => Ok(Self::Random(DEFAULT_RANDOM_LENGTH)),
+            ("random", [length]) => length
+                .parse()
+                .map(Self::Random)
+                .map_err(|_| format!("invalid length: {length}")),
+            ("random", _) => Err("usage: random [length]".to_string()),
+            ("encode", [name]) => parse_transform(name).map(Self::Encode),
+            ("decode", [name]) => parse_transform(name).map(Self::Decode),
+            ("rot13", []) => Ok(Self::Encode(Transform::Rot13)),
+            ("md5" | "sha256", []) => Ok(Self::Checksum {
+                algorithm: Algorithm::parse(name).unwrap_or(Algorithm::Md5),
+                line: false,
+            }),
+            ("md5" | "sha256", ["line"]) => Ok(Self::Checksum {
+                algorithm: Algorithm::parse(name).unwrap_or(Algorithm::Md5),
+                line: true,
+            }),
+            ("md5" | "sha256", _) => Err(format!("usage: {name} [line]")),
+            ("mark", note) => Ok(Self::Mark(note.join(" "))),
+            ("unmark", []) => Ok(Self::Unmark),
+            ("marks", []) => Ok(Self::Marks),
+            ("clipboard", []) => Ok(Self::Clipboard),
+            ("todos", []) => Ok(Self::Todos),
+            ("theme", [name]) => Ok(Self::Theme((*name).to_string())),
+            ("theme", _) => Err("usage: theme <name>".to_string()),
+            ("check", []) => Ok(Self::ToggleCheckbox),
+            ("promote", []) => Ok(Self::ChangeHeadingLevel(-1)),
+            ("demote", []) => Ok(Self::ChangeHeadingLevel(1)),
+            ("follow", []) => Ok(Self::FollowLink),
+            ("table", []) => Ok(Self::FormatTable),
+            ("fold", []) => Ok(Self::Fold),
+            ("foldall", []) => Ok(Self::FoldTopLevel),
+            ("foldcomments", []) => Ok(Self::FoldComments),
+            ("unfold", []) => Ok(Self::Unfold),
+            ("unfoldall", []) => Ok(Self::UnfoldAll),
+            ("snippet", [name]) => Ok(Self::Snippet((*name).to_string())),
+            ("snippet", _) => Err("usage: snippet <name>".to_string()),
+            ("encode" | "decode", _) => Err(format!("usage: {name} <base64|url|json|rot13>")),
+            (name, _) => Err(format!("unknown command: {name}")),
+        }
+    }
+}
+
+/// Replaces `%` with the path of the current file and `#` with the path of the alternate file,
+/// `\%` and `\#` insert the characters themselves.
+pub fn expand_placeholders(
+    input: &str,
+    current: Option<&str>,
+    alternate: Option<&str>,
+) -> Result<String, String> {
+    let mut expanded = String::with_capacity(input.len());
+    let mut chars = input.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(escaped @ ('%' | '#')) => expanded.push(escaped),
+                Some(other) => {
+                    expanded.push('\\');
+                    expanded.push(other);
+                }
+                None => expanded.push('\\'),
+            },
+            '%' => expanded.push_str(current.ok_or_else(|| "no file name".to_string())?),
+            '#' => expanded.push_str(alternate.ok_or_else(|| "no alternate file".to_string())?),
+            ch => expanded.push(ch),
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Parses the name of the encoding of an `encode` or `decode` command.
+fn parse_transform(name: &str) -> Result<Transform, String> {
+    Transform::parse(name).ok_or_else(|| format!("unknown encoding: {name}"))
+}
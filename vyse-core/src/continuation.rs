@@ -0,0 +1,313 @@
+use std::{ops::Range, path::Path};
+
+use crate::indent;
+
+/// How the lines of a kind of file are continued when pressing Enter.
+#[derive(Debug)]
+pub struct Filetype {
+    /// The name used to enable continuation for the filetype with the `continuation` option.
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    /// The line comment markers, longer ones first.
+    comment_markers: &'static [&'static str],
+    /// Whether `/* */` comments are continued with ` * `.
+    block_comments: bool,
+    /// Whether bulleted and numbered list items are continued.
+    lists: bool,
+}
+
+/// What pressing Enter at the end of a line does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Continuation {
+    /// The new line starts with this text, the indentation followed by the marker.
+    Prefix(String),
+    /// The marker of an empty item or comment line at this byte range is removed instead of
+    /// starting a new line, ending the list or comment.
+    End(Range<usize>),
+}
+
+/// The filetypes whose lists or comments are continued.
+pub const FILETYPES: &[Filetype] = &[
+    Filetype {
+        name: "markdown",
+        extensions: &["md", "markdown", "txt"],
+        comment_markers: &[],
+        block_comments: false,
+        lists: true,
+    },
+    Filetype {
+        name: "rust",
+        extensions: &["rs"],
+        comment_markers: &["///", "//!", "//"],
+        block_comments: true,
+        lists: false,
+    },
+    Filetype {
+        name: "c",
+        extensions: &["c", "h", "cc", "cpp", "cxx", "hpp"],
+        comment_markers: &["//"],
+        block_comments: true,
+        lists: false,
+    },
+    Filetype {
+        name: "javascript",
+        extensions: &["js", "jsx", "mjs", "ts", "tsx"],
+        comment_markers: &["//"],
+        block_comments: true,
+        lists: false,
+    },
+    Filetype {
+        name: "go",
+        extensions: &["go"],
+        comment_markers: &["//"],
+        block_comments: true,
+        lists: false,
+    },
+    Filetype {
+        name: "python",
+        extensions: &["py"],
+        comment_markers: &["#"],
+        block_comments: false,
+        lists: false,
+    },
+    Filetype {
+        name: "shell",
+        extensions: &["sh", "bash", "zsh"],
+        comment_markers: &["#"],
+        block_comments: false,
+        lists: false,
+    },
+    Filetype {
+        name: "toml",
+        extensions: &["toml"],
+        comment_markers: &["#"],
+        block_comments: false,
+        lists: false,
+    },
+];
+
+/// Returns the filetype of a file based on its extension.
+pub fn get_filetype(path: &Path) -> Option<&'static Filetype> {
+    let extension = path.extension()?.to_str()?;
+    FILETYPES
+        .iter()
+        .find(|filetype| filetype.extensions.contains(&extension))
+}
+
+impl Filetype {
+    /// Returns how a line is continued when it is split at a byte index, `None` if it does not
+    /// start with a list or comment marker before that index.
+    pub fn continue_line(&self, line: &str, byte_index: usize) -> Option<Continuation> {
+        let (before, after) = line.split_at(byte_index);
+        let indent = indent::leading_whitespace(before);
+        let text = &before[indent.len()..];
+        let (marker_len, next_marker) = self.get_marker(text)?;
+
+        if marker_len == text.len() && after.trim().is_empty() {
+            return Some(Continuation::End(indent.len()..byte_index));
+        }
+
+        Some(Continuation::Prefix(format!("{indent}{next_marker}")))
+    }
+
+    /// Returns whether a line is part of a comment, i.e. starts with a line comment marker or
+    /// opens, continues or closes a block comment.
+    pub fn is_comment(&self, line: &str) -> bool {
+        let text = line.trim_start();
+        let in_block_comment = text.starts_with("/*")
+            || text.starts_with("* ")
+            || text.starts_with("*/")
+            || text == "*";
+
+        (self.block_comments && in_block_comment)
+            || self
+                .comment_markers
+                .iter()
+                .any(|marker| text.starts_with(marker))
+    }
+
+    /// Returns the length of the marker a line starts with after its indentation, including the
+    /// spaces after it, and the marker of the next line.
+    fn get_marker(&self, text: &str) -> Option<(usize, String)> {
+        if self.lists {
+            if let Some(marker) = get_list_marker(text) {
+                return Some(marker);
+            }
+        }
+
+        if self.block_comments {
+            if let Some(rest) = text.strip_prefix("/*") {
+                // The opening line is never removed, so its marker is counted as empty.
+                return (!rest.contains("*/")).then(|| (0, " * ".to_string()));
+            }
+            if text.starts_with('*') && !text.contains("*/") {
+                return get_comment_marker(text, "*");
+            }
+        }
+
+        self.comment_markers
+            .iter()
+            .find_map(|marker| get_comment_marker(text, marker))
+    }
+}
+
+/// Returns the marker of a comment line starting with a comment marker followed by whitespace.
+fn get_comment_marker(text: &str, marker: &str) -> Option<(usize, String)> {
+    let rest = text.strip_prefix(marker)?;
+    let spaces = &rest[..rest.len() - rest.trim_start_matches(' ').len()];
+    if spaces.is_empty() && !rest.is_empty() {
+        return None;
+    }
+
+    let next_spaces = if spaces.is_empty() { " " } else { spaces };
+    Some((
+        marker.len() + spaces.len(),
+        format!("{marker}{next_spaces}"),
+    ))
+}
+
+/// Returns the marker of a list item: a bullet `-`, `*` or `+`, optionally followed by a task
+/// checkbox, or a number followed by `.` or `)`. Numbers are incremented for the next item and
+/// checkboxes are unchecked.
+fn get_list_marker(text: &str) -> Option<(usize, String)> {
+    let digits_len = text.len()
+        - text
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    let (marker, next_marker) = if digits_len > 0 && digits_len <= 9 {
+        let delimiter = text[digits_len..]
+            .chars()
+            .next()
+            .filter(|ch| *ch == '.' || *ch == ')')?;
+        let number: u32 = text[..digits_len].parse().ok()?;
+        (&text[..=digits_len], format!("{}{delimiter}", number + 1))
+    } else {
+        let bullet = text
+            .chars()
+            .next()
+            .filter(|ch| matches!(ch, '-' | '*' | '+'))?;
+        (&text[..1], bullet.to_string())
+    };
+
+    let rest = &text[marker.len()..];
+    let spaces = &rest[..rest.len() - rest.trim_start_matches(' ').len()];
+    if spaces.is_empty() {
+        return None;
+    }
+
+    let checkbox = ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find(|checkbox| rest[spaces.len()..].starts_with(**checkbox))
+        .map_or("", |_| "[ ] ");
+
+    Some((
+        marker.len() + spaces.len() + checkbox.len(),
+        format!("{next_marker}{spaces}{checkbox}"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filetype(name: &str) -> &'static Filetype {
+        FILETYPES
+            .iter()
+            .find(|filetype| filetype.name == name)
+            .unwrap()
+    }
+
+    fn continue_at_end(name: &str, line: &str) -> Option<Continuation> {
+        filetype(name).continue_line(line, line.len())
+    }
+
+    fn prefix(prefix: &str) -> Continuation {
+        Continuation::Prefix(prefix.to_string())
+    }
+
+    #[test]
+    fn detects_filetypes() {
+        assert_eq!(get_filetype(Path::new("src/main.rs")).unwrap().name, "rust");
+        assert_eq!(
+            get_filetype(Path::new("README.md")).unwrap().name,
+            "markdown"
+        );
+        assert!(get_filetype(Path::new("Makefile")).is_none());
+    }
+
+    #[test]
+    fn continues_lists() {
+        assert_eq!(continue_at_end("markdown", "- item"), Some(prefix("- ")));
+        assert_eq!(
+            continue_at_end("markdown", "  +   item"),
+            Some(prefix("  +   "))
+        );
+        assert_eq!(continue_at_end("markdown", "9. item"), Some(prefix("10. ")));
+        assert_eq!(
+            continue_at_end("markdown", "  3) item"),
+            Some(prefix("  4) "))
+        );
+        assert_eq!(
+            continue_at_end("markdown", "* [x] done"),
+            Some(prefix("* [ ] "))
+        );
+    }
+
+    #[test]
+    fn ignores_text_without_marker() {
+        assert_eq!(continue_at_end("markdown", "-item"), None);
+        assert_eq!(continue_at_end("markdown", "1.5 items"), None);
+        assert_eq!(continue_at_end("markdown", "plain text"), None);
+        assert_eq!(continue_at_end("rust", "- item"), None);
+        assert_eq!(continue_at_end("rust", "//x"), None);
+    }
+
+    #[test]
+    fn ends_empty_items() {
+        assert_eq!(
+            continue_at_end("markdown", "- "),
+            Some(Continuation::End(0..2))
+        );
+        assert_eq!(
+            continue_at_end("markdown", "  1. [ ] "),
+            Some(Continuation::End(2..9))
+        );
+        assert_eq!(
+            continue_at_end("python", "    # "),
+            Some(Continuation::End(4..6))
+        );
+    }
+
+    #[test]
+    fn continues_comments() {
+        assert_eq!(continue_at_end("rust", "/// docs"), Some(prefix("/// ")));
+        assert_eq!(
+            continue_at_end("rust", "    //! docs"),
+            Some(prefix("    //! "))
+        );
+        assert_eq!(continue_at_end("shell", "# text"), Some(prefix("# ")));
+        assert_eq!(
+            filetype("c").continue_line("// split here", 8),
+            Some(prefix("// "))
+        );
+    }
+
+    #[test]
+    fn continues_block_comments() {
+        assert_eq!(continue_at_end("rust", "/*"), Some(prefix(" * ")));
+        assert_eq!(continue_at_end("go", "/* text"), Some(prefix(" * ")));
+        assert_eq!(continue_at_end("c", " * text"), Some(prefix(" * ")));
+        assert_eq!(continue_at_end("c", " */"), None);
+        assert_eq!(continue_at_end("c", "/* closed */"), None);
+        assert_eq!(continue_at_end("python", "/* text"), None);
+    }
+
+    #[test]
+    fn detects_comment_lines() {
+        assert!(filetype("rust").is_comment("  // text"));
+        assert!(filetype("rust").is_comment(" * text"));
+        assert!(filetype("rust").is_comment(" */"));
+        assert!(!filetype("python").is_comment(" * text"));
+        assert!(!filetype("python").is_comment("x = 1  # text"));
+    }
+}
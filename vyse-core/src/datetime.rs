@@ -0,0 +1,123 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A point in time in UTC, split into its calendar components.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    /// Returns the current time.
+    pub fn now() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        Self::from_unix_timestamp(i64::try_from(seconds).unwrap_or(i64::MAX))
+    }
+
+    /// Converts seconds since the unix epoch into calendar components.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn from_unix_timestamp(timestamp: i64) -> Self {
+        let days = timestamp.div_euclid(86_400);
+        let seconds_of_day = timestamp.rem_euclid(86_400) as u32;
+
+        // Converts days to a civil date, see http://howardhinnant.github.io/date_algorithms.html.
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: seconds_of_day / 3600,
+            minute: seconds_of_day / 60 % 60,
+            second: seconds_of_day % 60,
+        }
+    }
+
+    /// Formats the time by replacing the tokens `YYYY`, `MM`, `DD`, `hh`, `mm` and `ss` in the
+    /// pattern with the zero-padded components.
+    pub fn format(self, pattern: &str) -> String {
+        let tokens = [
+            ("YYYY", format!("{:04}", self.year)),
+            ("MM", format!("{:02}", self.month)),
+            ("DD", format!("{:02}", self.day)),
+            ("hh", format!("{:02}", self.hour)),
+            ("mm", format!("{:02}", self.minute)),
+            ("ss", format!("{:02}", self.second)),
+        ];
+
+        let mut formatted = String::with_capacity(pattern.len());
+        let mut rest = pattern;
+        while let Some(ch) = rest.chars().next() {
+            if let Some((token, value)) = tokens.iter().find(|(token, _)| rest.starts_with(token)) {
+                formatted.push_str(value);
+                rest = &rest[token.len()..];
+            } else {
+                formatted.push(ch);
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+
+        formatted
+    }
+
+    /// Formats the time as `YYYY-MM-DDThh:mm:ss`.
+    pub fn to_iso8601(self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_unix_timestamps() {
+        assert_eq!(
+            DateTime::from_unix_timestamp(0).to_iso8601(),
+            "1970-01-01T00:00:00"
+        );
+        assert_eq!(
+            DateTime::from_unix_timestamp(-1).to_iso8601(),
+            "1969-12-31T23:59:59"
+        );
+        assert_eq!(
+            DateTime::from_unix_timestamp(951_782_400).to_iso8601(),
+            "2000-02-29T00:00:00"
+        );
+        assert_eq!(
+            DateTime::from_unix_timestamp(1_735_689_598).to_iso8601(),
+            "2024-12-31T23:59:58"
+        );
+    }
+
+    #[test]
+    fn formats_with_patterns() {
+        let time = DateTime::from_unix_timestamp(1_735_689_598);
+        assert_eq!(time.format("YYYY/MM/DD hh:mm:ss"), "2024/12/31 23:59:58");
+        assert_eq!(time.format("DD. Mär YYYY"), "31. Mär 2024");
+        assert_eq!(time.format("Y-M-D"), "Y-M-D");
+    }
+}
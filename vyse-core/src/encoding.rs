@@ -0,0 +1,233 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+};
+
+/// The character at the start of a file marking it as Unicode text, encoded in its encoding.
+pub const BYTE_ORDER_MARK: &str = "\u{FEFF}";
+
+/// The character encoding of a file, buffers are always UTF-8 in memory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Utf8 => write!(f, "UTF-8"),
+            Self::Utf16Le => write!(f, "UTF-16LE"),
+            Self::Utf16Be => write!(f, "UTF-16BE"),
+            Self::Latin1 => write!(f, "Latin-1"),
+        }
+    }
+}
+
+impl Encoding {
+    /// Detects the encoding of the contents of a file and decodes them, returning the bytes if
+    /// they are neither UTF-8, UTF-16 nor Latin-1 text.
+    ///
+    /// UTF-16 is recognized by its byte order mark or by every other byte being zero, Latin-1 by
+    /// not containing any control characters besides whitespace.
+    pub fn decode(bytes: Vec<u8>) -> Result<(String, Self), Vec<u8>> {
+        // ASCII text in UTF-16 is valid UTF-8 as well, with a NUL byte after every character.
+        if let Some(encoding) = Self::detect_utf16(&bytes) {
+            if let Some(text) = decode_utf16(&bytes, encoding) {
+                return Ok((text, encoding));
+            }
+        }
+
+        let bytes = match String::from_utf8(bytes) {
+            Ok(text) => return Ok((text, Self::Utf8)),
+            Err(err) => err.into_bytes(),
+        };
+
+        if !bytes.iter().any(|byte| is_control(*byte)) {
+            return Ok((
+                bytes.iter().map(|byte| char::from(*byte)).collect(),
+                Self::Latin1,
+            ));
+        }
+
+        Err(bytes)
+    }
+
+    /// Returns the byte order of UTF-16 text, recognized by its byte order mark or by every other
+    /// byte being zero, or `None` if the bytes do not look like UTF-16.
+    pub fn detect_utf16(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0xFF, 0xFE, ..] => Some(Self::Utf16Le),
+            [0xFE, 0xFF, ..] => Some(Self::Utf16Be),
+            _ => guess_utf16(bytes),
+        }
+    }
+
+    /// Decodes the start of UTF-16 text, replacing unpaired surrogates and a trailing odd byte.
+    pub fn decode_utf16_lossy(self, bytes: &[u8]) -> String {
+        char::decode_utf16(get_utf16_units(bytes, self))
+            .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    }
+
+    /// Returns whether every character of the text can be represented in the encoding.
+    pub fn can_encode(self, text: &str) -> bool {
+        self != Self::Latin1 || text.chars().all(|ch| u32::from(ch) <= 0xFF)
+    }
+
+    /// Encodes text, only allocating if the encoding is not UTF-8.
+    pub fn encode(self, text: &str) -> Cow<'_, [u8]> {
+        match self {
+            Self::Utf8 => Cow::Borrowed(text.as_bytes()),
+            Self::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Self::Utf16Be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            Self::Latin1 => text
+                .chars()
+                .map(|ch| u8::try_from(ch).unwrap_or(b'?'))
+                .collect(),
+        }
+    }
+}
+
+/// Guesses the byte order of UTF-16 text without a byte order mark from the zero high bytes of
+/// ASCII characters.
+fn guess_utf16(bytes: &[u8]) -> Option<Encoding> {
+    // Most files are not UTF-16, which is quick to tell for those without NUL bytes.
+    if !bytes.len().is_multiple_of(2) || !bytes.contains(&0) {
+        return None;
+    }
+
+    let pairs = bytes.len() / 2;
+    let zeros_at = |parity: usize| {
+        bytes
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|byte| **byte == 0)
+            .count()
+    };
+    let (even_zeros, odd_zeros) = (zeros_at(0), zeros_at(1));
+
+    if odd_zeros > pairs / 2 && even_zeros * 4 < odd_zeros {
+        Some(Encoding::Utf16Le)
+    } else if even_zeros > pairs / 2 && odd_zeros * 4 < even_zeros {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Decodes UTF-16 text, `None` if it contains unpaired surrogates.
+fn decode_utf16(bytes: &[u8], encoding: Encoding) -> Option<String> {
+    char::decode_utf16(get_utf16_units(bytes, encoding))
+        .collect::<Result<_, _>>()
+        .ok()
+}
+
+/// Returns the code units of UTF-16 text in its byte order.
+fn get_utf16_units(bytes: &[u8], encoding: Encoding) -> impl Iterator<Item = u16> + '_ {
+    bytes.chunks_exact(2).map(move |pair| {
+        let pair = [pair[0], pair[1]];
+        if encoding == Encoding::Utf16Be {
+            u16::from_be_bytes(pair)
+        } else {
+            u16::from_le_bytes(pair)
+        }
+    })
+}
+
+/// Returns whether the byte is a control character other than whitespace, which do not appear
+/// in Latin-1 text.
+fn is_control(byte: u8) -> bool {
+    (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0C)) || (0x7F..=0x9F).contains(&byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_utf8() {
+        let text = "héllo\n";
+        assert_eq!(
+            Encoding::decode(text.as_bytes().to_vec()),
+            Ok((text.to_string(), Encoding::Utf8))
+        );
+    }
+
+    #[test]
+    fn detects_utf16_by_byte_order_mark() {
+        assert_eq!(
+            Encoding::detect_utf16(&[0xFF, 0xFE, b'a', 0]),
+            Some(Encoding::Utf16Le)
+        );
+        assert_eq!(
+            Encoding::detect_utf16(&[0xFE, 0xFF, 0, b'a']),
+            Some(Encoding::Utf16Be)
+        );
+    }
+
+    #[test]
+    fn guesses_utf16_without_byte_order_mark() {
+        assert_eq!(
+            Encoding::detect_utf16(b"h\0i\0\n\0"),
+            Some(Encoding::Utf16Le)
+        );
+        assert_eq!(
+            Encoding::detect_utf16(b"\0h\0i\0\n"),
+            Some(Encoding::Utf16Be)
+        );
+        assert_eq!(Encoding::detect_utf16(b"hi\n"), None);
+        assert_eq!(Encoding::detect_utf16(b"\0\0\0\0"), None);
+    }
+
+    #[test]
+    fn falls_back_to_latin1() {
+        assert_eq!(
+            Encoding::decode(b"caf\xE9\n".to_vec()),
+            Ok(("café\n".to_string(), Encoding::Latin1))
+        );
+    }
+
+    #[test]
+    fn rejects_binary() {
+        let bytes = vec![0x7F, b'E', b'L', b'F', 0x02, 0x01, 0xFF];
+        assert_eq!(Encoding::decode(bytes.clone()), Err(bytes));
+    }
+
+    #[test]
+    fn round_trips() {
+        let text = "héllo wörld\r\n";
+
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+            Encoding::Latin1,
+        ] {
+            let bytes = encoding.encode(text).into_owned();
+            assert_eq!(
+                Encoding::decode(bytes),
+                Ok((text.to_string(), encoding)),
+                "{encoding}"
+            );
+        }
+    }
+
+    #[test]
+    fn latin1_replaces_unencodable_characters() {
+        assert!(Encoding::Latin1.can_encode("café"));
+        assert!(!Encoding::Latin1.can_encode("€"));
+        assert_eq!(Encoding::Latin1.encode("1€").as_ref(), b"1?");
+    }
+
+    #[test]
+    fn decodes_utf16_lossily() {
+        let bytes = [b'a', 0, 0x00, 0xD8, b'b', 0];
+        assert_eq!(Encoding::decode(bytes.to_vec()), Err(bytes.to_vec()));
+        assert_eq!(Encoding::Utf16Le.decode_utf16_lossy(&bytes), "a\u{FFFD}b");
+    }
+}
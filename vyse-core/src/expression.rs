@@ -0,0 +1,163 @@
+use std::{iter::Peekable, str::Chars};
+
+/// Evaluates an arithmetic expression, e.g. `(1 + 2) * 0x10 / 4`.
+///
+/// Supports `+`, `-`, `*`, `/`, `%` and `^` for powers with the usual precedence, parentheses,
+/// unary minus, decimal and hexadecimal numbers.
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expression.chars().peekable(),
+    };
+
+    let value = parser.parse_sum()?;
+    parser.skip_whitespace();
+
+    match parser.chars.next() {
+        Some(ch) => Err(format!("unexpected character: {ch}")),
+        None if value.is_finite() => Ok(value),
+        None => Err("result is not a number".to_string()),
+    }
+}
+
+/// Formats a result without a fractional part if it is a whole number.
+pub fn format_value(value: f64) -> String {
+    // Whole numbers up to 2^53 are represented exactly.
+    if value.fract() == 0.0 && value.abs() < 9_007_199_254_740_992.0 {
+        #[allow(clippy::cast_possible_truncation)]
+        let value = value as i64;
+        value.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// A recursive descent parser computing the value while parsing.
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    /// Parses terms joined by `+` and `-`.
+    fn parse_sum(&mut self) -> Result<f64, String> {
+        let mut value = self.parse_product()?;
+
+        loop {
+            match self.next_operator(&['+', '-']) {
+                Some('+') => value += self.parse_product()?,
+                Some(_) => value -= self.parse_product()?,
+                None => return Ok(value),
+            }
+        }
+    }
+
+    /// Parses factors joined by `*`, `/` and `%`.
+    fn parse_product(&mut self) -> Result<f64, String> {
+        let mut value = self.parse_power()?;
+
+        loop {
+            match self.next_operator(&['*', '/', '%']) {
+                Some('*') => value *= self.parse_power()?,
+                Some('/') => value /= self.parse_power()?,
+                Some(_) => value %= self.parse_power()?,
+                None => return Ok(value),
+            }
+        }
+    }
+
+    /// Parses a power, which is right associative.
+    fn parse_power(&mut self) -> Result<f64, String> {
+        let base = self.parse_unary()?;
+
+        match self.next_operator(&['^']) {
+            Some(_) => Ok(base.powf(self.parse_power()?)),
+            None => Ok(base),
+        }
+    }
+
+    /// Parses a number or parenthesized expression with any number of leading minus signs.
+    fn parse_unary(&mut self) -> Result<f64, String> {
+        if self.next_operator(&['-']).is_some() {
+            return Ok(-self.parse_unary()?);
+        }
+
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('(') => {
+                self.chars.next();
+                let value = self.parse_sum()?;
+
+                self.next_operator(&[')'])
+                    .map(|_| value)
+                    .ok_or_else(|| "missing closing parenthesis".to_string())
+            }
+            Some(ch) if ch.is_ascii_digit() || *ch == '.' => self.parse_number(),
+            Some(ch) => Err(format!("unexpected character: {ch}")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    /// Parses a decimal or `0x` prefixed hexadecimal number.
+    fn parse_number(&mut self) -> Result<f64, String> {
+        let mut number = String::new();
+        while let Some(ch) = self
+            .chars
+            .next_if(|ch| ch.is_ascii_alphanumeric() || *ch == '.')
+        {
+            number.push(ch);
+        }
+
+        let value = match number.strip_prefix("0x") {
+            #[allow(clippy::cast_precision_loss)]
+            Some(hex) => u64::from_str_radix(hex, 16).ok().map(|value| value as f64),
+            None => number.parse().ok(),
+        };
+
+        value.ok_or_else(|| format!("invalid number: {number}"))
+    }
+
+    /// Consumes the next character if it is one of the given operators.
+    fn next_operator(&mut self, operators: &[char]) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.next_if(|ch| operators.contains(ch))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|ch| ch.is_whitespace()).is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_with_precedence() {
+        assert_eq!(evaluate("(1 + 2) * 0x10 / 4"), Ok(12.0));
+        assert_eq!(evaluate("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(evaluate("7 % 4 - -1"), Ok(4.0));
+        assert_eq!(evaluate(" .5*3 "), Ok(1.5));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(evaluate("1 / 0"), Err("result is not a number".to_string()));
+        assert_eq!(
+            evaluate("1 +"),
+            Err("unexpected end of expression".to_string())
+        );
+        assert_eq!(
+            evaluate("(1 + 2"),
+            Err("missing closing parenthesis".to_string())
+        );
+        assert_eq!(evaluate("2 x"), Err("unexpected character: x".to_string()));
+        assert_eq!(evaluate("1.5.2"), Err("invalid number: 1.5.2".to_string()));
+    }
+
+    #[test]
+    fn formats_whole_numbers_without_fraction() {
+        assert_eq!(format_value(12.0), "12");
+        assert_eq!(format_value(-3.0), "-3");
+        assert_eq!(format_value(0.25), "0.25");
+    }
+}
@@ -0,0 +1,208 @@
+use std::iter;
+
+/// A range of lines collapsed into its first line, which stays visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fold {
+    pub start: usize,
+    /// The last hidden line.
+    pub end: usize,
+}
+
+impl Fold {
+    /// Returns the number of hidden lines.
+    pub fn hidden_count(self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns whether a row is the first line of the fold or one of its hidden lines.
+    pub fn contains(self, row: usize) -> bool {
+        (self.start..=self.end).contains(&row)
+    }
+}
+
+/// The folded ranges of a buffer.
+#[derive(Debug, Default)]
+pub struct Folds {
+    /// Folds that do not overlap, sorted by their start.
+    folds: Vec<Fold>,
+    /// The number of lines when the folds were last updated, to move them along with edits.
+    line_count: usize,
+}
+
+impl Folds {
+    /// Folds the lines after `start` up to `end` into it, replacing the folds inside the range.
+    /// Returns `false` if the range is empty or hidden by another fold.
+    pub fn add(&mut self, start: usize, end: usize, line_count: usize) -> bool {
+        self.line_count = line_count;
+        if end <= start || self.is_hidden(start) {
+            return false;
+        }
+
+        self.folds
+            .retain(|fold| !(start..=end).contains(&fold.start));
+        let idx = self.folds.partition_point(|fold| fold.start < start);
+        self.folds.insert(idx, Fold { start, end });
+
+        true
+    }
+
+    /// Opens the fold around a row, returns whether there was one.
+    pub fn remove_at(&mut self, row: usize) -> bool {
+        let count = self.folds.len();
+        self.folds.retain(|fold| !fold.contains(row));
+        self.folds.len() != count
+    }
+
+    /// Opens every fold.
+    pub fn clear(&mut self) {
+        self.folds.clear();
+    }
+
+    /// Returns whether nothing is folded.
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    /// Returns the fold whose first line is a row.
+    pub fn get(&self, row: usize) -> Option<Fold> {
+        self.folds
+            .binary_search_by_key(&row, |fold| fold.start)
+            .ok()
+            .map(|idx| self.folds[idx])
+    }
+
+    /// Returns whether a row is hidden by a fold.
+    pub fn is_hidden(&self, row: usize) -> bool {
+        self.get_hiding(row).is_some()
+    }
+
+    /// Returns the rows that are not hidden from a row on, downwards or upwards.
+    pub fn visible_rows(&self, from: usize, down: bool) -> impl Iterator<Item = usize> + '_ {
+        let mut row = Some(from);
+
+        iter::from_fn(move || {
+            let mut current = row?;
+            if let Some(fold) = self.get_hiding(current) {
+                current = if down { fold.end + 1 } else { fold.start };
+            }
+
+            row = if down {
+                current.checked_add(1)
+            } else {
+                current.checked_sub(1)
+            };
+            Some(current)
+        })
+    }
+
+    /// Moves the folds after an edit by the number of lines added or removed since the last
+    /// update, opening the folds the edit touched.
+    ///
+    /// Edits are made at the cursor, which ends up after the lines inserted by an edit and at
+    /// the start of the lines removed by it.
+    pub fn update(&mut self, line_count: usize, cursor_row: usize) {
+        let delta = line_count.cast_signed() - self.line_count.cast_signed();
+        self.line_count = line_count;
+        if delta == 0 {
+            return;
+        }
+
+        // The rows of the edit before it was made.
+        let (first, last) = if delta > 0 {
+            let first = cursor_row.saturating_sub(delta.unsigned_abs());
+            (first, first)
+        } else {
+            (cursor_row, cursor_row + delta.unsigned_abs())
+        };
+
+        self.folds
+            .retain(|fold| fold.end < first || fold.start > last);
+        for fold in self.folds.iter_mut().filter(|fold| fold.start > last) {
+            fold.start = fold.start.saturating_add_signed(delta);
+            fold.end = fold.end.saturating_add_signed(delta);
+        }
+    }
+
+    /// Returns the fold hiding a row.
+    fn get_hiding(&self, row: usize) -> Option<Fold> {
+        let idx = self.folds.partition_point(|fold| fold.start < row);
+        idx.checked_sub(1)
+            .map(|idx| self.folds[idx])
+            .filter(|fold| fold.end >= row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folds(ranges: &[(usize, usize)], line_count: usize) -> Folds {
+        let mut folds = Folds::default();
+        for &(start, end) in ranges {
+            assert!(folds.add(start, end, line_count));
+        }
+        folds
+    }
+
+    fn ranges(folds: &Folds) -> Vec<(usize, usize)> {
+        folds
+            .folds
+            .iter()
+            .map(|fold| (fold.start, fold.end))
+            .collect()
+    }
+
+    #[test]
+    fn adding_replaces_inner_folds() {
+        let mut folds = folds(&[(3, 4), (6, 8)], 20);
+        assert!(folds.add(2, 9, 20));
+        assert_eq!(ranges(&folds), [(2, 9)]);
+
+        assert!(!folds.add(5, 7, 20));
+        assert!(!folds.add(12, 12, 20));
+        assert_eq!(ranges(&folds), [(2, 9)]);
+    }
+
+    #[test]
+    fn skips_hidden_rows() {
+        let folds = folds(&[(2, 4), (6, 7)], 20);
+        let down: Vec<usize> = folds.visible_rows(1, true).take(5).collect();
+        assert_eq!(down, [1, 2, 5, 6, 8]);
+
+        let up: Vec<usize> = folds.visible_rows(7, false).collect();
+        assert_eq!(up, [6, 5, 2, 1, 0]);
+    }
+
+    #[test]
+    fn moves_folds_after_inserted_lines() {
+        let mut folds = folds(&[(2, 5), (10, 12)], 20);
+        // Two lines inserted at row 7, the cursor ends up after them.
+        folds.update(22, 9);
+        assert_eq!(ranges(&folds), [(2, 5), (12, 14)]);
+    }
+
+    #[test]
+    fn moves_folds_after_removed_lines() {
+        let mut folds = folds(&[(2, 5), (10, 12)], 20);
+        // Rows 7 and 8 removed, the cursor stays at their start.
+        folds.update(18, 7);
+        assert_eq!(ranges(&folds), [(2, 5), (8, 10)]);
+    }
+
+    #[test]
+    fn opens_folds_touched_by_edits() {
+        let mut folds = folds(&[(2, 5), (10, 12)], 20);
+        folds.update(19, 4);
+        assert_eq!(ranges(&folds), [(9, 11)]);
+
+        folds.update(20, 10);
+        assert!(folds.is_empty());
+    }
+
+    #[test]
+    fn ignores_edits_within_a_line() {
+        let mut folds = folds(&[(2, 5)], 20);
+        folds.update(20, 3);
+        assert_eq!(ranges(&folds), [(2, 5)]);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::datetime::DateTime;
+
+/// The number of lines at the top of a file that are searched for header fields.
+pub const HEADER_LINES: usize = 10;
+
+/// Returns the header line with its "Last modified" date or copyright year updated to the given
+/// time, or `None` if it needs no change.
+pub fn update_line(line: &str, now: DateTime) -> Option<String> {
+    update_last_modified(line, now).or_else(|| update_copyright(line, now.year))
+}
+
+/// Replaces the date following `Last modified:`, keeping whether it included the time.
+fn update_last_modified(line: &str, now: DateTime) -> Option<String> {
+    const LABEL: &str = "last modified:";
+
+    let label_end = line.to_ascii_lowercase().find(LABEL)? + LABEL.len();
+    let date_start = label_end + (line.len() - label_end - line[label_end..].trim_start().len());
+    let date_len = line[date_start..]
+        .find(|ch: char| !(ch.is_ascii_digit() || matches!(ch, '-' | ':' | 'T')))
+        .unwrap_or(line.len() - date_start);
+
+    let timestamp = now.to_iso8601();
+    let date = if date_len == "YYYY-MM-DD".len() {
+        &timestamp[.."YYYY-MM-DD".len()]
+    } else {
+        &timestamp
+    };
+
+    let old_date = &line[date_start..date_start + date_len];
+    if date_len == 0 || old_date == date {
+        return None;
+    }
+
+    Some(format!(
+        "{}{date}{}",
+        &line[..date_start],
+        &line[date_start + date_len..]
+    ))
+}
+
+/// Extends the last year of a copyright notice to the given year, e.g. `2021` to `2021-2024`.
+fn update_copyright(line: &str, year: i64) -> Option<String> {
+    if !line.to_ascii_lowercase().contains("copyright") {
+        return None;
+    }
+
+    let bytes = line.as_bytes();
+    let is_year_at = |idx: usize| {
+        bytes
+            .get(idx..idx + 4)
+            .is_some_and(|digits| digits.iter().all(u8::is_ascii_digit))
+            && !bytes.get(idx + 4).is_some_and(u8::is_ascii_digit)
+            && (idx == 0 || !bytes[idx - 1].is_ascii_digit())
+    };
+
+    let start = (0..bytes.len()).rev().find(|idx| is_year_at(*idx))?;
+    let last_year: i64 = line[start..start + 4].parse().ok()?;
+    if last_year >= year {
+        return None;
+    }
+
+    // Either the end of an existing range is moved, or the single year becomes a range.
+    let is_range_end = start >= 5 && bytes[start - 1] == b'-' && is_year_at(start - 5);
+    let replacement = if is_range_end {
+        year.to_string()
+    } else {
+        format!("{last_year}-{year}")
+    };
+
+    Some(format!(
+        "{}{replacement}{}",
+        &line[..start],
+        &line[start + 4..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: DateTime = DateTime {
+        year: 2024,
+        month: 12,
+        day: 31,
+        hour: 23,
+        minute: 59,
+        second: 58,
+    };
+
+    #[test]
+    fn updates_last_modified_dates() {
+        assert_eq!(
+            update_line("// Last modified: 2023-01-02", NOW).unwrap(),
+            "// Last modified: 2024-12-31"
+        );
+        assert_eq!(
+            update_line("# last modified:  2023-01-02T10:00:00 by me", NOW).unwrap(),
+            "# last modified:  2024-12-31T23:59:58 by me"
+        );
+        assert_eq!(update_line("Last modified: 2024-12-31", NOW), None);
+        assert_eq!(update_line("Last modified: never", NOW), None);
+    }
+
+    #[test]
+    fn extends_copyright_years() {
+        assert_eq!(
+            update_line("Copyright 2021 Someone", NOW).unwrap(),
+            "Copyright 2021-2024 Someone"
+        );
+        assert_eq!(
+            update_line("(c) COPYRIGHT 2019-2023, 12345", NOW).unwrap(),
+            "(c) COPYRIGHT 2019-2024, 12345"
+        );
+        assert_eq!(update_line("Copyright 2024", NOW), None);
+        assert_eq!(update_line("Copyright 12345", NOW), None);
+        assert_eq!(update_line("Released in 2021", NOW), None);
+    }
+}
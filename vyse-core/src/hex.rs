@@ -0,0 +1,79 @@
+use std::fmt::Write;
+
+/// The number of bytes shown in each row of a hex dump.
+pub const BYTES_PER_ROW: usize = 16;
+/// The width of the offset column, including the separator after it.
+const OFFSET_WIDTH: usize = 10;
+
+/// Formats a row of a hex dump, e.g. `00000010  48 65 6c 6c 6f ...  |Hello...|`.
+pub fn format_row(offset: usize, bytes: &[u8]) -> String {
+    let mut row = format!("{offset:08x}  ");
+
+    for idx in 0..BYTES_PER_ROW {
+        match bytes.get(idx) {
+            Some(byte) => {
+                let _ = write!(row, "{byte:02x} ");
+            }
+            None => row.push_str("   "),
+        }
+    }
+
+    row.push_str(" |");
+    row.extend(bytes.iter().map(|byte| {
+        if byte.is_ascii_graphic() || *byte == b' ' {
+            char::from(*byte)
+        } else {
+            '.'
+        }
+    }));
+    row.push('|');
+
+    row
+}
+
+/// Returns the screen column of the hex digits of the byte at the given column of a row.
+pub fn cursor_column(col: usize) -> usize {
+    OFFSET_WIDTH + col * 3
+}
+
+/// Returns the column of the byte whose hex digits are shown at the given screen column.
+pub fn column_at(width: usize) -> usize {
+    (width.saturating_sub(OFFSET_WIDTH) / 3).min(BYTES_PER_ROW - 1)
+}
+
+/// Parses a byte offset given in decimal or as hexadecimal with a `0x` prefix.
+pub fn parse_offset(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_rows() {
+        assert_eq!(
+            format_row(16, b"Hi \0"),
+            format!("00000010  48 69 20 00 {} |Hi .|", "   ".repeat(12))
+        );
+    }
+
+    #[test]
+    fn maps_columns_to_bytes() {
+        assert_eq!(cursor_column(2), 16);
+        assert_eq!(column_at(16), 2);
+        assert_eq!(column_at(18), 2);
+        assert_eq!(column_at(0), 0);
+        assert_eq!(column_at(1000), BYTES_PER_ROW - 1);
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(parse_offset("0x1f"), Some(31));
+        assert_eq!(parse_offset("42"), Some(42));
+        assert_eq!(parse_offset("0xg"), None);
+    }
+}
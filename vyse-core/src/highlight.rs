@@ -0,0 +1,233 @@
+use std::{ops::Range, path::Path};
+
+use crate::buffer::Buffer;
+
+/// The number of lines before the view that are scanned to find out whether it starts inside a
+/// block comment.
+const LOOKBEHIND_LINES: usize = 500;
+
+/// The kind of text a span is, which determines its color in the theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Keyword,
+    Type,
+    String,
+    Number,
+    Comment,
+    /// Tabs, trailing spaces and newlines shown as symbols.
+    Whitespace,
+    /// Control characters shown in caret notation or as escapes.
+    Control,
+}
+
+/// A highlighted byte range within a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub style: Style,
+}
+
+/// Computes the highlighted spans of the lines of a buffer.
+pub trait Highlighter {
+    /// Returns the spans of each of the given rows, sorted by their start.
+    fn highlight(&self, buffer: &Buffer, rows: Range<usize>) -> Vec<Vec<Span>>;
+}
+
+/// Returns the highlighter for a file based on its extension, if the language is known.
+pub fn highlighter_for(path: &Path) -> Option<Box<dyn Highlighter>> {
+    let extension = path.extension()?.to_str()?;
+    let syntax = SYNTAXES
+        .iter()
+        .find(|syntax| syntax.extensions.contains(&extension))?;
+
+    Some(Box::new(KeywordHighlighter { syntax }))
+}
+
+/// The tokens of a language the keyword highlighter knows about.
+#[derive(Debug)]
+struct Syntax {
+    extensions: &'static [&'static str],
+    /// The keywords separated by spaces.
+    keywords: &'static str,
+    line_comment: &'static str,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const C_KEYWORDS: &str =
+    "auto break case char class const continue default delete do double else enum extern \
+    float for goto if include define inline int long namespace new nullptr private \
+    protected public return short signed sizeof static struct switch template this \
+    typedef union unsigned using virtual void volatile while";
+
+const SYNTAXES: &[Syntax] = &[
+    Syntax {
+        extensions: &["rs"],
+        keywords:
+            "as async await break const continue crate dyn else enum extern false fn for if impl \
+            in let loop match mod move mut pub ref return self static struct super trait true \
+            type unsafe use where while",
+        line_comment: "//",
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+    },
+    Syntax {
+        extensions: &["c", "h", "cc", "cpp", "cxx", "hpp"],
+        keywords: C_KEYWORDS,
+        line_comment: "//",
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+    },
+    Syntax {
+        extensions: &["js", "jsx", "mjs", "ts", "tsx"],
+        keywords:
+            "async await break case catch class const continue default delete do else export \
+            extends false finally for function if import in instanceof interface let new null of \
+            return static switch this throw true try type typeof undefined var void while yield",
+        line_comment: "//",
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+    },
+    Syntax {
+        extensions: &["go"],
+        keywords:
+            "break case chan const continue default defer else false for func go goto if import \
+            interface map nil package range return select struct switch true type var",
+        line_comment: "//",
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+    },
+    Syntax {
+        extensions: &["py"],
+        keywords:
+            "False None True and as assert async await break class continue def del elif else \
+            except finally for from global if import in is lambda nonlocal not or pass raise \
+            return try while with yield",
+        line_comment: "#",
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Syntax {
+        extensions: &["sh", "bash", "zsh"],
+        keywords:
+            "case do done elif else esac export fi for function if in local return then until \
+            while",
+        line_comment: "#",
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Syntax {
+        extensions: &["toml"],
+        keywords: "false true",
+        line_comment: "#",
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+];
+
+/// Highlights keywords, capitalized type names, strings, numbers and comments of a language
+/// without parsing it.
+#[derive(Debug)]
+struct KeywordHighlighter {
+    syntax: &'static Syntax,
+}
+
+impl Highlighter for KeywordHighlighter {
+    fn highlight(&self, buffer: &Buffer, rows: Range<usize>) -> Vec<Vec<Span>> {
+        // Block comments are only tracked from a limited number of lines before the view, so
+        // scrolling stays fast in large files.
+        let mut in_block_comment = false;
+        for row in rows.start.saturating_sub(LOOKBEHIND_LINES)..rows.start {
+            if let Some(line) = buffer.get_line(row) {
+                self.highlight_line(&line, &mut in_block_comment);
+            }
+        }
+
+        rows.map(|row| {
+            buffer.get_line(row).map_or(Vec::new(), |line| {
+                self.highlight_line(&line, &mut in_block_comment)
+            })
+        })
+        .collect()
+    }
+}
+
+impl KeywordHighlighter {
+    /// Highlights a single line, updating whether the next line starts inside a block comment.
+    fn highlight_line(&self, line: &str, in_block_comment: &mut bool) -> Vec<Span> {
+        let syntax = self.syntax;
+        let mut spans = Vec::new();
+        let mut idx = 0;
+
+        while idx < line.len() {
+            let rest = &line[idx..];
+            let start = idx;
+
+            let style = if let Some((open, close)) = syntax
+                .block_comment
+                .filter(|(open, _)| *in_block_comment || rest.starts_with(open))
+            {
+                let search_from = if *in_block_comment { 0 } else { open.len() };
+                if let Some(end) = rest[search_from..].find(close) {
+                    *in_block_comment = false;
+                    idx += search_from + end + close.len();
+                } else {
+                    *in_block_comment = true;
+                    idx = line.len();
+                }
+                Style::Comment
+            } else if rest.starts_with(syntax.line_comment) {
+                idx = line.len();
+                Style::Comment
+            } else if let Some(quote) = rest.chars().next().filter(|ch| syntax.quotes.contains(ch))
+            {
+                idx += string_length(rest, quote);
+                Style::String
+            } else if rest.starts_with(|ch: char| ch.is_alphanumeric() || ch == '_') {
+                let word_length = rest
+                    .find(|ch: char| !ch.is_alphanumeric() && ch != '_')
+                    .unwrap_or(rest.len());
+                let word = &rest[..word_length];
+                idx += word_length;
+
+                if word.starts_with(|ch: char| ch.is_ascii_digit()) {
+                    Style::Number
+                } else if syntax.keywords.split(' ').any(|keyword| keyword == word) {
+                    Style::Keyword
+                } else if word.starts_with(|ch: char| ch.is_uppercase()) {
+                    Style::Type
+                } else {
+                    continue;
+                }
+            } else {
+                idx += rest.chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+
+            spans.push(Span {
+                start,
+                end: idx,
+                style,
+            });
+        }
+
+        spans
+    }
+}
+
+/// Returns the length of the string literal at the start of the text including its quotes,
+/// skipping escaped quotes. Unterminated strings end with the line.
+fn string_length(text: &str, quote: char) -> usize {
+    let mut chars = text.char_indices().skip(1);
+
+    while let Some((idx, ch)) = chars.next() {
+        if ch == '\\' {
+            chars.next();
+        } else if ch == quote {
+            return idx + ch.len_utf8();
+        }
+    }
+
+    text.len()
+}
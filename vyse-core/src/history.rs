@@ -0,0 +1,176 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::buffer::Location;
+
+/// Edits made within this duration of the previous one are undone together.
+const GROUP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A reversible change to a buffer, text may span multiple lines separated by `\n`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    Insert { location: Location, text: String },
+    Delete { location: Location, text: String },
+}
+
+impl Edit {
+    /// Returns where the edit starts.
+    pub fn location(&self) -> Location {
+        match self {
+            Self::Insert { location, .. } | Self::Delete { location, .. } => *location,
+        }
+    }
+
+    /// Returns the edit that reverts this one.
+    #[must_use]
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::Insert { location, text } => Self::Delete {
+                location: *location,
+                text: text.clone(),
+            },
+            Self::Delete { location, text } => Self::Insert {
+                location: *location,
+                text: text.clone(),
+            },
+        }
+    }
+}
+
+/// The edits that are undone or redone in a single step.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub edits: Vec<Edit>,
+    /// When the first edit was made, unknown for histories saved by older versions.
+    pub created_at: Option<SystemTime>,
+}
+
+/// The undo and redo stacks of a buffer.
+#[derive(Debug, Default)]
+pub struct History {
+    pub undo_stack: Vec<Transaction>,
+    pub redo_stack: Vec<Transaction>,
+    last_edit_at: Option<Instant>,
+}
+
+impl History {
+    /// Records an edit, grouping it with the previous one if they happened in quick succession.
+    pub fn record(&mut self, edit: Edit) {
+        let now = Instant::now();
+        let continues_group = self
+            .last_edit_at
+            .is_some_and(|last_edit_at| now.duration_since(last_edit_at) < GROUP_TIMEOUT);
+
+        match self.undo_stack.last_mut() {
+            Some(transaction) if continues_group => transaction.edits.push(edit),
+            _ => self.undo_stack.push(Transaction {
+                edits: vec![edit],
+                created_at: Some(SystemTime::now()),
+            }),
+        }
+
+        self.redo_stack.clear();
+        self.last_edit_at = Some(now);
+    }
+
+    /// Ends the current group, so the next edit starts a new undo step.
+    pub fn close_group(&mut self) {
+        self.last_edit_at = None;
+    }
+
+    /// Returns all transactions from the oldest to the newest, including undone ones.
+    pub fn transactions(&self) -> impl Iterator<Item = &Transaction> {
+        self.undo_stack.iter().chain(self.redo_stack.iter().rev())
+    }
+
+    /// Returns the number of transactions applied to reach the current state.
+    pub fn current_state(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Takes the most recent transaction to undo it.
+    pub fn undo(&mut self) -> Option<Transaction> {
+        let transaction = self.undo_stack.pop()?;
+        self.redo_stack.push(transaction.clone());
+        self.close_group();
+
+        Some(transaction)
+    }
+
+    /// Takes the most recently undone transaction to apply it again.
+    pub fn redo(&mut self) -> Option<Transaction> {
+        let transaction = self.redo_stack.pop()?;
+        self.undo_stack.push(transaction.clone());
+        self.close_group();
+
+        Some(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(col: usize, text: &str) -> Edit {
+        Edit::Insert {
+            location: Location { row: 0, col },
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn inverts_edits() {
+        let edit = insert(3, "a\nb");
+        assert_eq!(
+            edit.inverse(),
+            Edit::Delete {
+                location: Location { row: 0, col: 3 },
+                text: "a\nb".to_string(),
+            }
+        );
+        assert_eq!(edit.inverse().inverse(), edit);
+    }
+
+    #[test]
+    fn groups_quick_edits() {
+        let mut history = History::default();
+        history.record(insert(0, "a"));
+        history.record(insert(1, "b"));
+        history.close_group();
+        history.record(insert(2, "c"));
+
+        assert_eq!(history.current_state(), 2);
+        assert_eq!(
+            history.undo_stack[0].edits,
+            [insert(0, "a"), insert(1, "b")]
+        );
+        assert!(history.undo_stack[0].created_at.is_some());
+    }
+
+    #[test]
+    fn undoes_and_redoes() {
+        let mut history = History::default();
+        history.record(insert(0, "a"));
+        history.close_group();
+        history.record(insert(1, "b"));
+
+        assert_eq!(history.undo().unwrap().edits, [insert(1, "b")]);
+        assert_eq!(history.undo().unwrap().edits, [insert(0, "a")]);
+        assert!(history.undo().is_none());
+        assert_eq!(history.transactions().count(), 2);
+
+        assert_eq!(history.redo().unwrap().edits, [insert(0, "a")]);
+        assert_eq!(history.current_state(), 1);
+    }
+
+    #[test]
+    fn edits_discard_undone_transactions() {
+        let mut history = History::default();
+        history.record(insert(0, "a"));
+        history.undo();
+        history.record(insert(0, "b"));
+
+        // The edit after undoing starts a new step instead of joining the undone one.
+        assert!(history.redo().is_none());
+        assert_eq!(history.undo().unwrap().edits, [insert(0, "b")]);
+    }
+}
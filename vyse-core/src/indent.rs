@@ -0,0 +1,159 @@
+use std::fmt::{self, Display};
+
+/// The number of lines looked at to detect the indentation of a file.
+const DETECTION_LINES: usize = 1000;
+/// The widest indentation considered when detecting the indent width.
+const MAX_INDENT_WIDTH: usize = 8;
+/// The indent width of files that are not indented yet.
+const DEFAULT_INDENT_WIDTH: usize = 4;
+
+/// How a buffer is indented, used when pressing Tab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indentation {
+    Tabs,
+    Spaces(usize),
+}
+
+impl Default for Indentation {
+    fn default() -> Self {
+        Self::Spaces(DEFAULT_INDENT_WIDTH)
+    }
+}
+
+impl Display for Indentation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tabs => write!(f, "tabs"),
+            Self::Spaces(width) => write!(f, "spaces: {width}"),
+        }
+    }
+}
+
+impl Indentation {
+    /// Detects the indentation from the first lines of a file, `None` if none of them are
+    /// indented.
+    ///
+    /// The indent width is the most common increase in indentation between consecutive lines.
+    pub fn detect<'a>(lines: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        let mut increases = [0usize; MAX_INDENT_WIDTH + 1];
+        let mut previous_spaces = 0;
+
+        for line in lines.take(DETECTION_LINES) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if line.starts_with('\t') {
+                tab_lines += 1;
+                continue;
+            }
+
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            if spaces > 0 {
+                space_lines += 1;
+            }
+
+            if let Some(increase) = spaces.checked_sub(previous_spaces) {
+                if (1..=MAX_INDENT_WIDTH).contains(&increase) {
+                    increases[increase] += 1;
+                }
+            }
+            previous_spaces = spaces;
+        }
+
+        if tab_lines == 0 && space_lines == 0 {
+            return None;
+        }
+
+        if tab_lines > space_lines {
+            return Some(Self::Tabs);
+        }
+
+        // Prefer the smaller width on ties, e.g. when every block is nested twice.
+        let width = (1..=MAX_INDENT_WIDTH)
+            .rev()
+            .max_by_key(|width| increases[*width])
+            .filter(|width| increases[*width] > 0)?;
+
+        Some(Self::Spaces(width))
+    }
+
+    /// Returns the text inserted when pressing Tab at the given display column, which for spaces
+    /// reaches the next multiple of the indent width.
+    pub fn text_at(self, display_column: usize) -> String {
+        match self {
+            Self::Tabs => "\t".to_string(),
+            Self::Spaces(width) => " ".repeat(width - display_column % width),
+        }
+    }
+
+    /// Returns the length of one level of indentation at the start of a line, which is removed
+    /// when unindenting it: a tab or up to an indent width of spaces.
+    pub fn level_len(self, line: &str) -> usize {
+        if line.starts_with('\t') {
+            return 1;
+        }
+
+        // Lines indented with spaces in a file indented with tabs use the default width.
+        let width = match self {
+            Self::Spaces(width) => width,
+            Self::Tabs => DEFAULT_INDENT_WIDTH,
+        };
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        spaces.min(width)
+    }
+}
+
+/// Returns the whitespace at the start of a line.
+pub fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_indentation() {
+        let lines = ["fn a() {", "  if x {", "    y();", "", "  }", "}"];
+        assert_eq!(
+            Indentation::detect(lines.into_iter()),
+            Some(Indentation::Spaces(2))
+        );
+
+        let lines = ["a", "\tb", "\tc", "    d"];
+        assert_eq!(
+            Indentation::detect(lines.into_iter()),
+            Some(Indentation::Tabs)
+        );
+
+        assert_eq!(Indentation::detect(["a", "", "b"].into_iter()), None);
+    }
+
+    #[test]
+    fn prefers_smaller_widths_on_ties() {
+        let lines = ["a", "  b", "c", "    d"];
+        assert_eq!(
+            Indentation::detect(lines.into_iter()),
+            Some(Indentation::Spaces(2))
+        );
+    }
+
+    #[test]
+    fn indents_to_the_next_level() {
+        assert_eq!(Indentation::Spaces(4).text_at(1), "   ");
+        assert_eq!(Indentation::Spaces(4).text_at(4), "    ");
+        assert_eq!(Indentation::Tabs.text_at(3), "\t");
+    }
+
+    #[test]
+    fn measures_one_level() {
+        assert_eq!(Indentation::Spaces(4).level_len("      x"), 4);
+        assert_eq!(Indentation::Spaces(4).level_len("  x"), 2);
+        assert_eq!(Indentation::Spaces(4).level_len("\t  x"), 1);
+        assert_eq!(Indentation::Tabs.level_len("      x"), DEFAULT_INDENT_WIDTH);
+        assert_eq!(leading_whitespace(" \t x "), " \t ");
+    }
+}
@@ -0,0 +1,45 @@
+//! The editing engine of vyse: buffers, their history and the commands run on them, without any
+//! dependency on the terminal.
+
+#![warn(clippy::all, clippy::pedantic)]
+// The library is only used by the vyse binary, which does not need every public function to be
+// documented and annotated like a published API.
+#![allow(
+    clippy::must_use_candidate,
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::module_name_repetitions
+)]
+
+pub mod bidi;
+pub mod brackets;
+pub mod buffer;
+pub mod cancellation;
+pub mod change_list;
+pub mod checksum;
+pub mod command;
+pub mod continuation;
+pub mod datetime;
+pub mod encoding;
+pub mod expression;
+pub mod fold;
+pub mod header;
+pub mod hex;
+pub mod highlight;
+pub mod history;
+pub mod indent;
+pub mod line;
+pub mod line_ending;
+pub mod lock;
+pub mod markdown;
+pub mod number;
+pub mod paths;
+pub mod persistence;
+pub mod random;
+pub mod rope;
+pub mod scope;
+pub mod section;
+pub mod snippet;
+pub mod template;
+pub mod transform;
+pub mod unicode;
@@ -0,0 +1,523 @@
+use std::{borrow::Cow, ops::Deref};
+
+use crate::{
+    bidi::{self, Reordering},
+    highlight::{Span, Style},
+    unicode::{split_graphemes, Grapheme},
+};
+
+/// The number of cells between two tab stops.
+pub const TAB_WIDTH: usize = 4;
+
+/// The symbol a tab is shown as when whitespace is visible, followed by spaces up to the next
+/// tab stop.
+const TAB_SYMBOL: char = '→';
+/// The symbol trailing spaces are shown as when whitespace is visible.
+const TRAILING_SPACE_SYMBOL: char = '·';
+/// The symbol shown after the end of each line when newlines are visible.
+const NEWLINE_SYMBOL: char = '¬';
+
+/// Which kinds of whitespace are shown as symbols.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VisibleWhitespace {
+    pub tabs_and_trailing: bool,
+    pub newlines: bool,
+}
+
+/// The visible part of a line and whether text is hidden beyond either edge of the view, which
+/// is shown by a `<` or `>` marker in place of the first or last cell.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TruncatedLine<'a> {
+    /// The text between the markers.
+    pub text: Cow<'a, str>,
+    pub hidden_before: bool,
+    pub hidden_after: bool,
+    /// The highlighted byte ranges of the text.
+    pub styles: Vec<Span>,
+}
+
+/// The positions, counted from the left, of the graphemes shown in a range of cells and the
+/// number of cells to fill with spaces before and after them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct VisibleRange {
+    first: usize,
+    last: usize,
+    padding_before: usize,
+    padding_after: usize,
+}
+
+/// A line of text indexed by grapheme clusters, which is how columns are counted.
+#[derive(Debug, Clone)]
+pub struct Line<'a> {
+    text: Cow<'a, str>,
+    graphemes: Vec<Grapheme>,
+    /// The order the graphemes are shown in if the line contains right-to-left text.
+    reordering: Option<Reordering>,
+}
+
+impl<'a> Line<'a> {
+    /// Splits the text of a line into its grapheme clusters, expanding tabs to the next tab stop.
+    pub fn new<S: Into<Cow<'a, str>>>(text: S) -> Self {
+        let text = text.into();
+        let mut graphemes = split_graphemes(&text);
+
+        let mut cell = 0;
+        for grapheme in &mut graphemes {
+            let grapheme_text = &text[grapheme.start..grapheme.end];
+            if grapheme_text == "\t" {
+                grapheme.width = TAB_WIDTH - cell % TAB_WIDTH;
+            } else if let Some(notation) = control_notation(grapheme_text) {
+                grapheme.width = notation.len();
+            }
+            cell += grapheme.width;
+        }
+
+        let reordering = bidi::reorder(&text, &graphemes);
+        Self {
+            text,
+            graphemes,
+            reordering,
+        }
+    }
+
+    /// Returns the number of grapheme clusters in the line.
+    pub fn grapheme_count(&self) -> usize {
+        self.graphemes.len()
+    }
+
+    /// Returns the byte index at which the grapheme at the given column starts, or the length of
+    /// the line past its end.
+    pub fn byte_index(&self, col: usize) -> usize {
+        self.graphemes
+            .get(col)
+            .map_or(self.text.len(), |grapheme| grapheme.start)
+    }
+
+    /// Returns the column of the grapheme cluster containing the given byte index, counting a
+    /// byte index inside a cluster as being past it.
+    pub fn grapheme_index(&self, byte_index: usize) -> usize {
+        self.graphemes
+            .partition_point(|grapheme| grapheme.start < byte_index)
+    }
+
+    /// Returns the number of cells the line takes up on screen.
+    pub fn width(&self) -> usize {
+        self.width_before(self.graphemes.len())
+    }
+
+    /// Returns the number of cells taken up by the graphemes before the given column.
+    pub fn width_before(&self, col: usize) -> usize {
+        self.graphemes
+            .iter()
+            .take(col)
+            .map(|grapheme| grapheme.width)
+            .sum()
+    }
+
+    /// Returns the cell at which the grapheme at the given column is shown, which differs from
+    /// the width before it in right-to-left text.
+    pub fn display_column(&self, col: usize) -> usize {
+        match &self.reordering {
+            Some(reordering) if col < self.graphemes.len() => reordering.order
+                [..reordering.positions[col]]
+                .iter()
+                .map(|col| self.graphemes[*col].width)
+                .sum(),
+            _ => self.width_before(col),
+        }
+    }
+
+    /// Returns the number of cells the grapheme at the given column takes up, zero past the end
+    /// of the line.
+    pub fn grapheme_width(&self, col: usize) -> usize {
+        self.graphemes.get(col).map_or(0, |grapheme| grapheme.width)
+    }
+
+    /// Returns the column of the grapheme shown at the given cell, or the grapheme count past the
+    /// end of the line.
+    pub fn column_at_width(&self, width: usize) -> usize {
+        let mut end = 0;
+
+        for position in 0..self.graphemes.len() {
+            let col = self.get_column_at_position(position);
+            end += self.graphemes[col].width;
+            if end > width {
+                return col;
+            }
+        }
+
+        self.graphemes.len()
+    }
+
+    /// Returns the column of the grapheme shown at a position counted from the left.
+    fn get_column_at_position(&self, position: usize) -> usize {
+        self.reordering
+            .as_ref()
+            .map_or(position, |reordering| reordering.order[position])
+    }
+
+    /// Returns the text a grapheme is shown as: brackets in right-to-left text are mirrored and
+    /// control characters replaced with their notation, tabs are left to the caller.
+    fn get_shown_text(&self, col: usize) -> Cow<'_, str> {
+        let grapheme = &self.graphemes[col];
+        let text = &self.text[grapheme.start..grapheme.end];
+
+        let is_right_to_left = self
+            .reordering
+            .as_ref()
+            .is_some_and(|reordering| reordering.right_to_left[col]);
+        if let Some(mirrored) = bidi::mirror(text).filter(|_| is_right_to_left) {
+            return Cow::Owned(mirrored.to_string());
+        }
+
+        control_notation(text).map_or(Cow::Borrowed(text), Cow::Owned)
+    }
+
+    /// Returns the text shown in the given range of cells. Tabs and wide characters cut off at
+    /// either edge are replaced with spaces and control characters with their notation,
+    /// otherwise the text is borrowed from the buffer if possible.
+    pub fn into_visible(self, start: usize, width: usize) -> Cow<'a, str> {
+        let visible = self.get_visible_range(start, width);
+        let has_replacements = self.reordering.is_some()
+            || self.graphemes[visible.first..visible.last]
+                .iter()
+                .any(|grapheme| {
+                    let grapheme = &self.text[grapheme.start..grapheme.end];
+                    grapheme == "\t" || control_notation(grapheme).is_some()
+                });
+
+        if visible.padding_before == 0 && visible.padding_after == 0 && !has_replacements {
+            return self.into_slice(visible.first, visible.last);
+        }
+
+        let mut text = " ".repeat(visible.padding_before);
+        for position in visible.first..visible.last {
+            let col = self.get_column_at_position(position);
+            match self.get_shown_text(col).as_ref() {
+                "\t" => text.push_str(&" ".repeat(self.graphemes[col].width)),
+                shown => text.push_str(shown),
+            }
+        }
+        text.push_str(&" ".repeat(visible.padding_after));
+
+        Cow::Owned(text)
+    }
+
+    /// Returns the text shown in the given range of cells, leaving room for markers where text is
+    /// hidden beyond the edges of the range. The highlighted spans of the line are moved to where
+    /// they end up in the visible text, control characters are highlighted and whitespace is
+    /// replaced with symbols if requested.
+    pub fn into_truncated(
+        self,
+        start: usize,
+        width: usize,
+        spans: &[Span],
+        whitespace: VisibleWhitespace,
+    ) -> TruncatedLine<'a> {
+        let line_width = self.width();
+        let hidden_before = start > 0 && line_width > 0;
+        let hidden_after = line_width > start.saturating_add(width);
+
+        let start = start + usize::from(hidden_before);
+        let width = width.saturating_sub(usize::from(hidden_before) + usize::from(hidden_after));
+
+        let has_control_chars = self.text.chars().any(is_control);
+        if spans.is_empty()
+            && whitespace == VisibleWhitespace::default()
+            && !has_control_chars
+            && self.reordering.is_none()
+        {
+            return TruncatedLine {
+                text: self.into_visible(start, width),
+                hidden_before,
+                hidden_after,
+                styles: Vec::new(),
+            };
+        }
+
+        let visible = self.get_visible_range(start, width);
+        let trailing_start = self.text.trim_end_matches([' ', '\t']).len();
+        let mut text = " ".repeat(visible.padding_before);
+        let mut styles: Vec<Span> = Vec::new();
+
+        for position in visible.first..visible.last {
+            let col = self.get_column_at_position(position);
+            let grapheme = &self.graphemes[col];
+            let offset = text.len();
+            let mut style = spans
+                .iter()
+                .find(|span| (span.start..span.end).contains(&grapheme.start))
+                .map(|span| span.style);
+
+            match &self.text[grapheme.start..grapheme.end] {
+                "\t" if whitespace.tabs_and_trailing => {
+                    text.push(TAB_SYMBOL);
+                    text.push_str(&" ".repeat(grapheme.width.saturating_sub(1)));
+                    style = Some(Style::Whitespace);
+                }
+                "\t" => text.push_str(&" ".repeat(grapheme.width)),
+                " " if whitespace.tabs_and_trailing && grapheme.start >= trailing_start => {
+                    text.push(TRAILING_SPACE_SYMBOL);
+                    style = Some(Style::Whitespace);
+                }
+                grapheme => {
+                    text.push_str(&self.get_shown_text(col));
+                    if control_notation(grapheme).is_some() {
+                        style = Some(Style::Control);
+                    }
+                }
+            }
+
+            if let Some(style) = style {
+                push_span(&mut styles, offset, text.len(), style);
+            }
+        }
+        text.push_str(&" ".repeat(visible.padding_after));
+
+        // The newline symbol takes the cell after the line, if that cell is in view.
+        if whitespace.newlines && !hidden_after && line_width.saturating_sub(start) < width {
+            let offset = text.len();
+            text.push(NEWLINE_SYMBOL);
+            push_span(&mut styles, offset, text.len(), Style::Whitespace);
+        }
+
+        TruncatedLine {
+            text: Cow::Owned(text),
+            hidden_before,
+            hidden_after,
+            styles,
+        }
+    }
+
+    /// Finds the graphemes shown in the given range of cells and the padding needed in place of
+    /// graphemes that are cut off.
+    fn get_visible_range(&self, start: usize, width: usize) -> VisibleRange {
+        let end = start.saturating_add(width);
+        let mut cell = 0;
+        let mut first = self.graphemes.len();
+        let mut last = self.graphemes.len();
+        let mut padding_before = 0;
+        let mut padding_after = 0;
+
+        for position in 0..self.graphemes.len() {
+            let grapheme = &self.graphemes[self.get_column_at_position(position)];
+            let next_cell = cell + grapheme.width;
+
+            if first == self.graphemes.len() && next_cell > start {
+                first = position;
+                if cell < start {
+                    padding_before = next_cell.min(end) - start;
+                    first += 1;
+                }
+            }
+
+            if next_cell > end {
+                last = position;
+                if cell < end && cell >= start {
+                    padding_after = end - cell;
+                }
+                break;
+            }
+
+            cell = next_cell;
+        }
+
+        VisibleRange {
+            first,
+            last: last.max(first),
+            padding_before,
+            padding_after,
+        }
+    }
+
+    /// Returns the text between two columns, borrowing from the buffer if possible.
+    pub fn into_slice(self, start_col: usize, end_col: usize) -> Cow<'a, str> {
+        let start = self.byte_index(start_col);
+        let end = self.byte_index(end_col).max(start);
+
+        match self.text {
+            Cow::Borrowed(text) => Cow::Borrowed(&text[start..end]),
+            Cow::Owned(text) => Cow::Owned(text[start..end].to_string()),
+        }
+    }
+}
+
+impl Deref for Line<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.text
+    }
+}
+
+/// Returns whether a character is a control character other than a tab, which are not shown by
+/// terminals.
+fn is_control(ch: char) -> bool {
+    ch.is_control() && ch != '\t'
+}
+
+/// Returns how a control character is shown: caret notation like `^M` for ASCII control
+/// characters and escapes like `\u{85}` for the others.
+fn control_notation(grapheme: &str) -> Option<String> {
+    let ch = grapheme.chars().next().filter(|ch| is_control(*ch))?;
+
+    match u8::try_from(ch) {
+        Ok(byte @ 0..=0x1F) => Some(format!("^{}", char::from(byte + 0x40))),
+        Ok(0x7F) => Some("^?".to_string()),
+        _ => Some(format!("\\u{{{:x}}}", u32::from(ch))),
+    }
+}
+
+/// Adds a highlighted byte range, extending the previous span if it has the same style and ends
+/// where the range starts.
+fn push_span(styles: &mut Vec<Span>, start: usize, end: usize, style: Style) {
+    match styles.last_mut() {
+        Some(last) if last.end == start && last.style == style => last.end = end,
+        _ => styles.push(Span { start, end, style }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexes_grapheme_clusters() {
+        let line = Line::new("e\u{301}x");
+        assert_eq!(line.grapheme_count(), 2);
+        assert_eq!(line.byte_index(1), 3);
+        assert_eq!(line.byte_index(5), 4);
+        assert_eq!(line.grapheme_index(1), 1);
+        assert_eq!(line.grapheme_index(3), 1);
+        assert_eq!(line.grapheme_index(4), 2);
+        assert_eq!(line.into_slice(1, 2), "x");
+    }
+
+    #[test]
+    fn measures_wide_characters() {
+        let line = Line::new("a界b");
+        assert_eq!(line.width(), 4);
+        assert_eq!(line.width_before(2), 3);
+        assert_eq!(line.grapheme_width(1), 2);
+        assert_eq!(line.column_at_width(2), 1);
+        assert_eq!(line.column_at_width(3), 2);
+        assert_eq!(line.column_at_width(4), 3);
+    }
+
+    #[test]
+    fn pads_cut_off_wide_characters() {
+        assert_eq!(Line::new("a界b").into_visible(0, 2), "a ");
+        assert_eq!(Line::new("a界b").into_visible(2, 2), " b");
+        assert!(matches!(
+            Line::new("a界b").into_visible(1, 3),
+            Cow::Borrowed("界b")
+        ));
+    }
+
+    #[test]
+    fn expands_tabs_to_tab_stops() {
+        let line = Line::new("a\tb\t");
+        assert_eq!(line.grapheme_width(1), 3);
+        assert_eq!(line.grapheme_width(3), 3);
+        assert_eq!(line.width_before(2), 4);
+        assert_eq!(line.into_visible(0, 8), "a   b   ");
+        assert_eq!(Line::new("\tx").into_visible(2, 3), "  x");
+    }
+
+    fn truncate(text: &str, start: usize, width: usize) -> TruncatedLine<'_> {
+        Line::new(text).into_truncated(start, width, &[], VisibleWhitespace::default())
+    }
+
+    #[test]
+    fn leaves_room_for_truncation_markers() {
+        let line = truncate("abcdef", 0, 4);
+        assert_eq!(
+            (line.text.as_ref(), line.hidden_before, line.hidden_after),
+            ("abc", false, true)
+        );
+
+        let line = truncate("abcdef", 2, 4);
+        assert_eq!(
+            (line.text.as_ref(), line.hidden_before, line.hidden_after),
+            ("def", true, false)
+        );
+
+        let line = truncate("abcdef", 0, 6);
+        assert_eq!(
+            (line.text.as_ref(), line.hidden_before, line.hidden_after),
+            ("abcdef", false, false)
+        );
+
+        let line = truncate("", 2, 4);
+        assert!(!line.hidden_before && !line.hidden_after);
+    }
+
+    #[test]
+    fn shows_whitespace_as_symbols() {
+        let whitespace = VisibleWhitespace {
+            tabs_and_trailing: true,
+            newlines: true,
+        };
+        let line = Line::new("a\tb  ").into_truncated(0, 20, &[], whitespace);
+        assert_eq!(line.text, "a→  b··¬");
+        assert_eq!(
+            line.styles,
+            [
+                Span {
+                    start: 1,
+                    end: 6,
+                    style: Style::Whitespace
+                },
+                Span {
+                    start: 7,
+                    end: 13,
+                    style: Style::Whitespace
+                },
+            ]
+        );
+
+        // Spaces between words are not trailing and the newline is out of view.
+        let line = Line::new("a b").into_truncated(0, 3, &[], whitespace);
+        assert_eq!(line.text, "a b");
+    }
+
+    #[test]
+    fn shows_control_characters_in_notation() {
+        let line = Line::new("a\u{1b}b\u{7f}\u{85}");
+        assert_eq!(line.width(), 12);
+        assert_eq!(line.clone().into_visible(0, 12), "a^[b^?\\u{85}");
+
+        let line = line.into_truncated(0, 12, &[], VisibleWhitespace::default());
+        assert_eq!(
+            line.styles,
+            [
+                Span {
+                    start: 1,
+                    end: 3,
+                    style: Style::Control
+                },
+                Span {
+                    start: 4,
+                    end: 12,
+                    style: Style::Control
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn places_right_to_left_text() {
+        let line = Line::new("x אב y");
+        assert_eq!(line.display_column(2), 3);
+        assert_eq!(line.display_column(3), 2);
+        assert_eq!(line.display_column(6), 6);
+        assert_eq!(line.column_at_width(2), 3);
+        assert_eq!(line.into_visible(0, 6), "x בא y");
+    }
+
+    #[test]
+    fn mirrors_brackets_in_right_to_left_text() {
+        assert_eq!(Line::new("א(ב").into_visible(0, 3), "ב)א");
+        assert_eq!(Line::new("(א)").into_visible(0, 3), "(א)");
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+};
+
+/// The line ending a buffer is written with, lines are always separated by `\n` in memory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lf => write!(f, "LF"),
+            Self::Crlf => write!(f, "CRLF"),
+        }
+    }
+}
+
+impl LineEnding {
+    /// Parses the value of the `lineending` option.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "lf" => Some(Self::Lf),
+            "crlf" => Some(Self::Crlf),
+            _ => None,
+        }
+    }
+
+    /// Detects the line ending used by the majority of lines in a file.
+    pub fn detect(content: &str) -> Self {
+        let newlines = content.matches('\n').count();
+        let crlfs = content.matches("\r\n").count();
+
+        if crlfs > newlines - crlfs {
+            Self::Crlf
+        } else {
+            Self::Lf
+        }
+    }
+
+    /// Returns whether some lines of a file end with CRLF and others with LF only.
+    pub fn is_mixed(content: &str) -> bool {
+        let newlines = content.matches('\n').count();
+        let crlfs = content.matches("\r\n").count();
+
+        crlfs > 0 && crlfs < newlines
+    }
+
+    /// Replaces the `\n` separating lines in memory with this line ending.
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Self::Lf => text.into(),
+            Self::Crlf => text.replace('\n', self.as_str()).into(),
+        }
+    }
+
+    /// Returns the characters ending a line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_the_majority_line_ending() {
+        assert_eq!(LineEnding::detect(""), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::Lf);
+        assert!(LineEnding::is_mixed("a\r\nb\n"));
+        assert!(!LineEnding::is_mixed("a\r\nb\r\n"));
+        assert!(!LineEnding::is_mixed("a\nb"));
+    }
+
+    #[test]
+    fn applies_line_endings() {
+        assert_eq!(LineEnding::Crlf.apply("a\nb\n"), "a\r\nb\r\n");
+        assert!(matches!(
+            LineEnding::Lf.apply("a\nb"),
+            Cow::Borrowed("a\nb")
+        ));
+        assert_eq!(LineEnding::parse("crlf"), Some(LineEnding::Crlf));
+        assert_eq!(LineEnding::parse("CRLF"), None);
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::persistence;
+
+/// Whether locks left behind by crashed processes are recognized, which needs a way to check that
+/// a process is still running.
+pub const DETECTS_STALE_LOCKS: bool = cfg!(unix);
+
+/// An advisory lock marking a file as being edited by this process, released when dropped.
+///
+/// The lock file lists the ids of all processes holding the lock, one per line, so forcing a lock
+/// shares it instead of taking it away from the other process.
+#[derive(Debug)]
+pub struct FileLock {
+    lock_path: PathBuf,
+}
+
+/// Why a file could not be locked.
+#[derive(Debug)]
+pub enum LockError {
+    /// Another running vyse process holds the lock.
+    HeldBy(u32),
+    Io(io::Error),
+}
+
+impl FileLock {
+    /// Locks the file at the given path, unless another running process already holds the lock.
+    pub fn acquire(path: &Path) -> Result<Self, LockError> {
+        Self::acquire_at(get_lock_path(path).map_err(LockError::Io)?)
+    }
+
+    /// Locks the file at the given path, sharing the lock with any other process.
+    pub fn force(path: &Path) -> io::Result<Self> {
+        Self::force_at(get_lock_path(path)?)
+    }
+
+    fn acquire_at(lock_path: PathBuf) -> Result<Self, LockError> {
+        // Other buffers of this process holding the lock are not a conflict.
+        let owners = read_owners(&lock_path).map_err(LockError::Io)?;
+        if let Some(pid) = owners
+            .into_iter()
+            .find(|pid| *pid != process::id() && is_process_alive(*pid))
+        {
+            return Err(LockError::HeldBy(pid));
+        }
+
+        Self::force_at(lock_path).map_err(LockError::Io)
+    }
+
+    fn force_at(lock_path: PathBuf) -> io::Result<Self> {
+        // Owners that are gone, e.g. after a crash, are dropped from the list.
+        let mut owners: Vec<u32> = read_owners(&lock_path)?
+            .into_iter()
+            .filter(|pid| *pid == process::id() || is_process_alive(*pid))
+            .collect();
+        owners.push(process::id());
+        write_owners(&lock_path, &owners)?;
+
+        Ok(Self { lock_path })
+    }
+}
+
+impl Drop for FileLock {
+    /// Removes this lock from the owners, deleting the lock file once nobody else holds it.
+    fn drop(&mut self) {
+        let Ok(mut owners) = read_owners(&self.lock_path) else {
+            return;
+        };
+
+        if let Some(idx) = owners.iter().position(|pid| *pid == process::id()) {
+            owners.remove(idx);
+        }
+
+        let _ = if owners.is_empty() {
+            fs::remove_file(&self.lock_path)
+        } else {
+            write_owners(&self.lock_path, &owners)
+        };
+    }
+}
+
+/// Reads the ids of the processes holding a lock, one per line.
+fn read_owners(lock_path: &Path) -> io::Result<Vec<u32>> {
+    match fs::read_to_string(lock_path) {
+        Ok(content) => Ok(content
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+fn write_owners(lock_path: &Path, owners: &[u32]) -> io::Result<()> {
+    let content = owners.iter().fold(String::new(), |mut content, pid| {
+        let _ = writeln!(content, "{pid}");
+        content
+    });
+
+    fs::write(lock_path, content)
+}
+
+/// Returns the lock file for the given path, creating its directory if needed.
+fn get_lock_path(path: &Path) -> io::Result<PathBuf> {
+    let lock_dir = persistence::get_cache_dir()
+        .ok_or(io::ErrorKind::NotFound)?
+        .join("locks");
+    fs::create_dir_all(&lock_dir)?;
+
+    Ok(lock_dir.join(persistence::get_path_key(path)))
+}
+
+/// Returns whether a process with the given id is running.
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    extern "C" {
+        fn kill(pid: i32, signal: i32) -> i32;
+    }
+
+    /// The error of signalling a process owned by another user.
+    const EPERM: i32 = 1;
+
+    let Some(pid) = i32::try_from(pid).ok().filter(|pid| *pid > 0) else {
+        return false;
+    };
+
+    // SAFETY: Signal 0 is never delivered, it only checks whether the process exists.
+    let result = unsafe { kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(EPERM)
+}
+
+/// Returns whether a process with the given id is running, which cannot be checked here, so any
+/// lock is assumed to be held. See [`DETECTS_STALE_LOCKS`].
+#[cfg(not(unix))]
+fn is_process_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{env, os::unix::process::parent_id, process::Command};
+
+    use super::*;
+
+    fn lock_path(name: &str) -> PathBuf {
+        let lock_path = env::temp_dir().join(format!("vyse-lock-{}-{name}", process::id()));
+        let _ = fs::remove_file(&lock_path);
+        lock_path
+    }
+
+    /// Returns the id of a process that has exited.
+    fn get_dead_pid() -> u32 {
+        let mut child = Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        child.id()
+    }
+
+    #[test]
+    fn acquires_and_releases() {
+        let lock_path = lock_path("release");
+
+        let lock = FileLock::acquire_at(lock_path.clone()).unwrap();
+        assert_eq!(read_owners(&lock_path).unwrap(), [process::id()]);
+
+        drop(lock);
+        assert!(!lock_path.exists());
+    }
+
+    #[test]
+    fn shares_lock_between_buffers_of_one_process() {
+        let lock_path = lock_path("shared");
+
+        let first = FileLock::acquire_at(lock_path.clone()).unwrap();
+        let second = FileLock::acquire_at(lock_path.clone()).unwrap();
+        drop(first);
+        assert_eq!(read_owners(&lock_path).unwrap(), [process::id()]);
+
+        drop(second);
+        assert!(!lock_path.exists());
+    }
+
+    #[test]
+    fn reports_running_owner() {
+        let lock_path = lock_path("running");
+        write_owners(&lock_path, &[parent_id()]).unwrap();
+
+        assert!(matches!(
+            FileLock::acquire_at(lock_path.clone()),
+            Err(LockError::HeldBy(pid)) if pid == parent_id()
+        ));
+        fs::remove_file(&lock_path).unwrap();
+    }
+
+    #[test]
+    fn takes_over_stale_lock() {
+        let lock_path = lock_path("stale");
+        write_owners(&lock_path, &[get_dead_pid()]).unwrap();
+
+        let lock = FileLock::acquire_at(lock_path.clone()).unwrap();
+        assert_eq!(read_owners(&lock_path).unwrap(), [process::id()]);
+
+        drop(lock);
+        assert!(!lock_path.exists());
+    }
+
+    #[test]
+    fn forcing_keeps_other_owner() {
+        let lock_path = lock_path("forced");
+        write_owners(&lock_path, &[parent_id()]).unwrap();
+
+        let lock = FileLock::force_at(lock_path.clone()).unwrap();
+        assert_eq!(
+            read_owners(&lock_path).unwrap(),
+            [parent_id(), process::id()]
+        );
+
+        drop(lock);
+        assert_eq!(read_owners(&lock_path).unwrap(), [parent_id()]);
+        assert!(matches!(
+            FileLock::acquire_at(lock_path.clone()),
+            Err(LockError::HeldBy(_))
+        ));
+        fs::remove_file(&lock_path).unwrap();
+    }
+}
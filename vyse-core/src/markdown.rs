@@ -0,0 +1,328 @@
+use std::ops::Range;
+
+use crate::{indent, line::Line};
+
+/// The deepest heading level Markdown has.
+const MAX_HEADING_LEVEL: usize = 6;
+/// The narrowest a column of a table is, which fits the `---` of its delimiter row.
+const MIN_COLUMN_WIDTH: usize = 3;
+
+/// How the cells of a table column are aligned, set by colons in the delimiter row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    Default,
+    Left,
+    Center,
+    Right,
+}
+
+/// Checks the task of a list item or unchecks it, a list item without a checkbox gets an
+/// unchecked one. Returns `None` if the line is not a list item.
+pub fn toggle_checkbox(line: &str) -> Option<String> {
+    let indent = indent::leading_whitespace(line);
+    let marker_len = get_list_marker_len(&line[indent.len()..])?;
+    let (before, rest) = line.split_at(indent.len() + marker_len);
+
+    let toggled = if let Some(text) = rest.strip_prefix("[ ]") {
+        format!("[x]{text}")
+    } else if let Some(text) = rest.strip_prefix("[x]").or(rest.strip_prefix("[X]")) {
+        format!("[ ]{text}")
+    } else {
+        format!("[ ] {rest}")
+    };
+
+    Some(format!("{before}{toggled}"))
+}
+
+/// Adds levels to a heading, a negative delta promotes it to a higher level. A line that is not a
+/// heading becomes one when demoted, a top level heading becomes a plain line when promoted.
+/// Returns `None` if the level does not change.
+pub fn change_heading_level(line: &str, delta: isize) -> Option<String> {
+    let level = get_heading_level(line);
+    let new_level = level.saturating_add_signed(delta).min(MAX_HEADING_LEVEL);
+    if new_level == level {
+        return None;
+    }
+
+    let text = line[level..].trim_start();
+    Some(if new_level == 0 {
+        text.to_string()
+    } else {
+        format!("{} {text}", "#".repeat(new_level))
+    })
+}
+
+/// Returns the target of the link `[text](target)` around a byte index of a line, or of the first
+/// link on the line if there is none around it.
+pub fn find_link(line: &str, byte_index: usize) -> Option<&str> {
+    let mut links = Vec::new();
+    let mut search_start = 0;
+
+    while let Some(idx) = line[search_start..].find("](") {
+        let target_start = search_start + idx + 2;
+        let Some(target_len) = line[target_start..].find(')') else {
+            break;
+        };
+        let Some(start) = line[..search_start + idx].rfind('[') else {
+            search_start = target_start;
+            continue;
+        };
+
+        let end = target_start + target_len + 1;
+        links.push((start..end, &line[target_start..end - 1]));
+        search_start = end;
+    }
+
+    let (_, target) = links
+        .iter()
+        .find(|(range, _)| range.contains(&byte_index))
+        .or(links.first())?;
+
+    // The target may be followed by a title and be wrapped in angle brackets.
+    let target = target.split_whitespace().next()?;
+    Some(
+        target
+            .strip_prefix('<')
+            .and_then(|target| target.strip_suffix('>'))
+            .unwrap_or(target),
+    )
+}
+
+/// Returns whether a line is a row of a pipe table, i.e. starts with `|`.
+pub fn is_table_row(line: &str) -> bool {
+    line.trim_start().starts_with('|')
+}
+
+/// Pads the cells of a pipe table so its columns line up, given its rows and the row and byte
+/// index of the cursor. Returns the formatted rows and the byte index of the cursor in its row,
+/// which stays in the same cell, or `None` if the second row is not a delimiter row.
+pub fn format_table(rows: &[&str], cursor: (usize, usize)) -> Option<(Vec<String>, usize)> {
+    let indent = indent::leading_whitespace(rows.first()?);
+    let cells: Vec<Vec<Range<usize>>> = rows.iter().map(|row| split_cells(row)).collect();
+    let alignments = cells
+        .get(1)?
+        .iter()
+        .map(|range| get_alignment(rows[1][range.clone()].trim()))
+        .collect::<Option<Vec<_>>>()?;
+
+    let columns = cells.iter().map(Vec::len).max()?;
+    let mut widths = vec![MIN_COLUMN_WIDTH; columns];
+    // The delimiter row is rewritten to the width of the other rows.
+    for (_, (row, ranges)) in rows
+        .iter()
+        .zip(&cells)
+        .enumerate()
+        .filter(|(idx, _)| *idx != 1)
+    {
+        for (width, range) in widths.iter_mut().zip(ranges) {
+            *width = (*width).max(Line::new(row[range.clone()].trim()).width());
+        }
+    }
+
+    // The cell of the cursor and how far into its text the cursor is.
+    let (cursor_row, cursor_byte) = cursor;
+    let cursor_ranges = cells.get(cursor_row)?;
+    let cursor_column = cursor_ranges
+        .iter()
+        .position(|range| cursor_byte <= range.end)
+        .unwrap_or(cursor_ranges.len().saturating_sub(1));
+    let cursor_offset = cursor_ranges.get(cursor_column).map_or(0, |range| {
+        let cell = &rows[cursor_row][range.clone()];
+        let text_start = range.start + cell.len() - cell.trim_start().len();
+        cursor_byte.saturating_sub(text_start)
+    });
+
+    let mut new_cursor_byte = 0;
+    let formatted = rows
+        .iter()
+        .zip(&cells)
+        .enumerate()
+        .map(|(idx, (row, ranges))| {
+            let mut line = format!("{indent}|");
+            for (column, width) in widths.iter().enumerate() {
+                let alignment = alignments
+                    .get(column)
+                    .copied()
+                    .unwrap_or(Alignment::Default);
+                let text = ranges
+                    .get(column)
+                    .map_or("", |range| row[range.clone()].trim());
+                let (cell, padding) = if idx == 1 {
+                    (get_delimiter(alignment, *width), 0)
+                } else {
+                    pad_cell(text, alignment, *width)
+                };
+
+                line.push(' ');
+                if (idx, column) == (cursor_row, cursor_column) {
+                    // The cursor may stay in the space after the text to keep typing a word.
+                    let text_len = if idx == 1 { cell.len() } else { text.len() };
+                    new_cursor_byte = line.len() + padding + cursor_offset.min(text_len + 1);
+                }
+                line.push_str(&cell);
+                line.push_str(" |");
+            }
+            line
+        })
+        .collect();
+
+    Some((formatted, new_cursor_byte))
+}
+
+/// Returns the byte ranges of the cells of a table row between its pipes, including the spaces
+/// around their text. The last cell may lack its closing pipe. Escaped pipes are part of a cell.
+fn split_cells(row: &str) -> Vec<Range<usize>> {
+    let mut pipes: Vec<usize> = row
+        .char_indices()
+        .filter(|(idx, ch)| *ch == '|' && !row[..*idx].ends_with('\\'))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let end = row.trim_end().len();
+    if pipes.last().is_some_and(|last| last + 1 < end) {
+        pipes.push(end);
+    }
+
+    pipes
+        .windows(2)
+        .map(|pipes| pipes[0] + 1..pipes[1])
+        .collect()
+}
+
+/// Returns the alignment set by a cell of the delimiter row, `None` if it is not made of dashes
+/// with optional colons at either end.
+fn get_alignment(cell: &str) -> Option<Alignment> {
+    let left = cell.starts_with(':');
+    let right = cell.len() > 1 && cell.ends_with(':');
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || !dashes.chars().all(|ch| ch == '-') {
+        return None;
+    }
+
+    Some(match (left, right) {
+        (false, false) => Alignment::Default,
+        (true, false) => Alignment::Left,
+        (true, true) => Alignment::Center,
+        (false, true) => Alignment::Right,
+    })
+}
+
+/// Returns the cell of the delimiter row for a column.
+fn get_delimiter(alignment: Alignment, width: usize) -> String {
+    match alignment {
+        Alignment::Default => "-".repeat(width),
+        Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+        Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+        Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+    }
+}
+
+/// Pads the text of a cell to the width of its column, returns it together with the length of
+/// the padding before the text.
+fn pad_cell(text: &str, alignment: Alignment, width: usize) -> (String, usize) {
+    let padding = width.saturating_sub(Line::new(text).width());
+    let before = match alignment {
+        Alignment::Default | Alignment::Left => 0,
+        Alignment::Center => padding / 2,
+        Alignment::Right => padding,
+    };
+
+    (
+        format!(
+            "{}{text}{}",
+            " ".repeat(before),
+            " ".repeat(padding - before)
+        ),
+        before,
+    )
+}
+
+/// Returns the level of a heading, 0 if the line is not one.
+pub fn get_heading_level(line: &str) -> usize {
+    let level = line.len() - line.trim_start_matches('#').len();
+    let is_heading =
+        level <= MAX_HEADING_LEVEL && line[level..].chars().next().is_none_or(char::is_whitespace);
+
+    if is_heading {
+        level
+    } else {
+        0
+    }
+}
+
+/// Returns the length of the marker of a list item including the space after it, i.e. a bullet
+/// `-`, `*` or `+` or a number followed by `.` or `)`.
+fn get_list_marker_len(text: &str) -> Option<usize> {
+    let digits_len = text.len()
+        - text
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    let marker_len = if digits_len > 0 {
+        text[digits_len..]
+            .starts_with(['.', ')'])
+            .then_some(digits_len + 1)?
+    } else {
+        text.starts_with(['-', '*', '+']).then_some(1)?
+    };
+
+    text[marker_len..]
+        .starts_with(' ')
+        .then_some(marker_len + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggles_checkboxes() {
+        assert_eq!(toggle_checkbox("- [ ] task").unwrap(), "- [x] task");
+        assert_eq!(toggle_checkbox("  1. [X] done").unwrap(), "  1. [ ] done");
+        assert_eq!(toggle_checkbox("* item").unwrap(), "* [ ] item");
+        assert_eq!(toggle_checkbox("-item"), None);
+        assert_eq!(toggle_checkbox("text"), None);
+    }
+
+    #[test]
+    fn changes_heading_levels() {
+        assert_eq!(change_heading_level("## Title", 1).unwrap(), "### Title");
+        assert_eq!(change_heading_level("# Title", -1).unwrap(), "Title");
+        assert_eq!(change_heading_level("Title", 1).unwrap(), "# Title");
+        assert_eq!(change_heading_level("###### Title", 1), None);
+        assert_eq!(change_heading_level("Title", -1), None);
+        assert_eq!(get_heading_level("#tag"), 0);
+    }
+
+    #[test]
+    fn finds_links() {
+        let line = "see [a](a.md) and [b](<b.md> \"title\")";
+        assert_eq!(find_link(line, line.find("[b").unwrap()), Some("b.md"));
+        assert_eq!(find_link(line, 0), Some("a.md"));
+        assert_eq!(find_link("no [link] (here)", 0), None);
+    }
+
+    #[test]
+    fn lines_up_table_columns() {
+        let rows = ["| a | bb |", "|:-|-:|", "| ccc | d"];
+        let (formatted, cursor_byte) = format_table(&rows, (0, 6)).unwrap();
+        assert_eq!(
+            formatted,
+            ["| a   |  bb |", "| :-- | --: |", "| ccc |   d |"]
+        );
+        // The cursor stays at the start of `bb`.
+        assert_eq!(cursor_byte, 9);
+    }
+
+    #[test]
+    fn keeps_escaped_pipes_in_cells() {
+        let rows = ["  |a\\|b|", "  |:-:|"];
+        let (formatted, _) = format_table(&rows, (1, 3)).unwrap();
+        assert_eq!(formatted, ["  | a\\|b |", "  | :--: |"]);
+    }
+
+    #[test]
+    fn requires_delimiter_row() {
+        assert_eq!(format_table(&["| a |", "| b |"], (0, 0)), None);
+        assert_eq!(format_table(&["| a |"], (0, 0)), None);
+    }
+}
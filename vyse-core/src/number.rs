@@ -0,0 +1,192 @@
+use std::ops::Range;
+
+/// Returns the byte range of the first decimal number in a line that ends after a byte index,
+/// i.e. the number under the cursor or the next one after it.
+///
+/// Numbers may contain digit separators: underscores between any digits like `1_000_000`, or
+/// commas between groups of three digits like `1,000`. A minus sign belongs to the number unless
+/// it follows a word, where it is more likely a subtraction.
+pub fn find_number(line: &str, byte_index: usize) -> Option<Range<usize>> {
+    let bytes = line.as_bytes();
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if !bytes[idx].is_ascii_digit() {
+            idx += 1;
+            continue;
+        }
+
+        let is_negative =
+            idx > 0 && bytes[idx - 1] == b'-' && (idx < 2 || !is_word_byte(bytes[idx - 2]));
+        let start = if is_negative { idx - 1 } else { idx };
+        let end = get_number_end(bytes, idx);
+        if end > byte_index {
+            return Some(start..end);
+        }
+
+        idx = end;
+    }
+
+    None
+}
+
+/// Adds a delta to a number found by `find_number`, keeping its leading zeros and digit
+/// separators. Returns `None` if the number is too large.
+pub fn increment(number: &str, delta: i64) -> Option<String> {
+    let (is_negative, body) = match number.strip_prefix('-') {
+        Some(body) => (true, body),
+        None => (false, number),
+    };
+    let digits: String = body.chars().filter(char::is_ascii_digit).collect();
+    let magnitude: i128 = digits.parse().ok()?;
+    let value = if is_negative { -magnitude } else { magnitude }.checked_add(i128::from(delta))?;
+
+    let mut new_digits = value.unsigned_abs().to_string();
+    if digits.len() > 1 && digits.starts_with('0') {
+        new_digits = format!("{new_digits:0>width$}", width = digits.len());
+    }
+
+    if let Some(separator) = body.chars().find(|ch| !ch.is_ascii_digit()) {
+        let groups: Vec<usize> = body.split(separator).map(str::len).collect();
+        new_digits = group_digits(&new_digits, separator, &groups);
+    }
+
+    Some(if value < 0 {
+        format!("-{new_digits}")
+    } else {
+        new_digits
+    })
+}
+
+/// Returns where the digits and separators of a number starting at a byte index end.
+fn get_number_end(bytes: &[u8], start: usize) -> usize {
+    let get_digits_end = |from: usize| {
+        (from..bytes.len())
+            .find(|idx| !bytes[*idx].is_ascii_digit())
+            .unwrap_or(bytes.len())
+    };
+
+    let mut end = get_digits_end(start);
+    match bytes.get(end) {
+        Some(b'_') => {
+            while bytes.get(end) == Some(&b'_')
+                && bytes.get(end + 1).is_some_and(u8::is_ascii_digit)
+            {
+                end = get_digits_end(end + 1);
+            }
+        }
+        Some(b',') if end - start <= 3 => {
+            while bytes.get(end) == Some(&b',') {
+                let group_end = get_digits_end(end + 1);
+                if group_end - end - 1 != 3 {
+                    break;
+                }
+                end = group_end;
+            }
+        }
+        _ => (),
+    }
+
+    end
+}
+
+/// Inserts separators into digits like in the groups of the original number: regular groups are
+/// repeated, so `999_999` becomes `1_000_000`, otherwise the separators keep their distance from
+/// the last digit.
+fn group_digits(digits: &str, separator: char, groups: &[usize]) -> String {
+    let Some((first, rest)) = groups.split_first() else {
+        return digits.to_string();
+    };
+    let size = groups.last().copied().unwrap_or_default();
+
+    // The number of digits to the right of each separator.
+    let positions: Vec<usize> = if size > 0 && *first <= size && rest.iter().all(|len| *len == size)
+    {
+        (size..digits.len()).step_by(size).collect()
+    } else {
+        rest.iter()
+            .rev()
+            .scan(0, |pos, len| {
+                *pos += len;
+                Some(*pos)
+            })
+            .take_while(|pos| *pos < digits.len())
+            .collect()
+    };
+
+    let mut grouped = String::with_capacity(digits.len() + positions.len());
+    for (idx, ch) in digits.chars().enumerate() {
+        if idx > 0 && positions.contains(&(digits.len() - idx)) {
+            grouped.push(separator);
+        }
+        grouped.push(ch);
+    }
+
+    grouped
+}
+
+/// Returns whether a byte is part of a word, i.e. an ASCII letter, digit or underscore.
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_number_under_or_after_cursor() {
+        assert_eq!(find_number("x = 12 + 345", 0), Some(4..6));
+        assert_eq!(find_number("x = 12 + 345", 5), Some(4..6));
+        assert_eq!(find_number("x = 12 + 345", 6), Some(9..12));
+        assert_eq!(find_number("x = 12", 6), None);
+    }
+
+    #[test]
+    fn finds_negative_numbers_but_not_subtractions() {
+        assert_eq!(find_number("a = -5", 0), Some(4..6));
+        assert_eq!(find_number("a-5", 0), Some(2..3));
+    }
+
+    #[test]
+    fn finds_separated_numbers() {
+        assert_eq!(find_number("1_000_000", 0), Some(0..9));
+        assert_eq!(find_number("1,000,000", 0), Some(0..9));
+        assert_eq!(find_number("f(1, 2)", 0), Some(2..3));
+        assert_eq!(find_number("1,00", 0), Some(0..1));
+    }
+
+    #[test]
+    fn increments_plain_numbers() {
+        assert_eq!(increment("41", 1).as_deref(), Some("42"));
+        assert_eq!(increment("0", -1).as_deref(), Some("-1"));
+        assert_eq!(increment("-1", 2).as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn keeps_leading_zeros() {
+        assert_eq!(increment("007", 1).as_deref(), Some("008"));
+        assert_eq!(increment("099", 1).as_deref(), Some("100"));
+        assert_eq!(increment("009", 991).as_deref(), Some("1000"));
+    }
+
+    #[test]
+    fn keeps_separators() {
+        assert_eq!(increment("999_999", 1).as_deref(), Some("1_000_000"));
+        assert_eq!(increment("1,000", -1).as_deref(), Some("999"));
+        assert_eq!(increment("1_0000", 1).as_deref(), Some("1_0001"));
+        assert_eq!(increment("1_00_000", 1).as_deref(), Some("1_00_001"));
+        assert_eq!(increment("-1,000", -1).as_deref(), Some("-1,001"));
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        let max = i128::MAX.to_string();
+        assert_eq!(increment(&max, 1), None);
+        assert_eq!(increment(&"9".repeat(40), 1), None);
+        assert_eq!(
+            increment(&i64::MAX.to_string(), 1).as_deref(),
+            Some("9223372036854775808")
+        );
+    }
+}
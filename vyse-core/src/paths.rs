@@ -0,0 +1,293 @@
+use std::{
+    env, fs,
+    path::{self, Path, PathBuf},
+};
+
+/// The names skipped by walks through a project unless the `ignore` option is set, since they
+/// hold dependencies or build output.
+pub const DEFAULT_IGNORE: [&str; 5] = ["target", "node_modules", "build", "dist", "vendor"];
+
+/// The files listing patterns of the files to skip, read in every directory of a walk.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Which files a walk through a project visits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkOptions {
+    /// Patterns of the files and directories to skip besides those listed in ignore files, in
+    /// the format of `.gitignore`.
+    pub ignore: Vec<String>,
+    /// Visits the files and directories whose name starts with a dot.
+    pub hidden: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            ignore: DEFAULT_IGNORE.map(String::from).to_vec(),
+            hidden: false,
+        }
+    }
+}
+
+/// A line of an ignore file.
+#[derive(Debug, Clone)]
+struct IgnorePattern {
+    /// The directory of the ignore file, which anchored patterns are relative to.
+    base: PathBuf,
+    glob: String,
+    /// The pattern contains a `/` before its end, so it matches the path below the base rather
+    /// than the name at any depth.
+    anchored: bool,
+    /// The pattern ends with `/`, so it only matches directories.
+    dir_only: bool,
+}
+
+impl IgnorePattern {
+    /// Parses a line of an ignore file. Comments, empty lines and negated patterns, which are not
+    /// supported, are skipped.
+    fn parse(line: &str, base: &Path) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with(['#', '!']) {
+            return None;
+        }
+
+        let dir_only = line.ends_with('/');
+        let glob = line.trim_end_matches('/');
+        let anchored = glob.contains('/');
+
+        Some(Self {
+            base: base.to_path_buf(),
+            glob: glob.trim_start_matches('/').to_string(),
+            anchored,
+            dir_only,
+        })
+    }
+
+    /// Returns whether the pattern matches a path.
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        if self.anchored {
+            path.strip_prefix(&self.base).is_ok_and(|relative| {
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                glob_matches(&self.glob, &relative)
+            })
+        } else {
+            path.file_name()
+                .is_some_and(|name| glob_matches(&self.glob, &name.to_string_lossy()))
+        }
+    }
+}
+
+/// How file paths are shown in the status bar.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PathDisplay {
+    /// Relative to the project root, falling back to [`PathDisplay::Home`] outside of it.
+    #[default]
+    Relative,
+    /// Absolute, with the home directory abbreviated as `~`.
+    Home,
+    Absolute,
+}
+
+impl PathDisplay {
+    /// Parses the value of the `pathdisplay` option.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "relative" => Some(Self::Relative),
+            "home" => Some(Self::Home),
+            "absolute" => Some(Self::Absolute),
+            _ => None,
+        }
+    }
+
+    /// Formats a path for display, `working_dir` is used to find the project root.
+    pub fn format(self, path: &Path, working_dir: &Path) -> String {
+        let path = path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+        if self == Self::Relative {
+            if let Some(relative) = find_project_root(working_dir)
+                .and_then(|root| path.strip_prefix(root).ok().map(Path::to_path_buf))
+            {
+                return relative.display().to_string();
+            }
+        }
+
+        if self != Self::Absolute {
+            if let Some(relative) = env::var_os("HOME")
+                .and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf))
+            {
+                return Path::new("~").join(relative).display().to_string();
+            }
+        }
+
+        path.display().to_string()
+    }
+}
+
+/// Returns the closest directory containing the given one that looks like a project root.
+pub fn find_project_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".git").exists() || ancestor.join(".vyse").is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Resolves a path relative to the working directory, expanding a leading `~`.
+pub fn resolve(path: &str, working_dir: &Path) -> PathBuf {
+    if let Some(rest) = path
+        .strip_prefix("~/")
+        .or(path.strip_prefix('~').filter(|rest| rest.is_empty()))
+    {
+        if let Some(home) = env::var_os("HOME") {
+            return PathBuf::from(home).join(rest);
+        }
+    }
+
+    working_dir.join(path)
+}
+
+/// Calls `visit` for the files below a directory in the order of their paths, skipping hidden and
+/// ignored ones and everything in ignored directories. The walk stops once `visit` returns
+/// `false`.
+///
+/// The patterns of `.gitignore` and `.ignore` files apply to the directory they are in.
+pub fn walk(root: &Path, options: &WalkOptions, mut visit: impl FnMut(&Path) -> bool) {
+    let mut patterns: Vec<IgnorePattern> = options
+        .ignore
+        .iter()
+        .filter_map(|line| IgnorePattern::parse(line, root))
+        .collect();
+
+    walk_dir(root, options, &mut patterns, &mut visit);
+}
+
+/// Walks the entries of a directory, returns `false` if the walk was stopped.
+fn walk_dir(
+    dir: &Path,
+    options: &WalkOptions,
+    patterns: &mut Vec<IgnorePattern>,
+    visit: &mut impl FnMut(&Path) -> bool,
+) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return true;
+    };
+
+    let inherited = patterns.len();
+    for name in IGNORE_FILES {
+        if let Ok(content) = fs::read_to_string(dir.join(name)) {
+            patterns.extend(
+                content
+                    .lines()
+                    .filter_map(|line| IgnorePattern::parse(line, dir)),
+            );
+        }
+    }
+
+    let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+    entries.sort_by_key(fs::DirEntry::file_name);
+
+    let mut walking = true;
+    for entry in entries {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        // The repository itself is never part of the project files.
+        let name = entry.file_name();
+        let is_hidden = name.to_string_lossy().starts_with('.');
+        if (is_hidden && !options.hidden) || name == ".git" {
+            continue;
+        }
+
+        let is_dir = file_type.is_dir();
+        if patterns
+            .iter()
+            .any(|pattern| pattern.matches(&path, is_dir))
+        {
+            continue;
+        }
+
+        walking = if is_dir {
+            walk_dir(&path, options, patterns, visit)
+        } else {
+            !file_type.is_file() || visit(&path)
+        };
+
+        if !walking {
+            break;
+        }
+    }
+
+    patterns.truncate(inherited);
+    walking
+}
+
+/// Returns whether a glob matches the whole text. `*` matches anything but `/`, `**` anything
+/// including `/` and `?` a single character other than `/`.
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    matches_from(&glob, &text)
+}
+
+/// Matches the rest of a glob against the rest of a text.
+fn matches_from(glob: &[char], text: &[char]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directories at all.
+            let rest_after_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+            matches_from(rest_after_slash, text)
+                || (0..=text.len()).any(|skipped| matches_from(rest, &text[skipped..]))
+        }
+        ['*', rest @ ..] => {
+            let max = text.iter().position(|ch| *ch == '/').unwrap_or(text.len());
+            (0..=max).any(|skipped| matches_from(rest, &text[skipped..]))
+        }
+        ['?', rest @ ..] => text
+            .split_first()
+            .is_some_and(|(ch, text)| *ch != '/' && matches_from(rest, text)),
+        [ch, rest @ ..] => text
+            .split_first()
+            .is_some_and(|(other, text)| ch == other && matches_from(rest, text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_within_a_directory() {
+        assert!(glob_matches("*.log", "debug.log"));
+        assert!(!glob_matches("*.log", "logs/debug.log"));
+        assert!(glob_matches("file?.txt", "file1.txt"));
+        assert!(!glob_matches("file?.txt", "file10.txt"));
+    }
+
+    #[test]
+    fn double_stars_match_across_directories() {
+        assert!(glob_matches("docs/**/*.md", "docs/a/b/c.md"));
+        assert!(glob_matches("docs/**/*.md", "docs/c.md"));
+        assert!(glob_matches("**/cache", "a/b/cache"));
+        assert!(!glob_matches("docs/**/*.md", "src/c.md"));
+    }
+
+    #[test]
+    fn ignore_patterns() {
+        let base = Path::new("/project");
+        let unanchored = IgnorePattern::parse("*.tmp", base).unwrap();
+        let anchored = IgnorePattern::parse("/out/", base).unwrap();
+
+        assert!(IgnorePattern::parse("# comment", base).is_none());
+        assert!(IgnorePattern::parse("!keep.tmp", base).is_none());
+        assert!(unanchored.matches(Path::new("/project/a/b.tmp"), false));
+        assert!(anchored.matches(Path::new("/project/out"), true));
+        assert!(!anchored.matches(Path::new("/project/out"), false));
+        assert!(!anchored.matches(Path::new("/project/src/out"), true));
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    env, fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::{
+    buffer::Location,
+    history::{Edit, History, Transaction},
+};
+
+/// The first line of every undo file, to detect files written by an incompatible version.
+const UNDO_FILE_HEADER: &str = "vyse-undo 1";
+
+/// A FNV-1a hash used to identify files and their contents across sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentHash(u64);
+
+impl Default for ContentHash {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl ContentHash {
+    /// Hashes the given bytes.
+    pub fn of(bytes: &[u8]) -> Self {
+        let mut hash = Self::default();
+        hash.update(bytes);
+        hash
+    }
+
+    /// Adds more bytes to the hash.
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Writes the history of the file at the given path to the cache directory.
+pub fn save_history(path: &Path, content_hash: ContentHash, history: &History) -> io::Result<()> {
+    let undo_path = get_undo_path(path).ok_or(io::ErrorKind::NotFound)?;
+    if let Some(parent) = undo_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut writer = BufWriter::new(fs::File::create(undo_path)?);
+    write_history(&mut writer, content_hash, history)?;
+    writer.flush()
+}
+
+/// Writes a history in the format of undo files.
+fn write_history(
+    writer: &mut impl Write,
+    content_hash: ContentHash,
+    history: &History,
+) -> io::Result<()> {
+    writeln!(writer, "{UNDO_FILE_HEADER}")?;
+    writeln!(writer, "hash {:016x}", content_hash.0)?;
+
+    for (name, stack) in [("undo", &history.undo_stack), ("redo", &history.redo_stack)] {
+        writeln!(writer, "{name} {}", stack.len())?;
+
+        for transaction in stack {
+            match transaction
+                .created_at
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            {
+                Some(age) => writeln!(
+                    writer,
+                    "transaction {} {}",
+                    transaction.edits.len(),
+                    age.as_secs()
+                )?,
+                None => writeln!(writer, "transaction {}", transaction.edits.len())?,
+            }
+
+            for edit in &transaction.edits {
+                let (kind, location, text) = match edit {
+                    Edit::Insert { location, text } => ("insert", location, text),
+                    Edit::Delete { location, text } => ("delete", location, text),
+                };
+
+                writeln!(
+                    writer,
+                    "{kind} {} {} {}",
+                    location.row,
+                    location.col,
+                    escape(text)
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the history of the file at the given path, if it was saved for the same contents.
+pub fn load_history(path: &Path, content_hash: ContentHash) -> Option<History> {
+    let content = fs::read_to_string(get_undo_path(path)?).ok()?;
+    parse_history(&content, content_hash)
+}
+
+/// Parses the contents of an undo file, if it was saved for the same contents.
+fn parse_history(content: &str, content_hash: ContentHash) -> Option<History> {
+    let mut lines = content.lines();
+
+    if lines.next()? != UNDO_FILE_HEADER {
+        return None;
+    }
+
+    let hash = u64::from_str_radix(lines.next()?.strip_prefix("hash ")?, 16).ok()?;
+    if ContentHash(hash) != content_hash {
+        return None;
+    }
+
+    let mut history = History::default();
+    history.undo_stack = parse_stack(&mut lines, "undo")?;
+    history.redo_stack = parse_stack(&mut lines, "redo")?;
+
+    Some(history)
+}
+
+/// Parses a stack of transactions introduced by a line with its name and length.
+fn parse_stack<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    name: &str,
+) -> Option<Vec<Transaction>> {
+    let (header, count) = lines.next()?.split_once(' ')?;
+    if header != name {
+        return None;
+    }
+
+    let mut stack = Vec::new();
+    for _ in 0..count.parse::<usize>().ok()? {
+        // The creation time was added later, so it is optional.
+        let mut header = lines.next()?.strip_prefix("transaction ")?.split(' ');
+        let count = header.next()?;
+        let created_at = header
+            .next()
+            .and_then(|secs| secs.parse().ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        let mut edits = Vec::new();
+
+        for _ in 0..count.parse::<usize>().ok()? {
+            edits.push(parse_edit(lines.next()?)?);
+        }
+
+        stack.push(Transaction { edits, created_at });
+    }
+
+    Some(stack)
+}
+
+/// Parses a single edit, e.g. `insert 3 14 text`.
+fn parse_edit(line: &str) -> Option<Edit> {
+    let mut parts = line.splitn(4, ' ');
+    let kind = parts.next()?;
+    let location = Location {
+        row: parts.next()?.parse().ok()?,
+        col: parts.next()?.parse().ok()?,
+    };
+    let text = unescape(parts.next()?)?;
+
+    match kind {
+        "insert" => Some(Edit::Insert { location, text }),
+        "delete" => Some(Edit::Delete { location, text }),
+        _ => None,
+    }
+}
+
+/// Returns the file the history of the given path is stored in.
+fn get_undo_path(path: &Path) -> Option<PathBuf> {
+    Some(get_cache_dir()?.join("undo").join(get_path_key(path)))
+}
+
+/// Returns a file name identifying the given path, independent of how it was written.
+pub fn get_path_key(path: &Path) -> String {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let ContentHash(key) = ContentHash::of(path.as_os_str().as_encoded_bytes());
+
+    format!("{key:016x}")
+}
+
+/// Returns the cache directory of vyse, following the XDG base directory specification.
+pub fn get_cache_dir() -> Option<PathBuf> {
+    let cache_dir = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(cache_dir.join("vyse"))
+}
+
+/// Returns the config directory of vyse, following the XDG base directory specification.
+pub fn get_config_dir() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_dir.join("vyse"))
+}
+
+/// Escapes backslashes, line breaks and tabs, so the text is read back unchanged from its line.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+/// Reverts [`escape`], `None` if the text contains an unknown escape.
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+
+        unescaped.push(match chars.next()? {
+            '\\' => '\\',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            _ => return None,
+        });
+    }
+
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(edits: Vec<Edit>, secs: Option<u64>) -> Transaction {
+        Transaction {
+            edits,
+            created_at: secs.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+        }
+    }
+
+    #[test]
+    fn escapes_round_trip() {
+        for text in [
+            "",
+            "plain",
+            "a\\nb",
+            "line\r\n",
+            "\ttab\\",
+            "trailing\r",
+            "ü\n\n",
+        ] {
+            let escaped = escape(text);
+            assert!(!escaped.contains(['\n', '\r']), "{escaped:?}");
+            assert_eq!(unescape(&escaped).as_deref(), Some(text));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_escapes() {
+        assert_eq!(unescape("\\x"), None);
+        assert_eq!(unescape("trailing\\"), None);
+    }
+
+    #[test]
+    fn history_round_trips() {
+        let location = Location { row: 2, col: 5 };
+        let mut history = History::default();
+        history.undo_stack = vec![
+            transaction(
+                vec![
+                    Edit::Insert {
+                        location,
+                        text: "first\r\nsecond\r".to_string(),
+                    },
+                    Edit::Delete {
+                        location,
+                        text: "\t \\n ".to_string(),
+                    },
+                ],
+                Some(1_700_000_000),
+            ),
+            transaction(
+                vec![Edit::Insert {
+                    location: Location::default(),
+                    text: String::new(),
+                }],
+                None,
+            ),
+        ];
+        history.redo_stack = vec![transaction(
+            vec![Edit::Delete {
+                location,
+                text: "x\n".to_string(),
+            }],
+            Some(1),
+        )];
+
+        let hash = ContentHash::of(b"contents");
+        let mut bytes = Vec::new();
+        write_history(&mut bytes, hash, &history).unwrap();
+        let content = String::from_utf8(bytes).unwrap();
+
+        let loaded = parse_history(&content, hash).unwrap();
+        assert_eq!(loaded.undo_stack, history.undo_stack);
+        assert_eq!(loaded.redo_stack, history.redo_stack);
+        assert!(parse_history(&content, ContentHash::of(b"other")).is_none());
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    fmt::Write,
+    fs::File,
+    io::{self, Read},
+};
+
+/// The characters random strings are made of.
+const ALPHANUMERIC: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Bytes at or above the largest multiple of the alphabet size would make some characters more
+/// likely, so they are skipped.
+const UNBIASED_LIMIT: usize = 256 - 256 % ALPHANUMERIC.len();
+
+/// Reads random bytes from the random source of the operating system.
+fn random_bytes(count: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; count];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Generates a random version 4 UUID, e.g. `0b6c5e56-1a7f-4e3b-9c1d-2f4e8a9b7c6d`.
+pub fn uuid() -> io::Result<String> {
+    let mut bytes = random_bytes(16)?;
+    bytes[6] = bytes[6] & 0x0f | 0x40;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+
+    let hex = bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    });
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+/// Generates a random string of letters and digits.
+pub fn alphanumeric(length: usize) -> io::Result<String> {
+    let mut string = String::with_capacity(length);
+
+    while string.len() < length {
+        for byte in random_bytes(length)? {
+            if usize::from(byte) < UNBIASED_LIMIT && string.len() < length {
+                string.push(char::from(
+                    ALPHANUMERIC[usize::from(byte) % ALPHANUMERIC.len()],
+                ));
+            }
+        }
+    }
+
+    Ok(string)
+}
@@ -0,0 +1,421 @@
+use std::{borrow::Cow, mem::size_of};
+
+/// The maximum number of bytes stored in a single leaf.
+const MAX_LEAF_BYTES: usize = 2048;
+/// Trees deeper than this are rebuilt to keep lookups logarithmic.
+const MAX_DEPTH: usize = 48;
+
+/// A balanced binary tree of text chunks, making edits anywhere in large texts cheap.
+///
+/// All offsets are byte offsets into the text and have to lie on character boundaries.
+#[derive(Debug, Clone)]
+pub struct Rope {
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf(String),
+    Branch {
+        left: Box<Node>,
+        right: Box<Node>,
+        bytes: usize,
+        newlines: usize,
+        depth: usize,
+    },
+}
+
+impl Default for Rope {
+    fn default() -> Self {
+        Self {
+            root: Node::Leaf(String::new()),
+        }
+    }
+}
+
+impl From<String> for Rope {
+    fn from(text: String) -> Self {
+        Self {
+            root: Node::from_leaves(split_into_leaves(&text)),
+        }
+    }
+}
+
+impl Rope {
+    /// Returns the length of the text in bytes.
+    pub fn len(&self) -> usize {
+        self.root.bytes()
+    }
+
+    /// Returns whether the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of lines, which is one more than the number of newlines.
+    pub fn line_count(&self) -> usize {
+        self.root.newlines() + 1
+    }
+
+    /// Returns the byte offset at which the given line starts.
+    pub fn line_start(&self, row: usize) -> Option<usize> {
+        if row == 0 {
+            return Some(0);
+        }
+
+        self.root.newline_offset(row - 1).map(|offset| offset + 1)
+    }
+
+    /// Returns the byte offset at which the given line ends, excluding the newline.
+    pub fn line_end(&self, row: usize) -> Option<usize> {
+        if row >= self.line_count() {
+            return None;
+        }
+
+        Some(self.root.newline_offset(row).unwrap_or(self.len()))
+    }
+
+    /// Returns the given line without its newline.
+    pub fn line(&self, row: usize) -> Option<Cow<'_, str>> {
+        Some(self.slice(self.line_start(row)?, self.line_end(row)?))
+    }
+
+    /// Returns the text between two byte offsets, only allocating if it spans multiple chunks.
+    pub fn slice(&self, start: usize, end: usize) -> Cow<'_, str> {
+        self.root.slice(start, end.min(self.len()))
+    }
+
+    /// Inserts text at the given byte offset.
+    pub fn insert(&mut self, offset: usize, text: &str) {
+        self.root.insert(offset.min(self.len()), text);
+        self.rebalance_if_needed();
+    }
+
+    /// Removes the text between two byte offsets.
+    pub fn remove(&mut self, start: usize, end: usize) {
+        let end = end.min(self.len());
+        if start < end {
+            self.root.remove(start, end);
+            self.rebalance_if_needed();
+        }
+    }
+
+    /// Returns the chunks of the text in order.
+    pub fn chunks(&self) -> Chunks<'_> {
+        Chunks {
+            stack: vec![&self.root],
+        }
+    }
+
+    /// Estimates the number of bytes allocated for the rope.
+    pub fn memory_usage(&self) -> usize {
+        self.root.memory_usage()
+    }
+
+    /// Rebuilds the tree once it became too deep from repeated edits in the same place.
+    fn rebalance_if_needed(&mut self) {
+        if self.root.depth() > MAX_DEPTH {
+            let text: String = self.chunks().collect();
+            *self = Self::from(text);
+        }
+    }
+}
+
+/// An iterator over the chunks of a [`Rope`].
+pub struct Chunks<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            match node {
+                Node::Leaf(text) if text.is_empty() => (),
+                Node::Leaf(text) => return Some(text),
+                Node::Branch { left, right, .. } => {
+                    self.stack.push(right);
+                    self.stack.push(left);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl Node {
+    /// Builds a balanced tree from the given leaves.
+    fn from_leaves(leaves: Vec<String>) -> Self {
+        let mut nodes: Vec<Self> = leaves.into_iter().map(Self::Leaf).collect();
+
+        if nodes.is_empty() {
+            return Self::Leaf(String::new());
+        }
+
+        while nodes.len() > 1 {
+            let mut parents = Vec::with_capacity(nodes.len().div_ceil(2));
+            let mut nodes_iter = nodes.into_iter();
+
+            while let Some(left) = nodes_iter.next() {
+                match nodes_iter.next() {
+                    Some(right) => parents.push(Self::branch(left, right)),
+                    None => parents.push(left),
+                }
+            }
+
+            nodes = parents;
+        }
+
+        nodes.pop().unwrap_or(Self::Leaf(String::new()))
+    }
+
+    /// Creates a branch with the summary of both children.
+    fn branch(left: Self, right: Self) -> Self {
+        Self::Branch {
+            bytes: left.bytes() + right.bytes(),
+            newlines: left.newlines() + right.newlines(),
+            depth: left.depth().max(right.depth()) + 1,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        match self {
+            Self::Leaf(text) => text.len(),
+            Self::Branch { bytes, .. } => *bytes,
+        }
+    }
+
+    fn newlines(&self) -> usize {
+        match self {
+            Self::Leaf(text) => text.bytes().filter(|byte| *byte == b'\n').count(),
+            Self::Branch { newlines, .. } => *newlines,
+        }
+    }
+
+    fn depth(&self) -> usize {
+        match self {
+            Self::Leaf(_) => 0,
+            Self::Branch { depth, .. } => *depth,
+        }
+    }
+
+    /// Recomputes the summary of a branch after one of its children changed.
+    fn update_summary(&mut self) {
+        if let Self::Branch {
+            left,
+            right,
+            bytes,
+            newlines,
+            depth,
+        } = self
+        {
+            *bytes = left.bytes() + right.bytes();
+            *newlines = left.newlines() + right.newlines();
+            *depth = left.depth().max(right.depth()) + 1;
+        }
+    }
+
+    /// Returns the byte offset of the newline with the given index.
+    fn newline_offset(&self, index: usize) -> Option<usize> {
+        match self {
+            Self::Leaf(text) => text
+                .bytes()
+                .enumerate()
+                .filter(|(_, byte)| *byte == b'\n')
+                .nth(index)
+                .map(|(offset, _)| offset),
+            Self::Branch { left, right, .. } => {
+                let left_newlines = left.newlines();
+
+                if index < left_newlines {
+                    left.newline_offset(index)
+                } else {
+                    right
+                        .newline_offset(index - left_newlines)
+                        .map(|offset| offset + left.bytes())
+                }
+            }
+        }
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Cow<'_, str> {
+        match self {
+            Self::Leaf(text) => Cow::Borrowed(&text[start..end]),
+            Self::Branch { left, right, .. } => {
+                let left_bytes = left.bytes();
+
+                if end <= left_bytes {
+                    left.slice(start, end)
+                } else if start >= left_bytes {
+                    right.slice(start - left_bytes, end - left_bytes)
+                } else {
+                    let mut text = left.slice(start, left_bytes).into_owned();
+                    text.push_str(&right.slice(0, end - left_bytes));
+                    Cow::Owned(text)
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, offset: usize, text: &str) {
+        match self {
+            Self::Leaf(leaf) => {
+                leaf.insert_str(offset, text);
+
+                if leaf.len() > MAX_LEAF_BYTES {
+                    *self = Self::from_leaves(split_into_leaves(leaf));
+                }
+            }
+            Self::Branch { left, right, .. } => {
+                let left_bytes = left.bytes();
+
+                if offset <= left_bytes {
+                    left.insert(offset, text);
+                } else {
+                    right.insert(offset - left_bytes, text);
+                }
+
+                self.update_summary();
+            }
+        }
+    }
+
+    fn remove(&mut self, start: usize, end: usize) {
+        match self {
+            Self::Leaf(leaf) => leaf.replace_range(start..end, ""),
+            Self::Branch { left, right, .. } => {
+                let left_bytes = left.bytes();
+
+                if start < left_bytes {
+                    left.remove(start, end.min(left_bytes));
+                }
+
+                if end > left_bytes {
+                    right.remove(start.saturating_sub(left_bytes), end - left_bytes);
+                }
+
+                self.update_summary();
+            }
+        }
+    }
+
+    fn memory_usage(&self) -> usize {
+        match self {
+            Self::Leaf(text) => size_of::<Self>() + text.capacity(),
+            Self::Branch { left, right, .. } => {
+                size_of::<Self>() + left.memory_usage() + right.memory_usage()
+            }
+        }
+    }
+}
+
+/// Splits text into leaf sized chunks on character boundaries.
+fn split_into_leaves(text: &str) -> Vec<String> {
+    let mut leaves = Vec::with_capacity(text.len() / MAX_LEAF_BYTES + 1);
+    let mut rest = text;
+
+    while rest.len() > MAX_LEAF_BYTES {
+        let mut split = MAX_LEAF_BYTES / 2;
+        while !rest.is_char_boundary(split) {
+            split += 1;
+        }
+
+        let (leaf, remainder) = rest.split_at(split);
+        leaves.push(leaf.to_string());
+        rest = remainder;
+    }
+
+    leaves.push(rest.to_string());
+    leaves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(rope: &Rope) -> String {
+        rope.chunks().collect()
+    }
+
+    #[test]
+    fn insert_and_remove_match_string() {
+        let mut rope = Rope::default();
+        let mut expected = String::new();
+
+        for idx in 0..2000 {
+            let offset = (idx * 7) % (expected.len() + 1);
+            let offset = (offset..=expected.len())
+                .find(|offset| expected.is_char_boundary(*offset))
+                .unwrap_or(expected.len());
+            rope.insert(offset, "ab\né");
+            expected.insert_str(offset, "ab\né");
+
+            if idx % 3 == 0 {
+                let start = offset.saturating_sub(2);
+                let start = (0..=start)
+                    .rev()
+                    .find(|start| expected.is_char_boundary(*start))
+                    .unwrap_or(0);
+                rope.remove(start, offset);
+                expected.replace_range(start..offset, "");
+            }
+        }
+
+        assert_eq!(text(&rope), expected);
+        assert_eq!(rope.len(), expected.len());
+        assert_eq!(rope.line_count(), expected.matches('\n').count() + 1);
+    }
+
+    #[test]
+    fn lines_span_chunks() {
+        let line = "x".repeat(MAX_LEAF_BYTES);
+        let rope = Rope::from(format!("{line}\n{line}\nend"));
+
+        assert!(rope.chunks().count() > 1);
+        assert_eq!(rope.line_count(), 3);
+        assert_eq!(rope.line(1).as_deref(), Some(line.as_str()));
+        assert_eq!(rope.line(2).as_deref(), Some("end"));
+        assert_eq!(rope.line(3), None);
+        assert_eq!(rope.line_start(2), Some(2 * MAX_LEAF_BYTES + 2));
+        assert_eq!(rope.line_end(0), Some(MAX_LEAF_BYTES));
+    }
+
+    #[test]
+    fn remove_across_chunks_and_clamps() {
+        let mut rope = Rope::from("ab".repeat(MAX_LEAF_BYTES));
+
+        rope.remove(10, 3 * MAX_LEAF_BYTES / 2);
+        assert_eq!(rope.len(), MAX_LEAF_BYTES / 2 + 10);
+
+        rope.remove(5, usize::MAX);
+        assert_eq!(text(&rope), "ababa");
+
+        rope.remove(3, 3);
+        assert_eq!(text(&rope), "ababa");
+    }
+
+    #[test]
+    fn splits_leaves_on_char_boundaries() {
+        let rope = Rope::from("é".repeat(MAX_LEAF_BYTES));
+
+        assert!(rope.chunks().all(|chunk| chunk.chars().all(|ch| ch == 'é')));
+        assert_eq!(rope.len(), 2 * MAX_LEAF_BYTES);
+    }
+
+    #[test]
+    fn rebalances_after_repeated_inserts() {
+        let mut rope = Rope::default();
+        let chunk = "y".repeat(MAX_LEAF_BYTES / 2 + 1);
+
+        for _ in 0..500 {
+            rope.insert(0, &chunk);
+            assert!(rope.root.depth() <= MAX_DEPTH);
+        }
+
+        assert_eq!(rope.len(), 500 * chunk.len());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{buffer::Buffer, indent, line::TAB_WIDTH};
+
+/// The maximum number of lines searched for the start of a scope, so the search stays fast in
+/// large files.
+const MAX_SEARCHED_LINES: usize = 10_000;
+
+/// Returns the rows of the lines opening the scopes around a row, outermost first, at most
+/// `limit` of the innermost ones.
+///
+/// Scopes are derived from indentation: a scope is opened by the nearest line above that is
+/// indented less than everything in it. Blank lines belong to the scope of the next line.
+pub fn enclosing_lines(buffer: &Buffer, row: usize, limit: usize) -> Vec<usize> {
+    let Some(mut indent) = (row..buffer.get_line_count())
+        .take(MAX_SEARCHED_LINES)
+        .find_map(|row| get_indent_width(buffer, row))
+    else {
+        return Vec::new();
+    };
+
+    let mut rows = Vec::new();
+    for row in (row.saturating_sub(MAX_SEARCHED_LINES)..row).rev() {
+        if indent == 0 {
+            break;
+        }
+
+        match get_indent_width(buffer, row) {
+            Some(width) if width < indent => {
+                rows.push(row);
+                indent = width;
+            }
+            _ => (),
+        }
+    }
+
+    rows.truncate(limit);
+    rows.reverse();
+    rows
+}
+
+/// Returns the last row of the block a line opens, i.e. of the lines after it that are indented
+/// more, `None` if the next line that is not blank is not indented more. Blank lines at the end
+/// of the block are left out.
+pub fn block_end(buffer: &Buffer, row: usize) -> Option<usize> {
+    let indent = get_indent_width(buffer, row)?;
+    let mut end = None;
+
+    for row in row + 1..buffer.get_line_count() {
+        match get_indent_width(buffer, row) {
+            Some(width) if width > indent => end = Some(row),
+            Some(_) => break,
+            None => (),
+        }
+    }
+
+    end
+}
+
+/// Returns the width of the indentation of a line, `None` for blank lines.
+fn get_indent_width(buffer: &Buffer, row: usize) -> Option<usize> {
+    let line = buffer.get_line(row)?;
+    let whitespace = indent::leading_whitespace(&line);
+    if whitespace.len() == line.len() {
+        return None;
+    }
+
+    Some(whitespace.chars().fold(0, |width, ch| {
+        if ch == '\t' {
+            width - width % TAB_WIDTH + TAB_WIDTH
+        } else {
+            width + 1
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> Buffer {
+        Buffer::from_reader(text.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn finds_enclosing_lines() {
+        let buffer = buffer("fn a() {\n    if x {\n\n        y();\n    }\n}");
+        assert_eq!(enclosing_lines(&buffer, 3, 3), [0, 1]);
+        assert_eq!(enclosing_lines(&buffer, 3, 1), [1]);
+        assert_eq!(enclosing_lines(&buffer, 4, 3), [0]);
+        assert!(enclosing_lines(&buffer, 0, 3).is_empty());
+    }
+
+    #[test]
+    fn gives_blank_lines_the_scope_of_the_next_line() {
+        let buffer = buffer("fn a() {\n    if x {\n\n        y();\n    }\n}");
+        assert_eq!(enclosing_lines(&buffer, 2, 3), [0, 1]);
+    }
+
+    #[test]
+    fn measures_tabs_to_tab_stops() {
+        let buffer = buffer("a\n  b\n\tc");
+        assert_eq!(enclosing_lines(&buffer, 2, 3), [0, 1]);
+    }
+
+    #[test]
+    fn finds_block_ends() {
+        let buffer = buffer("fn a() {\n    if x {\n        y();\n    }\n\n}\nb\n  c\n\n");
+        assert_eq!(block_end(&buffer, 0), Some(3));
+        assert_eq!(block_end(&buffer, 1), Some(2));
+        assert_eq!(block_end(&buffer, 2), None);
+        assert_eq!(block_end(&buffer, 6), Some(7));
+        assert_eq!(block_end(&buffer, 4), None);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{buffer::Buffer, markdown};
+
+/// The keywords that start the definition of a function, type or module in common languages.
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "fn",
+    "func",
+    "function",
+    "def",
+    "class",
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "impl",
+    "interface",
+    "mod",
+    "module",
+    "namespace",
+];
+/// The keywords that may come before a definition keyword, e.g. `pub async fn`.
+const MODIFIERS: &[&str] = &[
+    "pub",
+    "async",
+    "unsafe",
+    "const",
+    "extern",
+    "export",
+    "default",
+    "static",
+    "public",
+    "private",
+    "protected",
+    "abstract",
+    "final",
+    "inline",
+];
+
+/// Returns the row of the first section starting after a row, or of the last one starting
+/// before it if `forward` is not set.
+///
+/// Sections of Markdown files start at headings outside of code blocks. In other files they
+/// start at the definitions of functions and types, or at a `{` in the first column for code
+/// whose definitions are not recognized.
+pub fn find(buffer: &Buffer, row: usize, forward: bool, markdown: bool) -> Option<usize> {
+    let mut starts = get_starts(buffer, markdown);
+    if forward {
+        starts.find(|start| *start > row)
+    } else {
+        starts.take_while(|start| *start < row).last()
+    }
+}
+
+/// Returns the rows of the Markdown headings whose sections contain a row, outermost first, at
+/// most `limit` of the innermost ones. A heading on the row itself is left out.
+pub fn enclosing_headings(buffer: &Buffer, row: usize, limit: usize) -> Vec<usize> {
+    let mut headings: Vec<(usize, usize)> = Vec::new();
+    for start in get_starts(buffer, true).take_while(|start| *start <= row) {
+        let level = buffer
+            .get_line(start)
+            .map_or(0, |line| markdown::get_heading_level(&line));
+
+        // A heading ends the sections of the previous headings of its level or deeper.
+        while headings.last().is_some_and(|(_, other)| *other >= level) {
+            headings.pop();
+        }
+        if start < row {
+            headings.push((start, level));
+        }
+    }
+
+    let skipped = headings.len().saturating_sub(limit);
+    headings[skipped..].iter().map(|(row, _)| *row).collect()
+}
+
+/// Returns the rows at which sections start, in order.
+fn get_starts(buffer: &Buffer, markdown: bool) -> impl Iterator<Item = usize> + '_ {
+    let mut in_code_block = false;
+
+    buffer.lines().enumerate().filter_map(move |(row, line)| {
+        let is_start = if markdown {
+            // Code blocks may contain lines starting with `#`, like shell comments.
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+            }
+            !in_code_block && markdown::get_heading_level(&line) > 0
+        } else {
+            line.starts_with('{') || is_definition(&line)
+        };
+
+        is_start.then_some(row)
+    })
+}
+
+/// Returns whether a line starts a definition, i.e. starts with a definition keyword after any
+/// modifiers and is followed by a name.
+pub fn is_definition(line: &str) -> bool {
+    let mut words = line
+        .split(|ch: char| ch.is_whitespace() || ch == '(' || ch == '<')
+        .filter(|word| !word.is_empty())
+        // `pub(crate)` and `extern "C"` are modifiers too.
+        .filter(|word| !word.ends_with(')') && !word.starts_with('"'));
+
+    let Some(keyword) = words.find(|word| !MODIFIERS.contains(word)) else {
+        return false;
+    };
+
+    DEFINITION_KEYWORDS.contains(&keyword) && words.next().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> Buffer {
+        Buffer::from_reader(text.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn detects_definitions() {
+        assert!(is_definition("pub(crate) async fn run() {"));
+        assert!(is_definition("extern \"C\" fn callback("));
+        assert!(is_definition("    def method(self):"));
+        assert!(is_definition("impl<T> Trait for T {"));
+        assert!(!is_definition("fn"));
+        assert!(!is_definition("let function = 1;"));
+    }
+
+    #[test]
+    fn finds_definitions() {
+        let buffer = buffer("fn a() {\n}\n\nstruct B;\n{\n}");
+        assert_eq!(find(&buffer, 0, true, false), Some(3));
+        assert_eq!(find(&buffer, 3, true, false), Some(4));
+        assert_eq!(find(&buffer, 4, true, false), None);
+        assert_eq!(find(&buffer, 3, false, false), Some(0));
+        assert_eq!(find(&buffer, 0, false, false), None);
+    }
+
+    #[test]
+    fn skips_code_blocks_in_markdown() {
+        let buffer = buffer("# A\n```sh\n# comment\n```\n## B\ntext");
+        assert_eq!(find(&buffer, 0, true, true), Some(4));
+        assert_eq!(find(&buffer, 5, false, true), Some(4));
+    }
+
+    #[test]
+    fn finds_enclosing_headings() {
+        let buffer = buffer("# A\n## B\ntext\n## C\n### D\ntext");
+        assert_eq!(enclosing_headings(&buffer, 2, 3), [0, 1]);
+        assert_eq!(enclosing_headings(&buffer, 5, 3), [0, 3, 4]);
+        assert_eq!(enclosing_headings(&buffer, 5, 2), [3, 4]);
+        assert_eq!(enclosing_headings(&buffer, 4, 3), [0, 3]);
+        assert!(enclosing_headings(&buffer, 0, 3).is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use std::{collections::HashMap, fs, ops::Range, path::Path};
+
+use crate::persistence;
+
+/// A snippet with its placeholders replaced by their default text.
+///
+/// Snippets mark placeholders with `$1`, `${1}` or `${1:default}`, which are visited in the order
+/// of their numbers, and `$0` for where the cursor ends up. Placeholders sharing a number mirror
+/// each other, a placeholder without a default shows the default of another one with its number.
+/// `\$`, `\}` and `\\` insert the characters themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub text: String,
+    /// The number and byte range in the text of each placeholder, in the order they appear.
+    pub placeholders: Vec<(usize, Range<usize>)>,
+}
+
+/// A part of the source of a snippet.
+enum Token {
+    Text(char),
+    Placeholder {
+        number: usize,
+        default: Option<String>,
+    },
+}
+
+impl Snippet {
+    /// Parses the source of a snippet, indenting the lines after the first with `indent`.
+    pub fn parse(source: &str, indent: &str) -> Self {
+        let tokens = tokenize(source);
+
+        let mut defaults = HashMap::new();
+        for token in &tokens {
+            if let Token::Placeholder {
+                number,
+                default: Some(default),
+            } = token
+            {
+                defaults.entry(*number).or_insert(default.as_str());
+            }
+        }
+
+        let mut text = String::with_capacity(source.len());
+        let mut placeholders = Vec::new();
+        for token in &tokens {
+            match token {
+                Token::Text('\n') => {
+                    text.push('\n');
+                    text.push_str(indent);
+                }
+                Token::Text(ch) => text.push(*ch),
+                Token::Placeholder { number, .. } => {
+                    let start = text.len();
+                    text.push_str(defaults.get(number).copied().unwrap_or_default());
+                    placeholders.push((*number, start..text.len()));
+                }
+            }
+        }
+
+        Self { text, placeholders }
+    }
+}
+
+/// Returns the source of a snippet, read from `snippets/<extension>/<name>` or `snippets/<name>`
+/// in the config directory, e.g. `snippets/rs/fn` for a Rust function.
+pub fn load(name: &str, path: Option<&Path>) -> Option<String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
+    }
+
+    let snippets_dir = persistence::get_config_dir()?.join("snippets");
+    let extension = path.and_then(Path::extension);
+
+    let mut source = extension
+        .map(|extension| snippets_dir.join(extension).join(name))
+        .into_iter()
+        .chain([snippets_dir.join(name)])
+        .filter(|path| path.is_file())
+        .find_map(|path| fs::read_to_string(path).ok())?;
+
+    // Files end with a newline that is not part of the snippet.
+    if source.ends_with('\n') {
+        source.pop();
+    }
+
+    Some(source)
+}
+
+/// Splits the source of a snippet into characters and placeholders. A `$` that does not start a
+/// valid placeholder is kept as is.
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+
+    while let Some(ch) = rest.chars().next() {
+        if ch == '\\' {
+            if let Some(escaped) = rest[1..].chars().next().filter(|ch| "$}\\".contains(*ch)) {
+                tokens.push(Token::Text(escaped));
+                rest = &rest[1 + escaped.len_utf8()..];
+                continue;
+            }
+        }
+
+        if ch == '$' {
+            if let Some((token, len)) = parse_placeholder(&rest[1..]) {
+                tokens.push(token);
+                rest = &rest[1 + len..];
+                continue;
+            }
+        }
+
+        tokens.push(Token::Text(ch));
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    tokens
+}
+
+/// Parses the placeholder after a `$`, returns it together with its length.
+fn parse_placeholder(text: &str) -> Option<(Token, usize)> {
+    let digits_len = text.len()
+        - text
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    if digits_len > 0 {
+        let number = text[..digits_len].parse().ok()?;
+        return Some((
+            Token::Placeholder {
+                number,
+                default: None,
+            },
+            digits_len,
+        ));
+    }
+
+    let inner = text.strip_prefix('{')?;
+    let digits_len = inner.len()
+        - inner
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    let number = inner[..digits_len].parse().ok()?;
+    let rest = &inner[digits_len..];
+
+    if rest.starts_with('}') {
+        return Some((
+            Token::Placeholder {
+                number,
+                default: None,
+            },
+            digits_len + 2,
+        ));
+    }
+
+    // The default ends at the first unescaped `}` and may not span lines.
+    let mut default = String::new();
+    let mut chars = rest.strip_prefix(':')?.char_indices();
+    while let Some((idx, ch)) = chars.next() {
+        match ch {
+            '}' => {
+                return Some((
+                    Token::Placeholder {
+                        number,
+                        default: Some(default),
+                    },
+                    digits_len + idx + 3,
+                ));
+            }
+            '\\' => default.push(chars.next().map(|(_, ch)| ch)?),
+            '\n' => return None,
+            ch => default.push(ch),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_placeholders_with_defaults() {
+        let snippet = Snippet::parse("fn ${1:name}($2) {\n\t$0\n}", "    ");
+        assert_eq!(snippet.text, "fn name() {\n    \t\n    }");
+        assert_eq!(snippet.placeholders, [(1, 3..7), (2, 8..8), (0, 17..17)]);
+    }
+
+    #[test]
+    fn mirrors_placeholders_with_one_number() {
+        let snippet = Snippet::parse("$1 = ${1:value}; ${1}", "");
+        assert_eq!(snippet.text, "value = value; value");
+        assert_eq!(snippet.placeholders, [(1, 0..5), (1, 8..13), (1, 15..20)]);
+    }
+
+    #[test]
+    fn keeps_escaped_and_invalid_markers() {
+        let snippet = Snippet::parse(r"\$1 \} \\ $x ${a} ${1:a\}b} ${2:open", "");
+        assert_eq!(snippet.text, r"$1 } \ $x ${a} a}b ${2:open");
+        assert_eq!(snippet.placeholders, [(1, 15..18)]);
+
+        let snippet = Snippet::parse("${1:two\nlines}", "");
+        assert_eq!(snippet.text, "${1:two\nlines}");
+        assert!(snippet.placeholders.is_empty());
+    }
+}
@@ -0,0 +1,22 @@
+use std::{fs, path::Path};
+
+use crate::persistence;
+
+/// Returns the skeleton for a new file, read from `templates/<file name>` or
+/// `templates/<extension>` in the config directory, e.g. `templates/rs` for Rust files.
+pub fn load(path: &Path) -> Option<String> {
+    let templates_dir = persistence::get_config_dir()?.join("templates");
+    let candidates = [path.file_name(), path.extension()];
+
+    let mut template = candidates
+        .into_iter()
+        .flatten()
+        .find_map(|name| fs::read_to_string(templates_dir.join(name)).ok())?;
+
+    // Buffers are saved with a final newline, so the one of the template is not part of the text.
+    if template.ends_with('\n') {
+        template.pop();
+    }
+
+    Some(template)
+}
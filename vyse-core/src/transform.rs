@@ -0,0 +1,288 @@
+use std::fmt::Write;
+
+/// The characters of the base64 alphabet, indexed by their value.
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// An encoding text can be converted to and from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    Base64,
+    /// Percent-encoding as used in URLs.
+    Url,
+    /// The contents of a JSON string literal.
+    Json,
+    /// Letters rotated by 13 places, which is its own inverse.
+    Rot13,
+}
+
+impl Transform {
+    /// Parses the name of an encoding, e.g. `base64`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "base64" => Some(Self::Base64),
+            "url" => Some(Self::Url),
+            "json" => Some(Self::Json),
+            "rot13" => Some(Self::Rot13),
+            _ => None,
+        }
+    }
+
+    /// Encodes the text.
+    pub fn encode(self, text: &str) -> String {
+        match self {
+            Self::Base64 => encode_base64(text.as_bytes()),
+            Self::Url => encode_url(text),
+            Self::Json => escape_json(text),
+            Self::Rot13 => rot13(text),
+        }
+    }
+
+    /// Decodes the text, failing if it is not validly encoded.
+    pub fn decode(self, text: &str) -> Result<String, String> {
+        let bytes = match self {
+            Self::Base64 => decode_base64(text)?,
+            Self::Url => decode_url(text)?,
+            Self::Json => return unescape_json(text),
+            Self::Rot13 => return Ok(rot13(text)),
+        };
+
+        String::from_utf8(bytes).map_err(|_| "decoded text is not valid UTF-8".to_string())
+    }
+}
+
+/// Encodes bytes as padded base64.
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (idx, byte)| {
+            group | u32::from(*byte) << (16 - 8 * idx)
+        });
+
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                let value = (group >> (18 - 6 * idx)) & 0x3f;
+                encoded.push(char::from(BASE64_ALPHABET[value as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Decodes base64, the padding may be left out and whitespace is ignored.
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let mut group = 0u32;
+    let mut bits = 0;
+
+    for ch in text
+        .trim_end_matches(|ch: char| ch == '=' || ch.is_whitespace())
+        .chars()
+    {
+        if ch.is_whitespace() {
+            continue;
+        }
+
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|&digit| char::from(digit) == ch)
+            .ok_or_else(|| format!("invalid base64 character: {ch}"))?;
+        group = group << 6 | u32::try_from(value).unwrap_or_default();
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push(u8::try_from(group >> bits & 0xff).unwrap_or_default());
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Percent-encodes everything except the characters URLs never reserve.
+fn encode_url(text: &str) -> String {
+    text.bytes().fold(String::new(), |mut encoded, byte| {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+        encoded
+    })
+}
+
+/// Decodes percent-encoded bytes, other characters are kept as they are.
+fn decode_url(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let byte = tail
+                .get(..2)
+                .and_then(|digits| std::str::from_utf8(digits).ok())
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| "invalid percent-encoding".to_string())?;
+            bytes.push(byte);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Escapes text to be placed between the quotes of a JSON string.
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            ch if ch.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", u32::from(ch));
+            }
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+/// Resolves the escape sequences of the contents of a JSON string.
+fn unescape_json(text: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('"') => unescaped.push('"'),
+            Some('\\') => unescaped.push('\\'),
+            Some('/') => unescaped.push('/'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('b') => unescaped.push('\u{8}'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('u') => {
+                let mut code = read_code_unit(&mut chars)?;
+
+                // Characters outside the basic plane are escaped as a surrogate pair.
+                if (0xd800..0xdc00).contains(&code) {
+                    if chars.next() != Some('\\') || chars.next() != Some('u') {
+                        return Err("unpaired surrogate".to_string());
+                    }
+                    let low = read_code_unit(&mut chars)?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err("unpaired surrogate".to_string());
+                    }
+                    code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                }
+
+                unescaped.push(char::from_u32(code).ok_or("unpaired surrogate")?);
+            }
+            Some(ch) => return Err(format!("invalid escape sequence: \\{ch}")),
+            None => return Err("unterminated escape sequence".to_string()),
+        }
+    }
+
+    Ok(unescaped)
+}
+
+/// Reads the four hex digits of a `\u` escape sequence.
+fn read_code_unit(chars: &mut impl Iterator<Item = char>) -> Result<u32, String> {
+    let digits: String = chars.take(4).collect();
+    if digits.len() != 4 {
+        return Err("invalid unicode escape".to_string());
+    }
+
+    u32::from_str_radix(&digits, 16).map_err(|_| "invalid unicode escape".to_string())
+}
+
+/// Rotates ASCII letters by 13 places, keeping their case.
+fn rot13(text: &str) -> String {
+    text.chars()
+        .map(|ch| match ch {
+            'a'..='m' | 'A'..='M' => char::from(ch as u8 + 13),
+            'n'..='z' | 'N'..='Z' => char::from(ch as u8 - 13),
+            ch => ch,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSFORMS: [Transform; 4] = [
+        Transform::Base64,
+        Transform::Url,
+        Transform::Json,
+        Transform::Rot13,
+    ];
+
+    #[test]
+    fn round_trips() {
+        for text in ["", "a", "ab", "abc", "a \"b\"\\\n\t\u{1}é😀 /%+"] {
+            for transform in TRANSFORMS {
+                let encoded = transform.encode(text);
+                assert_eq!(
+                    transform.decode(&encoded).as_deref(),
+                    Ok(text),
+                    "{transform:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(Transform::Base64.encode("hello"), "aGVsbG8=");
+        assert_eq!(Transform::Base64.decode("aGVs\nbG8").unwrap(), "hello");
+        assert!(Transform::Base64.decode("a*").is_err());
+        assert!(Transform::Base64.decode("//79").is_err());
+    }
+
+    #[test]
+    fn encodes_urls() {
+        assert_eq!(Transform::Url.encode("a b/é~"), "a%20b%2F%C3%A9~");
+        assert_eq!(Transform::Url.decode("a+b%2f").unwrap(), "a+b/");
+        assert!(Transform::Url.decode("%zz").is_err());
+        assert!(Transform::Url.decode("%4").is_err());
+    }
+
+    #[test]
+    fn escapes_json() {
+        assert_eq!(
+            Transform::Json.encode("\"a\"\n\u{1}"),
+            "\\\"a\\\"\\n\\u0001"
+        );
+        assert_eq!(Transform::Json.decode("\\ud83d\\ude00\\/").unwrap(), "😀/");
+        assert!(Transform::Json.decode("\\ud83d").is_err());
+        assert!(Transform::Json.decode("\\x").is_err());
+        assert!(Transform::Json.decode("\\u12").is_err());
+    }
+
+    #[test]
+    fn rotates_letters() {
+        assert_eq!(Transform::Rot13.encode("Hello, World!"), "Uryyb, Jbeyq!");
+        assert_eq!(Transform::parse("rot13"), Some(Transform::Rot13));
+        assert_eq!(Transform::parse("rot47"), None);
+    }
+}
@@ -0,0 +1,302 @@
+use std::cmp::Ordering;
+
+/// Characters that extend the preceding grapheme cluster: combining marks, joiners, variation
+/// selectors, emoji modifiers and tags.
+const EXTENDING_RANGES: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x0483, 0x0489),
+    (0x0591, 0x05BD),
+    (0x05BF, 0x05BF),
+    (0x05C1, 0x05C2),
+    (0x05C4, 0x05C5),
+    (0x05C7, 0x05C7),
+    (0x0610, 0x061A),
+    (0x064B, 0x065F),
+    (0x0670, 0x0670),
+    (0x06D6, 0x06DC),
+    (0x06DF, 0x06E4),
+    (0x06E7, 0x06E8),
+    (0x06EA, 0x06ED),
+    (0x0711, 0x0711),
+    (0x0730, 0x074A),
+    (0x07A6, 0x07B0),
+    (0x0816, 0x082D),
+    (0x0900, 0x0903),
+    (0x093A, 0x093C),
+    (0x093E, 0x094F),
+    (0x0951, 0x0957),
+    (0x0962, 0x0963),
+    (0x0981, 0x0983),
+    (0x09BC, 0x09BC),
+    (0x09BE, 0x09CD),
+    (0x09D7, 0x09D7),
+    (0x09E2, 0x09E3),
+    (0x0A01, 0x0A03),
+    (0x0A3C, 0x0A51),
+    (0x0A70, 0x0A71),
+    (0x0A75, 0x0A75),
+    (0x0A81, 0x0A83),
+    (0x0ABC, 0x0ABC),
+    (0x0ABE, 0x0ACD),
+    (0x0AE2, 0x0AE3),
+    (0x0B01, 0x0B03),
+    (0x0B3C, 0x0B3C),
+    (0x0B3E, 0x0B57),
+    (0x0B62, 0x0B63),
+    (0x0B82, 0x0B82),
+    (0x0BBE, 0x0BCD),
+    (0x0BD7, 0x0BD7),
+    (0x0C00, 0x0C04),
+    (0x0C3E, 0x0C56),
+    (0x0C62, 0x0C63),
+    (0x0C81, 0x0C83),
+    (0x0CBC, 0x0CBC),
+    (0x0CBE, 0x0CD6),
+    (0x0CE2, 0x0CE3),
+    (0x0D00, 0x0D03),
+    (0x0D3B, 0x0D3C),
+    (0x0D3E, 0x0D4D),
+    (0x0D57, 0x0D57),
+    (0x0D62, 0x0D63),
+    (0x0D81, 0x0D83),
+    (0x0DCA, 0x0DDF),
+    (0x0DF2, 0x0DF3),
+    (0x0E31, 0x0E31),
+    (0x0E34, 0x0E3A),
+    (0x0E47, 0x0E4E),
+    (0x0EB1, 0x0EB1),
+    (0x0EB4, 0x0EBC),
+    (0x0EC8, 0x0ECD),
+    (0x0F18, 0x0F19),
+    (0x0F35, 0x0F35),
+    (0x0F37, 0x0F37),
+    (0x0F39, 0x0F39),
+    (0x0F3E, 0x0F3F),
+    (0x0F71, 0x0F84),
+    (0x0F86, 0x0F87),
+    (0x0F8D, 0x0FBC),
+    (0x102B, 0x103E),
+    (0x1056, 0x1059),
+    (0x1160, 0x11FF),
+    (0x135D, 0x135F),
+    (0x1712, 0x1714),
+    (0x1732, 0x1734),
+    (0x1752, 0x1753),
+    (0x1772, 0x1773),
+    (0x17B4, 0x17D3),
+    (0x17DD, 0x17DD),
+    (0x180B, 0x180D),
+    (0x180F, 0x180F),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x200C, 0x200D),
+    (0x20D0, 0x20FF),
+    (0x2CEF, 0x2CF1),
+    (0x2DE0, 0x2DFF),
+    (0x302A, 0x302F),
+    (0x3099, 0x309A),
+    (0xA66F, 0xA672),
+    (0xA674, 0xA67D),
+    (0xA69E, 0xA69F),
+    (0xA6F0, 0xA6F1),
+    (0xA802, 0xA802),
+    (0xA806, 0xA806),
+    (0xA80B, 0xA80B),
+    (0xA823, 0xA827),
+    (0xA8C4, 0xA8C5),
+    (0xA8E0, 0xA8F1),
+    (0xFB1E, 0xFB1E),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+    (0xFF9E, 0xFF9F),
+    (0x1_F3FB, 0x1_F3FF),
+    (0xE_0020, 0xE_007F),
+    (0xE_0100, 0xE_01EF),
+];
+
+/// Characters that take up two cells in a terminal: East Asian wide and fullwidth characters and
+/// emoji shown in emoji presentation by default.
+const WIDE_RANGES: &[(u32, u32)] = &[
+    (0x1100, 0x115F),
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x23E9, 0x23EC),
+    (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267F, 0x267F),
+    (0x2693, 0x2693),
+    (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE),
+    (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA),
+    (0x26F2, 0x26F3),
+    (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA),
+    (0x26FD, 0x26FD),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x2728, 0x2728),
+    (0x274C, 0x274C),
+    (0x274E, 0x274E),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27B0, 0x27B0),
+    (0x27BF, 0x27BF),
+    (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B50),
+    (0x2B55, 0x2B55),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xA960, 0xA97F),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE10, 0xFE19),
+    (0xFE30, 0xFE6F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x1_6FE0, 0x1_6FE4),
+    (0x1_7000, 0x1_8AFF),
+    (0x1_B000, 0x1_B2FF),
+    (0x1_F004, 0x1_F004),
+    (0x1_F0CF, 0x1_F0CF),
+    (0x1_F18E, 0x1_F18E),
+    (0x1_F191, 0x1_F19A),
+    (0x1_F200, 0x1_F202),
+    (0x1_F210, 0x1_F23B),
+    (0x1_F240, 0x1_F248),
+    (0x1_F250, 0x1_F251),
+    (0x1_F260, 0x1_F265),
+    (0x1_F300, 0x1_F320),
+    (0x1_F32D, 0x1_F335),
+    (0x1_F337, 0x1_F37C),
+    (0x1_F37E, 0x1_F393),
+    (0x1_F3A0, 0x1_F3CA),
+    (0x1_F3CF, 0x1_F3D3),
+    (0x1_F3E0, 0x1_F3F0),
+    (0x1_F3F4, 0x1_F3F4),
+    (0x1_F3F8, 0x1_F43E),
+    (0x1_F440, 0x1_F440),
+    (0x1_F442, 0x1_F4FC),
+    (0x1_F4FF, 0x1_F53D),
+    (0x1_F54B, 0x1_F54E),
+    (0x1_F550, 0x1_F567),
+    (0x1_F57A, 0x1_F57A),
+    (0x1_F595, 0x1_F596),
+    (0x1_F5A4, 0x1_F5A4),
+    (0x1_F5FB, 0x1_F64F),
+    (0x1_F680, 0x1_F6C5),
+    (0x1_F6CC, 0x1_F6CC),
+    (0x1_F6D0, 0x1_F6D2),
+    (0x1_F6D5, 0x1_F6D7),
+    (0x1_F6EB, 0x1_F6EC),
+    (0x1_F6F4, 0x1_F6FC),
+    (0x1_F7E0, 0x1_F7EB),
+    (0x1_F90C, 0x1_F93A),
+    (0x1_F93C, 0x1_F945),
+    (0x1_F947, 0x1_F9FF),
+    (0x1_FA70, 0x1_FAFF),
+    (0x2_0000, 0x2_FFFD),
+    (0x3_0000, 0x3_FFFD),
+];
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+const EMOJI_PRESENTATION_SELECTOR: char = '\u{FE0F}';
+
+/// A grapheme cluster, given as a byte range within its line, and the cells it takes up on screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Grapheme {
+    pub start: usize,
+    pub end: usize,
+    pub width: usize,
+}
+
+/// Splits text into grapheme clusters, i.e. the characters a user perceives.
+///
+/// This covers combining marks, emoji sequences and regional indicator pairs, but is not a
+/// complete implementation of the Unicode segmentation rules.
+pub fn split_graphemes(text: &str) -> Vec<Grapheme> {
+    let mut graphemes = Vec::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, ch)) = chars.next() {
+        let mut previous = ch;
+        let mut regional_indicators = usize::from(is_regional_indicator(ch));
+
+        while let Some(&(_, next)) = chars.peek() {
+            let joins = is_extending(next)
+                || previous == ZERO_WIDTH_JOINER
+                || (previous == '\r' && next == '\n')
+                || (regional_indicators == 1 && is_regional_indicator(next));
+
+            if !joins {
+                break;
+            }
+
+            regional_indicators += usize::from(is_regional_indicator(next));
+            previous = next;
+            chars.next();
+        }
+
+        let end = chars.peek().map_or(text.len(), |(idx, _)| *idx);
+        graphemes.push(Grapheme {
+            start,
+            end,
+            width: grapheme_width(&text[start..end]),
+        });
+    }
+
+    graphemes
+}
+
+/// Returns the number of cells a grapheme cluster takes up in a terminal.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    let mut chars = grapheme.chars();
+    let Some(first) = chars.next() else {
+        return 0;
+    };
+
+    let is_flag = is_regional_indicator(first) && chars.next().is_some();
+    if is_flag || grapheme.contains(EMOJI_PRESENTATION_SELECTOR) || is_in_ranges(first, WIDE_RANGES)
+    {
+        2
+    } else {
+        1
+    }
+}
+
+/// Returns whether the character extends the preceding grapheme cluster.
+fn is_extending(ch: char) -> bool {
+    is_in_ranges(ch, EXTENDING_RANGES)
+}
+
+/// Returns whether the character lies in one of the sorted, inclusive ranges.
+fn is_in_ranges(ch: char, ranges: &[(u32, u32)]) -> bool {
+    let code = u32::from(ch);
+
+    ranges
+        .binary_search_by(|(start, end)| {
+            if code < *start {
+                Ordering::Greater
+            } else if code > *end {
+                Ordering::Less
+            } else {
+                Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Returns whether the character is a regional indicator, two of which form a flag.
+fn is_regional_indicator(ch: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&ch)
+}